@@ -1,8 +1,11 @@
 
 use crate::bus::Bus;
 use crate::registers::Registers;
+use crate::instruction::{Access, Instruction};
+use crate::execution_mode::ExecutionMode;
+use crate::variant::Variant;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum AddressingMode {
     Accumulator,
     Absolute,
@@ -11,139 +14,244 @@ pub enum AddressingMode {
     Immediate,
     Implied,
     Indirect,
+    /// `(abs,X)`, only used by JMP on the 65C02.
+    AbsoluteXIndexedIndirect,
     XIndexedIndirect,
     IndirectYIndexed,
     Relative,
     Zeropage,
     ZeropageXIndexed,
     ZeropageYIndexed,
+    /// `(zp)`, added by the 65C02.
+    ZeropageIndirect,
+    /// `zp,rel`, used by the Rockwell BBR and BBS instructions.
+    ZeropageRelative,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Operand {
     Addr(u16),
     Value(u8),
     Accumulator,
+    /// Zero page address to test and branch target of BBR and BBS.
+    ZeropageRelative(u16, u16),
     None
 }
 
 impl AddressingMode {
-    pub fn get_operand<T: Bus>(&self, bus: &mut T, regs: &mut Registers) -> Operand {
+    ///
+    /// Returns the number of operand bytes following the opcode.
+    ///
+    pub fn operand_length(&self) -> u16 {
+        match self {
+            AddressingMode::Accumulator | AddressingMode::Implied => 0,
+            AddressingMode::Immediate | AddressingMode::Relative | AddressingMode::Zeropage |
+            AddressingMode::ZeropageXIndexed | AddressingMode::ZeropageYIndexed |
+            AddressingMode::ZeropageIndirect | AddressingMode::XIndexedIndirect |
+            AddressingMode::IndirectYIndexed => 1,
+            AddressingMode::Absolute | AddressingMode::AbsoluteXIndexed | AddressingMode::AbsoluteYIndexed |
+            AddressingMode::Indirect | AddressingMode::AbsoluteXIndexedIndirect |
+            AddressingMode::ZeropageRelative => 2,
+        }
+    }
+
+    ///
+    /// Fetches the operand of the current instruction. The second element of
+    /// the result is true when indexing moved the address to a different
+    /// page, which costs an extra cycle for instructions that read memory.
+    ///
+    /// In cycle accurate mode, the dummy accesses the chip issues while
+    /// computing the address are also performed on the bus. Those depend on
+    /// how the instruction accesses the operand.
+    ///
+    /// The CMOS variants fix the page wrap of indirect jumps at the cost of
+    /// an extra cycle.
+    ///
+    pub fn get_operand<T: Bus>(&self, bus: &mut T, regs: &mut Registers, instruction: Instruction, variant: Variant, mode: ExecutionMode) -> (Operand, bool) {
+        let access = instruction.access();
         match self {
             AddressingMode::Accumulator => {
-                Operand::Accumulator
+                self.dummy_read(bus, regs.program_counter, mode);
+                (Operand::Accumulator, false)
+            },
+            AddressingMode::Absolute if access == Access::Subroutine => {
+                (Operand::Addr(self.get_subroutine_address(bus, regs, mode)), false)
             },
             AddressingMode::Absolute => {
-                Operand::Addr(self.get_absolute_address(bus, regs))
+                (Operand::Addr(self.get_absolute_address(bus, regs)), false)
             },
             AddressingMode::AbsoluteXIndexed => {
-                Operand::Addr(self.get_absolute_address(bus, regs).wrapping_add(regs.x_index as u16))
+                let base = self.get_absolute_address(bus, regs);
+                self.index_address(bus, regs, base, regs.x_index, instruction, variant, mode)
             },
             AddressingMode::AbsoluteYIndexed => {
-                Operand::Addr(self.get_absolute_address(bus, regs).wrapping_add(regs.y_index as u16))
+                let base = self.get_absolute_address(bus, regs);
+                self.index_address(bus, regs, base, regs.y_index, instruction, variant, mode)
             },
             AddressingMode::Immediate => {
-                Operand::Value(self.get_immediate_value(bus, regs))
+                (Operand::Value(self.get_immediate_value(bus, regs)), false)
             },
             AddressingMode::Implied => {
-                Operand::None
+                self.dummy_read(bus, regs.program_counter, mode);
+                (Operand::None, false)
             },
             AddressingMode::Indirect => {
-                Operand::Addr(self.get_indirect_address(bus, regs))
+                (Operand::Addr(self.get_indirect_address(bus, regs, variant, mode)), false)
+            },
+            AddressingMode::AbsoluteXIndexedIndirect => {
+                (Operand::Addr(self.get_absolute_x_indexed_indirect_address(bus, regs, mode)), false)
             },
             AddressingMode::XIndexedIndirect => {
-                Operand::Addr(self.get_x_indexed_indirect_address(bus, regs))
+                (Operand::Addr(self.get_x_indexed_indirect_address(bus, regs, mode)), false)
             },
             AddressingMode::IndirectYIndexed => {
-                Operand::Addr(self.get_indirect_y_indexed_address(bus, regs))
+                let base = self.get_zeropage_indirect_address(bus, regs);
+                self.index_address(bus, regs, base, regs.y_index, instruction, variant, mode)
             },
             AddressingMode::Relative => {
-                Operand::Addr(self.get_relative_addr(bus, regs))
+                (Operand::Addr(self.get_relative_addr(bus, regs)), false)
             },
             AddressingMode::Zeropage => {
-                Operand::Addr(self.get_zeropage_addr(bus, regs))
+                (Operand::Addr(self.get_zeropage_addr(bus, regs)), false)
             },
             AddressingMode::ZeropageXIndexed => {
-                Operand::Addr(self.get_zeropage_x_indexed_addr(bus, regs))
+                (Operand::Addr(self.get_zeropage_x_indexed_addr(bus, regs, mode)), false)
             },
             AddressingMode::ZeropageYIndexed => {
-                Operand::Addr(self.get_zeropage_y_indexed_addr(bus, regs))
+                (Operand::Addr(self.get_zeropage_y_indexed_addr(bus, regs, mode)), false)
+            },
+            AddressingMode::ZeropageIndirect => {
+                (Operand::Addr(self.get_zeropage_indirect_address(bus, regs)), false)
+            },
+            AddressingMode::ZeropageRelative => {
+                let addr = self.get_zeropage_addr(bus, regs);
+                (Operand::ZeropageRelative(addr, self.get_relative_addr(bus, regs)), false)
             }
         }
     }
 
+    fn dummy_read<T: Bus>(&self, bus: &mut T, addr: u16, mode: ExecutionMode) {
+        if mode.is_cycle_accurate() {
+            bus.read(addr);
+        }
+    }
+
+    ///
+    /// Adds the index to the base address. Fixing the high byte takes a
+    /// cycle, which is only skipped by the instructions with a page cross
+    /// penalty when no page is crossed. In that cycle, the NMOS chip reads
+    /// the address before the fix, and the CMOS chips read the last
+    /// instruction byte again.
+    ///
+    #[allow(clippy::too_many_arguments)]
+    fn index_address<T: Bus>(&self, bus: &mut T, regs: &Registers, base: u16, index: u8, instruction: Instruction, variant: Variant, mode: ExecutionMode) -> (Operand, bool) {
+        let addr = base.wrapping_add(index as u16);
+        let page_crossed = (base & 0xFF00) != (addr & 0xFF00);
+        if page_crossed || !instruction.has_page_cross_penalty(variant) {
+            let dummy_addr = if variant.is_cmos() {
+                regs.program_counter.wrapping_sub(1)
+            } else {
+                (base & 0xFF00) | (addr & 0x00FF)
+            };
+            self.dummy_read(bus, dummy_addr, mode);
+        }
+        (Operand::Addr(addr), page_crossed)
+    }
+
+    ///
+    /// JSR pushes the return address in between fetching the two bytes of
+    /// the subroutine address, after reading the top of the stack.
+    ///
+    fn get_subroutine_address<T: Bus>(&self, bus: &mut T, regs: &mut Registers, mode: ExecutionMode) -> u16 {
+        let lo : u16 = bus.read(regs.program_counter).into();
+        regs.program_counter = regs.program_counter.wrapping_add(1);
+        self.dummy_read(bus, regs.stack.address(), mode);
+        regs.stack.push_address(regs.program_counter, bus);
+        let hi : u16 = bus.read(regs.program_counter).into();
+        regs.program_counter = regs.program_counter.wrapping_add(1);
+        hi << 8 | lo
+    }
+
     fn get_absolute_address<T: Bus>(&self, bus: &mut T, regs: &mut Registers) -> u16 {
         let lo : u16 = bus.read(regs.program_counter).into();
-        regs.program_counter += 1;
+        regs.program_counter = regs.program_counter.wrapping_add(1);
         let hi : u16 = bus.read(regs.program_counter).into();
-        regs.program_counter += 1;
+        regs.program_counter = regs.program_counter.wrapping_add(1);
         hi << 8 | lo
     }
 
     fn get_immediate_value<T: Bus>(&self, bus: &mut T, regs: &mut Registers) -> u8 {
         let val = bus.read(regs.program_counter);
-        regs.program_counter += 1;
+        regs.program_counter = regs.program_counter.wrapping_add(1);
         val
     }
 
-    fn get_indirect_address<T: Bus>(&self, bus: &mut T, regs: &mut Registers) -> u16{
-        let lo : u16 = bus.read(regs.program_counter).into();
-        regs.program_counter += 1;
-        let hi : u16 = bus.read(regs.program_counter).into();
-        regs.program_counter += 1;
-
-        let ptr: u16 = hi << 8 | lo;
-        // The pointer wraps around in a page boundary
-        let ptr_plus_1 = match lo {
-            0xff => hi << 8,
-            _ => ptr + 1
+    fn get_indirect_address<T: Bus>(&self, bus: &mut T, regs: &mut Registers, variant: Variant, mode: ExecutionMode) -> u16{
+        let ptr = self.get_absolute_address(bus, regs);
+        let ptr_plus_1 = if variant.is_cmos() {
+            // The extra cycle is spent carrying into the high byte.
+            self.dummy_read(bus, regs.program_counter.wrapping_sub(1), mode);
+            ptr.wrapping_add(1)
+        } else {
+            // The pointer wraps around in a page boundary
+            (ptr & 0xFF00) | (ptr.wrapping_add(1) & 0x00FF)
         };
         let lo: u16 = bus.read(ptr).into();
         let hi: u16 = bus.read(ptr_plus_1).into();
         hi << 8 | lo
     }
 
-    fn get_x_indexed_indirect_address<T: Bus>(&self, bus: &mut T, regs: &mut Registers) -> u16 {
-        let lo : u16 = bus.read(regs.program_counter).into();
-        regs.program_counter += 1;
-        let hi : u16 = bus.read(regs.program_counter).into();
-        regs.program_counter += 1;
-
-        let ptr: u16 = (hi << 8 | lo) + regs.x_index as u16;
-        // The pointer wraps around in a page boundary
-        let ptr_plus_1 = match lo {
-            0xff => hi << 8,
-            _ => ptr + 1
-        };
+    fn get_absolute_x_indexed_indirect_address<T: Bus>(&self, bus: &mut T, regs: &mut Registers, mode: ExecutionMode) -> u16 {
+        let base = self.get_absolute_address(bus, regs);
+        // The high byte of the base address is read again while X is added.
+        self.dummy_read(bus, regs.program_counter.wrapping_sub(1), mode);
+        let ptr = base.wrapping_add(regs.x_index as u16);
         let lo: u16 = bus.read(ptr).into();
-        let hi: u16 = bus.read(ptr_plus_1).into();
+        let hi: u16 = bus.read(ptr.wrapping_add(1)).into();
+        hi << 8 | lo
+    }
+
+    fn read_zeropage_pointer<T: Bus>(&self, bus: &mut T, ptr: u8) -> u16 {
+        // The pointer wraps around in the zero page
+        let lo: u16 = bus.read(ptr as u16).into();
+        let hi: u16 = bus.read(ptr.wrapping_add(1) as u16).into();
         hi << 8 | lo
     }
 
-    fn get_indirect_y_indexed_address<T: Bus>(&self, bus: &mut T, regs: &mut Registers) -> u16 {
-        self.get_indirect_address(bus, regs) + regs.y_index as u16
+    fn get_x_indexed_indirect_address<T: Bus>(&self, bus: &mut T, regs: &mut Registers, mode: ExecutionMode) -> u16 {
+        let ptr = self.get_zeropage_x_indexed_addr(bus, regs, mode) as u8;
+        self.read_zeropage_pointer(bus, ptr)
+    }
+
+    fn get_zeropage_indirect_address<T: Bus>(&self, bus: &mut T, regs: &mut Registers) -> u16 {
+        let ptr = self.get_zeropage_addr(bus, regs) as u8;
+        self.read_zeropage_pointer(bus, ptr)
     }
 
     fn get_relative_addr<T: Bus>(&self, bus: &mut T, regs: &mut Registers) -> u16 {
         let relative_immediate = bus.read(regs.program_counter) as i8;
-        regs.program_counter += 1;
+        regs.program_counter = regs.program_counter.wrapping_add(1);
         regs.program_counter.wrapping_add(relative_immediate as u16)
     }
 
     fn get_zeropage_addr<T: Bus>(&self, bus: &mut T, regs: &mut Registers) -> u16 {
         let zeropage_addr = bus.read(regs.program_counter) as u16;
-        regs.program_counter += 1;
+        regs.program_counter = regs.program_counter.wrapping_add(1);
         zeropage_addr
     }
 
-    fn get_zeropage_x_indexed_addr<T: Bus>(&self, bus: &mut T, regs: &mut Registers) -> u16 {
+    fn get_zeropage_x_indexed_addr<T: Bus>(&self, bus: &mut T, regs: &mut Registers, mode: ExecutionMode) -> u16 {
         let zeropage_addr = self.get_zeropage_addr(bus, regs) as u8;
+        // The base address is read while the index is added.
+        self.dummy_read(bus, zeropage_addr as u16, mode);
         let addr = zeropage_addr.wrapping_add(regs.x_index);
         addr as u16
     }
 
-    fn get_zeropage_y_indexed_addr<T: Bus>(&self, bus: &mut T, regs: &mut Registers) -> u16 {
+    fn get_zeropage_y_indexed_addr<T: Bus>(&self, bus: &mut T, regs: &mut Registers, mode: ExecutionMode) -> u16 {
         let zeropage_addr = self.get_zeropage_addr(bus, regs) as u8;
+        // The base address is read while the index is added.
+        self.dummy_read(bus, zeropage_addr as u16, mode);
         let addr = zeropage_addr.wrapping_add(regs.y_index);
         addr as u16
     }
@@ -161,7 +269,7 @@ mod test {
         let mut regs = Registers::new();
         regs.accumulator = 123;
 
-        assert_eq!(am.get_operand(&mut bus, &mut regs), Operand::Value(123));
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Nmos6502, ExecutionMode::Fast), (Operand::Accumulator, false));
     }
 
     #[test]
@@ -173,7 +281,7 @@ mod test {
         bus.write(0x1234, 0x23u8);
         bus.write(0x1235, 0x34u8);
 
-        assert_eq!(am.get_operand(&mut bus, &mut regs), Operand::Addr(0x3423));
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Nmos6502, ExecutionMode::Fast), (Operand::Addr(0x3423), false));
     }
 
     #[test]
@@ -186,7 +294,7 @@ mod test {
         bus.write(0x1234, 0x23u8);
         bus.write(0x1235, 0x34u8);
 
-        assert_eq!(am.get_operand(&mut bus, &mut regs), Operand::Addr(0x342F));
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Nmos6502, ExecutionMode::Fast), (Operand::Addr(0x342F), false));
     }
 
     #[test]
@@ -199,7 +307,20 @@ mod test {
         bus.write(0x1234, 0x23u8);
         bus.write(0x1235, 0x34u8);
 
-        assert_eq!(am.get_operand(&mut bus, &mut regs), Operand::Addr(0x342F));
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Nmos6502, ExecutionMode::Fast), (Operand::Addr(0x342F), false));
+    }
+
+    #[test]
+    fn test_absolute_x_index_page_crossing() {
+        let am = AddressingMode::AbsoluteXIndexed;
+        let mut bus = DummyBus::new();
+        let mut regs = Registers::new();
+        regs.program_counter = 0x1234;
+        regs.x_index = 0x10;
+        bus.write(0x1234, 0xF8u8);
+        bus.write(0x1235, 0x34u8);
+
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Nmos6502, ExecutionMode::Fast), (Operand::Addr(0x3508), true));
     }
 
     #[test]
@@ -210,7 +331,7 @@ mod test {
         regs.program_counter = 0x1234;
         bus.write(0x1234, 0x23u8);
 
-        assert_eq!(am.get_operand(&mut bus, &mut regs), Operand::Value(0x23));
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Nmos6502, ExecutionMode::Fast), (Operand::Value(0x23), false));
     }
 
     #[test]
@@ -219,7 +340,7 @@ mod test {
         let mut bus = DummyBus::new();
         let mut regs = Registers::new();
 
-        assert_eq!(am.get_operand(&mut bus, &mut regs), Operand::None);
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Nmos6502, ExecutionMode::Fast), (Operand::None, false));
     }
 
     #[test]
@@ -233,7 +354,52 @@ mod test {
         bus.write(0x6423, 0xFF);
         bus.write(0x6424, 0x2F);
 
-        assert_eq!(am.get_operand(&mut bus, &mut regs), Operand::Addr(0x2FFF));
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Nmos6502, ExecutionMode::Fast), (Operand::Addr(0x2FFF), false));
+    }
+
+    #[test]
+    fn test_indirect_addressing_mode_page_wrap() {
+        let am = AddressingMode::Indirect;
+        let mut bus = DummyBus::new();
+        let mut regs = Registers::new();
+        regs.program_counter = 0x1234;
+        bus.write(0x1234, 0xFF);
+        bus.write(0x1235, 0x64);
+        bus.write(0x64FF, 0x34);
+        bus.write(0x6400, 0x12);
+        bus.write(0x6500, 0x56);
+
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Nmos6502, ExecutionMode::Fast), (Operand::Addr(0x1234), false));
+    }
+
+    #[test]
+    fn test_indirect_addressing_mode_cmos() {
+        let am = AddressingMode::Indirect;
+        let mut bus = DummyBus::new();
+        let mut regs = Registers::new();
+        regs.program_counter = 0x1234;
+        bus.write(0x1234, 0xFF);
+        bus.write(0x1235, 0x64);
+        bus.write(0x64FF, 0x34);
+        bus.write(0x6400, 0x12);
+        bus.write(0x6500, 0x56);
+
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Wdc65c02, ExecutionMode::Fast), (Operand::Addr(0x5634), false));
+    }
+
+    #[test]
+    fn test_absolute_x_indexed_indirect_addressing_mode() {
+        let am = AddressingMode::AbsoluteXIndexedIndirect;
+        let mut bus = DummyBus::new();
+        let mut regs = Registers::new();
+        regs.program_counter = 0x1234;
+        regs.x_index = 0x04;
+        bus.write(0x1234, 0xFE);
+        bus.write(0x1235, 0x64);
+        bus.write(0x6502, 0x34);
+        bus.write(0x6503, 0x12);
+
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Jmp, Variant::Wdc65c02, ExecutionMode::Fast), (Operand::Addr(0x1234), false));
     }
 
     #[test]
@@ -244,11 +410,10 @@ mod test {
         regs.program_counter = 0x1234;
         regs.x_index = 0x20;
         bus.write(0x1234, 0x23);
-        bus.write(0x1235, 0x64);
-        bus.write(0x6443, 0xFF);
-        bus.write(0x6444, 0x2F);
+        bus.write(0x0043, 0xFF);
+        bus.write(0x0044, 0x2F);
 
-        assert_eq!(am.get_operand(&mut bus, &mut regs), Operand::Addr(0x2FFF));
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Nmos6502, ExecutionMode::Fast), (Operand::Addr(0x2FFF), false));
     }
 
     #[test]
@@ -259,11 +424,38 @@ mod test {
         regs.program_counter = 0x1234;
         regs.y_index = 0x20;
         bus.write(0x1234, 0x23);
-        bus.write(0x1235, 0x64);
-        bus.write(0x6423, 0x30);
-        bus.write(0x6424, 0x2F);
+        bus.write(0x0023, 0x30);
+        bus.write(0x0024, 0x2F);
+
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Nmos6502, ExecutionMode::Fast), (Operand::Addr(0x2F50), false));
+    }
+
+    #[test]
+    fn test_x_indexed_indirect_zeropage_wrap() {
+        let am = AddressingMode::XIndexedIndirect;
+        let mut bus = DummyBus::new();
+        let mut regs = Registers::new();
+        regs.program_counter = 0x1234;
+        regs.x_index = 0x10;
+        bus.write(0x1234, 0xEF);
+        bus.write(0x00FF, 0x34);
+        bus.write(0x0000, 0x12);
 
-        assert_eq!(am.get_operand(&mut bus, &mut regs), Operand::Addr(0x2F50));
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Nmos6502, ExecutionMode::Fast), (Operand::Addr(0x1234), false));
+    }
+
+    #[test]
+    fn test_indirect_y_indexed_page_crossing() {
+        let am = AddressingMode::IndirectYIndexed;
+        let mut bus = DummyBus::new();
+        let mut regs = Registers::new();
+        regs.program_counter = 0x1234;
+        regs.y_index = 0x20;
+        bus.write(0x1234, 0x23);
+        bus.write(0x0023, 0xF0);
+        bus.write(0x0024, 0x2F);
+
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Nmos6502, ExecutionMode::Fast), (Operand::Addr(0x3010), true));
     }
 
     #[test]
@@ -275,8 +467,33 @@ mod test {
         bus.write(0x1234, 0x23);
         bus.write(0x1235, -0x10i8 as u8);
 
-        assert_eq!(am.get_operand(&mut bus, &mut regs), Operand::Addr(0x1258));
-        assert_eq!(am.get_operand(&mut bus, &mut regs), Operand::Addr(0x1226));
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Nmos6502, ExecutionMode::Fast), (Operand::Addr(0x1258), false));
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Nmos6502, ExecutionMode::Fast), (Operand::Addr(0x1226), false));
+    }
+
+    #[test]
+    fn test_zeropage_indirect_addressing_mode() {
+        let am = AddressingMode::ZeropageIndirect;
+        let mut bus = DummyBus::new();
+        let mut regs = Registers::new();
+        regs.program_counter = 0x1234;
+        bus.write(0x1234, 0xFF);
+        bus.write(0x00FF, 0x34);
+        bus.write(0x0000, 0x12);
+
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Wdc65c02, ExecutionMode::Fast), (Operand::Addr(0x1234), false));
+    }
+
+    #[test]
+    fn test_zeropage_relative_addressing_mode() {
+        let am = AddressingMode::ZeropageRelative;
+        let mut bus = DummyBus::new();
+        let mut regs = Registers::new();
+        regs.program_counter = 0x1234;
+        bus.write(0x1234, 0x56);
+        bus.write(0x1235, -0x10i8 as u8);
+
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Jmp, Variant::Wdc65c02, ExecutionMode::Fast), (Operand::ZeropageRelative(0x56, 0x1226), false));
     }
 
     #[test]
@@ -288,7 +505,7 @@ mod test {
         bus.write(0x1234, 0x56);
         bus.write(0x1235, 0x52);
 
-        assert_eq!(am.get_operand(&mut bus, &mut regs), Operand::Addr(0x56));
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Nmos6502, ExecutionMode::Fast), (Operand::Addr(0x56), false));
     }
 
     #[test]
@@ -301,7 +518,7 @@ mod test {
         bus.write(0x1234, 0x56);
         bus.write(0x1235, 0x52);
 
-        assert_eq!(am.get_operand(&mut bus, &mut regs), Operand::Addr(0xD6));
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Nmos6502, ExecutionMode::Fast), (Operand::Addr(0xD6), false));
     }
 
     #[test]
@@ -314,6 +531,6 @@ mod test {
         bus.write(0x1234, 0x56);
         bus.write(0x1235, 0x52);
 
-        assert_eq!(am.get_operand(&mut bus, &mut regs), Operand::Addr(0xD6));
+        assert_eq!(am.get_operand(&mut bus, &mut regs, Instruction::Lda, Variant::Nmos6502, ExecutionMode::Fast), (Operand::Addr(0xD6), false));
     }
 }
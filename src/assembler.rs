@@ -0,0 +1,911 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::addressing_modes::AddressingMode;
+use crate::bus::Bus;
+use crate::error::{AssemblyError, AssemblyErrorKind};
+use crate::opcodes::{decode, OPCODES};
+use crate::variant::Variant;
+
+/// Alternative mnemonics of undocumented opcodes used by other assemblers.
+const ALIASES: [(&str, &str); 4] = [("ASR", "ALR"), ("AXS", "SBX"), ("ISB", "ISC"), ("DCM", "DCP")];
+
+/// Bytes assembled at consecutive addresses.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub origin: u16,
+    pub bytes: Vec<u8>,
+}
+
+/// Output of the `Assembler`: the machine code and the value of every
+/// symbol.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Assembly {
+    /// Code and data, a new segment starting at every `.org` that doesn't
+    /// follow the previous bytes.
+    pub segments: Vec<Segment>,
+    /// Labels and constants, predefined symbols included.
+    pub symbols: BTreeMap<String, u16>,
+}
+
+impl Assembly {
+    /// Returns the value of a symbol.
+    pub fn symbol(&self, name: &str) -> Option<u16> {
+        self.symbols.get(name).copied()
+    }
+
+    ///
+    /// Writes every segment to the bus.
+    ///
+    pub fn load<T: Bus>(&self, bus: &mut T) {
+        for segment in &self.segments {
+            for (offset, byte) in segment.bytes.iter().enumerate() {
+                bus.write(segment.origin.wrapping_add(offset as u16), *byte);
+            }
+        }
+    }
+}
+
+/// Two pass assembler for the instruction set of a `Variant`.
+///
+/// The source has one statement per line, with an optional `label:` in
+/// front and comments after `;`:
+///
+/// - instructions in standard syntax, `a:` forcing absolute addressing of
+///   zero page addresses;
+/// - `.org expr` or `* = expr` to set the address of what follows;
+/// - `.byte` and `.word` with lists of expressions, `.byte` also taking
+///   strings;
+/// - `name = expr` to define a constant.
+///
+/// Expressions are made of numbers (`$ff`, `%1010`, `255`, `'a'`),
+/// symbols, `*` for the address of the current line, the unary operators
+/// `-`, `~`, `<` (low byte) and `>` (high byte), the binary operators
+/// `+ - * / & | ^ << >>` and parentheses. Symbols can be used before they
+/// are defined; such operands take the absolute form.
+///
+/// # Example
+///```
+///    let assembler = mos6502::Assembler::new(mos6502::Variant::Nmos6502);
+///    let assembly = assembler.assemble("
+///        .org $0200
+///    loop:
+///        dex
+///        bne loop
+///        rts
+///    ").unwrap();
+///    assert_eq!(assembly.segments[0].bytes, [0xCA, 0xD0, 0xFD, 0x60]);
+///    assert_eq!(assembly.symbol("loop"), Some(0x0200));
+///```
+pub struct Assembler {
+    opcodes: HashMap<(String, AddressingMode), u8>,
+    symbols: HashMap<String, i64>,
+}
+
+impl Assembler {
+    ///
+    /// Constructs an Assembler for the opcodes of the given variant. Where
+    /// several opcodes share a mnemonic and addressing mode, the documented
+    /// NMOS one is used.
+    ///
+    pub fn new(variant: Variant) -> Assembler {
+        let mut opcodes = HashMap::new();
+        for opcode in 0..=255u8 {
+            if let Some((instruction, mode, _)) = decode(opcode, variant) {
+                let documented = OPCODES[opcode as usize].map(|(i, _, _)| i) == Some(instruction);
+                let key = (instruction.mnemonic(), mode);
+                if documented || !opcodes.contains_key(&key) {
+                    opcodes.insert(key, opcode);
+                }
+            }
+        }
+        Assembler { opcodes, symbols: HashMap::new() }
+    }
+
+    ///
+    /// Predefines a symbol, such as the address of a device register.
+    ///
+    pub fn define<S: Into<String>>(&mut self, name: S, value: u16) {
+        self.symbols.insert(name.into(), value as i64);
+    }
+
+    ///
+    /// Assembles the source, stopping at the first error.
+    ///
+    pub fn assemble(&self, source: &str) -> Result<Assembly, AssemblyError> {
+        let mut lines = Vec::new();
+        for (index, text) in source.lines().enumerate() {
+            let line = parse_line(text).map_err(|kind| AssemblyError { line: index + 1, kind })?;
+            lines.push(line);
+        }
+
+        let mut symbols = self.symbols.clone();
+        let (modes, addresses) = self.first_pass(&lines, &mut symbols)?;
+        resolve_constants(&lines, &addresses, &mut symbols)?;
+        let segments = self.second_pass(&lines, &modes, &symbols)?;
+
+        Ok(Assembly {
+            segments,
+            symbols: symbols.into_iter().map(|(name, value)| (name, value as u16)).collect(),
+        })
+    }
+
+    ///
+    /// Defines the labels and picks the addressing mode of every
+    /// instruction, which fixes its size. Returns the modes and the address
+    /// of every line.
+    ///
+    fn first_pass(&self, lines: &[Line], symbols: &mut HashMap<String, i64>) -> Result<(Vec<Option<AddressingMode>>, Vec<u16>), AssemblyError> {
+        let mut defined: HashSet<String> = symbols.keys().cloned().collect();
+        let mut modes = Vec::with_capacity(lines.len());
+        let mut addresses = Vec::with_capacity(lines.len());
+        let mut pc: u16 = 0;
+
+        for (index, line) in lines.iter().enumerate() {
+            let error = |kind| AssemblyError { line: index + 1, kind };
+            addresses.push(pc);
+
+            if let Some(label) = &line.label {
+                define(&mut defined, label).map_err(error)?;
+                symbols.insert(label.clone(), pc as i64);
+            }
+
+            let mut mode = None;
+            match &line.statement {
+                Statement::Empty => {},
+                Statement::Assign(name, expr) => {
+                    define(&mut defined, name).map_err(error)?;
+                    // Constants that depend on later labels are resolved after this pass.
+                    if let Some(value) = eval(expr, pc, symbols, false).map_err(error)? {
+                        symbols.insert(name.clone(), value);
+                    }
+                },
+                Statement::Org(expr) => {
+                    pc = expect_word(eval(expr, pc, symbols, true).map_err(error)?.unwrap()).map_err(error)?;
+                },
+                Statement::Data { word, items } => {
+                    let size: usize = items.iter().map(|item| match item {
+                        DataItem::Bytes(bytes) => bytes.len(),
+                        DataItem::Expr(_) => if *word { 2 } else { 1 },
+                    }).sum();
+                    pc = pc.wrapping_add(size as u16);
+                },
+                Statement::Instruction { mnemonic, operand, force_absolute } => {
+                    let value = match operand {
+                        Operand::Direct(expr) | Operand::XIndexed(expr) | Operand::YIndexed(expr) |
+                        Operand::Indirect(expr) | Operand::XIndexedIndirect(expr) => {
+                            eval(expr, pc, symbols, false).map_err(error)?
+                        },
+                        _ => None,
+                    };
+                    let selected = self.select_mode(mnemonic, operand, value, *force_absolute).map_err(error)?;
+                    pc = pc.wrapping_add(1 + selected.operand_length());
+                    mode = Some(selected);
+                },
+            }
+            modes.push(mode);
+        }
+        Ok((modes, addresses))
+    }
+
+    ///
+    /// Evaluates every expression, now that all labels are known, and
+    /// emits the bytes.
+    ///
+    fn second_pass(&self, lines: &[Line], modes: &[Option<AddressingMode>], symbols: &HashMap<String, i64>) -> Result<Vec<Segment>, AssemblyError> {
+        let mut output = Output { segments: Vec::new() };
+        let mut pc: u16 = 0;
+
+        for (index, line) in lines.iter().enumerate() {
+            let error = |kind| AssemblyError { line: index + 1, kind };
+            match &line.statement {
+                Statement::Empty | Statement::Assign(_, _) => {},
+                Statement::Org(expr) => {
+                    pc = expect_word(eval(expr, pc, symbols, true).map_err(error)?.unwrap()).map_err(error)?;
+                },
+                Statement::Data { word, items } => {
+                    for item in items {
+                        match item {
+                            DataItem::Bytes(bytes) => {
+                                for byte in bytes {
+                                    pc = output.emit(pc, *byte);
+                                }
+                            },
+                            DataItem::Expr(expr) => {
+                                let value = eval(expr, pc, symbols, true).map_err(error)?.unwrap();
+                                if *word {
+                                    let value = expect_word(value).map_err(error)?;
+                                    pc = output.emit(pc, value as u8);
+                                    pc = output.emit(pc, (value >> 8) as u8);
+                                } else {
+                                    pc = output.emit(pc, expect_byte(value).map_err(error)?);
+                                }
+                            },
+                        }
+                    }
+                },
+                Statement::Instruction { mnemonic, operand, .. } => {
+                    let mode = modes[index].unwrap();
+                    let start = pc;
+                    pc = output.emit(pc, self.opcodes[&(mnemonic.clone(), mode)]);
+                    let value = |expr: &Expr| eval(expr, start, symbols, true).map(Option::unwrap).map_err(error);
+
+                    match (operand, mode) {
+                        (Operand::None, _) | (Operand::Accumulator, _) => {},
+                        (Operand::Immediate(expr), _) => {
+                            pc = output.emit(pc, expect_byte(value(expr)?).map_err(error)?);
+                        },
+                        (Operand::Direct(expr), AddressingMode::Relative) => {
+                            let offset = value(expr)? - (start as i64 + 2);
+                            pc = output.emit(pc, expect_branch(offset).map_err(error)?);
+                        },
+                        (Operand::Pair(zeropage, target), _) => {
+                            pc = output.emit(pc, expect_zeropage(value(zeropage)?).map_err(error)?);
+                            let offset = value(target)? - (start as i64 + 3);
+                            pc = output.emit(pc, expect_branch(offset).map_err(error)?);
+                        },
+                        (Operand::Direct(expr), _) | (Operand::XIndexed(expr), _) | (Operand::YIndexed(expr), _) |
+                        (Operand::Indirect(expr), _) | (Operand::XIndexedIndirect(expr), _) |
+                        (Operand::IndirectYIndexed(expr), _) => {
+                            let value = value(expr)?;
+                            if mode.operand_length() == 1 {
+                                pc = output.emit(pc, expect_zeropage(value).map_err(error)?);
+                            } else {
+                                let value = expect_word(value).map_err(error)?;
+                                pc = output.emit(pc, value as u8);
+                                pc = output.emit(pc, (value >> 8) as u8);
+                            }
+                        },
+                    }
+                },
+            }
+        }
+        Ok(output.segments)
+    }
+
+    ///
+    /// Picks the addressing mode of an instruction. Zero page modes are
+    /// used when the address is known to fit, unless absolute addressing is
+    /// forced.
+    ///
+    fn select_mode(&self, mnemonic: &str, operand: &Operand, value: Option<i64>, force_absolute: bool) -> Result<AddressingMode, AssemblyErrorKind> {
+        let has = |mode| self.opcodes.contains_key(&(mnemonic.to_string(), mode));
+        if !self.opcodes.keys().any(|(name, _)| name == mnemonic) {
+            return Err(AssemblyErrorKind::UnknownMnemonic(mnemonic.to_string()));
+        }
+
+        let (zeropage, absolute) = match operand {
+            Operand::None if has(AddressingMode::Implied) => return Ok(AddressingMode::Implied),
+            Operand::None | Operand::Accumulator => (None, Some(AddressingMode::Accumulator)),
+            Operand::Immediate(_) => (None, Some(AddressingMode::Immediate)),
+            Operand::Direct(_) if has(AddressingMode::Relative) => (None, Some(AddressingMode::Relative)),
+            Operand::Direct(_) => (Some(AddressingMode::Zeropage), Some(AddressingMode::Absolute)),
+            Operand::XIndexed(_) => (Some(AddressingMode::ZeropageXIndexed), Some(AddressingMode::AbsoluteXIndexed)),
+            Operand::YIndexed(_) => (Some(AddressingMode::ZeropageYIndexed), Some(AddressingMode::AbsoluteYIndexed)),
+            Operand::Indirect(_) => (Some(AddressingMode::ZeropageIndirect), Some(AddressingMode::Indirect)),
+            Operand::XIndexedIndirect(_) => (Some(AddressingMode::XIndexedIndirect), Some(AddressingMode::AbsoluteXIndexedIndirect)),
+            Operand::IndirectYIndexed(_) => (Some(AddressingMode::IndirectYIndexed), None),
+            Operand::Pair(_, _) => (Some(AddressingMode::ZeropageRelative), None),
+        };
+        let zeropage = zeropage.filter(|mode| has(*mode));
+        let absolute = absolute.filter(|mode| has(*mode));
+
+        match (zeropage, absolute) {
+            (Some(zeropage), None) => Ok(zeropage),
+            (Some(zeropage), Some(_)) if !force_absolute && value.is_some_and(|v| (0..0x100).contains(&v)) => Ok(zeropage),
+            (_, Some(absolute)) => Ok(absolute),
+            (None, None) => Err(AssemblyErrorKind::InvalidAddressingMode(mnemonic.to_string())),
+        }
+    }
+}
+
+///
+/// Marks a symbol as defined, failing if it already was.
+///
+fn define(defined: &mut HashSet<String>, name: &str) -> Result<(), AssemblyErrorKind> {
+    if defined.insert(name.to_string()) {
+        Ok(())
+    } else {
+        Err(AssemblyErrorKind::DuplicateSymbol(name.to_string()))
+    }
+}
+
+///
+/// Evaluates the constants left unknown by the first pass, which can
+/// depend on each other in any order.
+///
+fn resolve_constants(lines: &[Line], addresses: &[u16], symbols: &mut HashMap<String, i64>) -> Result<(), AssemblyError> {
+    loop {
+        let mut progress = false;
+        let mut pending = None;
+        for (index, line) in lines.iter().enumerate() {
+            if let Statement::Assign(name, expr) = &line.statement {
+                if symbols.contains_key(name) {
+                    continue;
+                }
+                let error = |kind| AssemblyError { line: index + 1, kind };
+                match eval(expr, addresses[index], symbols, false).map_err(error)? {
+                    Some(value) => {
+                        symbols.insert(name.clone(), value);
+                        progress = true;
+                    },
+                    None => pending = pending.or(Some(index)),
+                }
+            }
+        }
+        match pending {
+            None => return Ok(()),
+            Some(index) if !progress => {
+                // Report the symbol that can't be found.
+                if let Statement::Assign(_, expr) = &lines[index].statement {
+                    eval(expr, addresses[index], symbols, true).map_err(|kind| AssemblyError { line: index + 1, kind })?;
+                }
+                return Ok(());
+            },
+            Some(_) => {},
+        }
+    }
+}
+
+/// Segments being emitted by the second pass.
+struct Output {
+    segments: Vec<Segment>,
+}
+
+impl Output {
+    ///
+    /// Appends a byte at the given address and returns the next address.
+    ///
+    fn emit(&mut self, pc: u16, byte: u8) -> u16 {
+        match self.segments.last_mut() {
+            Some(segment) if segment.origin.wrapping_add(segment.bytes.len() as u16) == pc => {
+                segment.bytes.push(byte);
+            },
+            _ => self.segments.push(Segment { origin: pc, bytes: vec![byte] }),
+        }
+        pc.wrapping_add(1)
+    }
+}
+
+fn expect_byte(value: i64) -> Result<u8, AssemblyErrorKind> {
+    if (-0x80..0x100).contains(&value) {
+        Ok(value as u8)
+    } else {
+        Err(AssemblyErrorKind::ValueOutOfRange(value))
+    }
+}
+
+fn expect_zeropage(value: i64) -> Result<u8, AssemblyErrorKind> {
+    if (0..0x100).contains(&value) {
+        Ok(value as u8)
+    } else {
+        Err(AssemblyErrorKind::ValueOutOfRange(value))
+    }
+}
+
+fn expect_word(value: i64) -> Result<u16, AssemblyErrorKind> {
+    if (-0x8000..0x10000).contains(&value) {
+        Ok(value as u16)
+    } else {
+        Err(AssemblyErrorKind::ValueOutOfRange(value))
+    }
+}
+
+fn expect_branch(offset: i64) -> Result<u8, AssemblyErrorKind> {
+    if (-0x80..0x80).contains(&offset) {
+        Ok(offset as u8)
+    } else {
+        Err(AssemblyErrorKind::BranchOutOfRange(offset))
+    }
+}
+
+struct Line {
+    label: Option<String>,
+    statement: Statement,
+}
+
+enum Statement {
+    Empty,
+    Org(Expr),
+    Assign(String, Expr),
+    Data { word: bool, items: Vec<DataItem> },
+    Instruction { mnemonic: String, operand: Operand, force_absolute: bool },
+}
+
+enum DataItem {
+    Expr(Expr),
+    Bytes(Vec<u8>),
+}
+
+/// Operand syntax, before the addressing mode is picked.
+enum Operand {
+    None,
+    Accumulator,
+    Immediate(Expr),
+    Direct(Expr),
+    XIndexed(Expr),
+    YIndexed(Expr),
+    Indirect(Expr),
+    XIndexedIndirect(Expr),
+    IndirectYIndexed(Expr),
+    /// `zp,target` of BBR and BBS.
+    Pair(Expr, Expr),
+}
+
+enum Expr {
+    Number(i64),
+    Symbol(String),
+    /// `*`, the address of the current line.
+    Pc,
+    Unary(char, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+}
+
+///
+/// Evaluates an expression. Undefined symbols make the value unknown, or
+/// fail if `strict` is set.
+///
+fn eval(expr: &Expr, pc: u16, symbols: &HashMap<String, i64>, strict: bool) -> Result<Option<i64>, AssemblyErrorKind> {
+    Ok(match expr {
+        Expr::Number(value) => Some(*value),
+        Expr::Pc => Some(pc as i64),
+        Expr::Symbol(name) => match symbols.get(name) {
+            Some(value) => Some(*value),
+            None if strict => return Err(AssemblyErrorKind::UndefinedSymbol(name.clone())),
+            None => None,
+        },
+        Expr::Unary(op, operand) => eval(operand, pc, symbols, strict)?.map(|value| match op {
+            '-' => -value,
+            '~' => !value,
+            '<' => value & 0xFF,
+            _ => (value >> 8) & 0xFF,
+        }),
+        Expr::Binary(op, left, right) => {
+            let left = eval(left, pc, symbols, strict)?;
+            let right = eval(right, pc, symbols, strict)?;
+            match (left, right) {
+                (Some(left), Some(right)) => Some(match *op {
+                    "+" => left.wrapping_add(right),
+                    "-" => left.wrapping_sub(right),
+                    "*" => left.wrapping_mul(right),
+                    "/" if right == 0 => return Err(AssemblyErrorKind::Syntax("division by zero".to_string())),
+                    "/" => left / right,
+                    "&" => left & right,
+                    "|" => left | right,
+                    "^" => left ^ right,
+                    "<<" => left.wrapping_shl(right as u32),
+                    _ => left.wrapping_shr(right as u32),
+                }),
+                _ => None,
+            }
+        },
+    })
+}
+
+fn syntax_error<T>(message: &str) -> Result<T, AssemblyErrorKind> {
+    Err(AssemblyErrorKind::Syntax(message.to_string()))
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+///
+/// Removes the comment at the end of a line, ignoring `;` in quotes.
+///
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    for (index, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {},
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == ';' => return &text[..index],
+            None => {},
+        }
+    }
+    text
+}
+
+///
+/// Splits a list on the commas that are not in parentheses or quotes.
+///
+fn split_list(text: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut depth = 0;
+    let mut quote = None;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {},
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    items.push(text[start..index].trim());
+                    start = index + 1;
+                },
+                _ => {},
+            },
+        }
+    }
+    items.push(text[start..].trim());
+    items
+}
+
+fn parse_line(text: &str) -> Result<Line, AssemblyErrorKind> {
+    let mut text = strip_comment(text).trim();
+    let mut label = None;
+    if let Some(index) = text.find(':') {
+        if is_identifier(&text[..index]) {
+            label = Some(text[..index].to_string());
+            text = text[index + 1..].trim();
+        }
+    }
+    Ok(Line { label, statement: parse_statement(text)? })
+}
+
+fn parse_statement(text: &str) -> Result<Statement, AssemblyErrorKind> {
+    if text.is_empty() {
+        return Ok(Statement::Empty);
+    }
+    let (word, rest) = match text.find(char::is_whitespace) {
+        Some(index) => (&text[..index], text[index..].trim()),
+        None => (text, ""),
+    };
+
+    if let Some(value) = text.strip_prefix('*').map(str::trim).and_then(|rest| rest.strip_prefix('=')) {
+        return Ok(Statement::Org(parse_expr(value)?));
+    }
+    if let Some(value) = rest.strip_prefix('=') {
+        if !is_identifier(word) {
+            return syntax_error("invalid symbol name");
+        }
+        return Ok(Statement::Assign(word.to_string(), parse_expr(value)?));
+    }
+
+    match word.to_ascii_lowercase().as_str() {
+        ".org" => Ok(Statement::Org(parse_expr(rest)?)),
+        ".byte" | ".word" => {
+            let word = word.eq_ignore_ascii_case(".word");
+            let mut items = Vec::new();
+            for item in split_list(rest) {
+                match item.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+                    Some(string) if !word => items.push(DataItem::Bytes(string.bytes().collect())),
+                    _ => items.push(DataItem::Expr(parse_expr(item)?)),
+                }
+            }
+            Ok(Statement::Data { word, items })
+        },
+        directive if directive.starts_with('.') => syntax_error(&format!("unknown directive `{}`", word)),
+        _ => {
+            let mut mnemonic = word.to_ascii_uppercase();
+            let mut force_absolute = false;
+            if let Some(stripped) = mnemonic.strip_suffix("+2") {
+                mnemonic = stripped.to_string();
+                force_absolute = true;
+            }
+            if let Some((_, name)) = ALIASES.iter().find(|(alias, _)| *alias == mnemonic) {
+                mnemonic = name.to_string();
+            }
+            let mut operand = rest;
+            if operand.len() > 2 && operand[..2].eq_ignore_ascii_case("a:") {
+                operand = &operand[2..];
+                force_absolute = true;
+            }
+            Ok(Statement::Instruction { mnemonic, operand: parse_operand(operand)?, force_absolute })
+        },
+    }
+}
+
+fn parse_operand(text: &str) -> Result<Operand, AssemblyErrorKind> {
+    if text.is_empty() {
+        return Ok(Operand::None);
+    }
+    if text.eq_ignore_ascii_case("a") {
+        return Ok(Operand::Accumulator);
+    }
+    if let Some(value) = text.strip_prefix('#') {
+        return Ok(Operand::Immediate(parse_expr(value)?));
+    }
+
+    if text.starts_with('(') {
+        if let Some(close) = matching_paren(text) {
+            let inner = &text[1..close];
+            let after = text[close + 1..].replace(char::is_whitespace, "");
+            let items = split_list(inner);
+            if after.is_empty() && items.len() == 2 && items[1].eq_ignore_ascii_case("x") {
+                return Ok(Operand::XIndexedIndirect(parse_expr(items[0])?));
+            }
+            if after.is_empty() {
+                return Ok(Operand::Indirect(parse_expr(inner)?));
+            }
+            if after.eq_ignore_ascii_case(",y") {
+                return Ok(Operand::IndirectYIndexed(parse_expr(inner)?));
+            }
+        }
+    }
+
+    let items = split_list(text);
+    match items.as_slice() {
+        [value] => Ok(Operand::Direct(parse_expr(value)?)),
+        [value, index] if index.eq_ignore_ascii_case("x") => Ok(Operand::XIndexed(parse_expr(value)?)),
+        [value, index] if index.eq_ignore_ascii_case("y") => Ok(Operand::YIndexed(parse_expr(value)?)),
+        [zeropage, target] => Ok(Operand::Pair(parse_expr(zeropage)?, parse_expr(target)?)),
+        _ => syntax_error("invalid operand"),
+    }
+}
+
+///
+/// Returns the index of the parenthesis closing the one at the start.
+///
+fn matching_paren(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            },
+            _ => {},
+        }
+    }
+    None
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(i64),
+    Symbol(String),
+    Op(&'static str),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, AssemblyErrorKind> {
+    const OPERATORS: [&str; 14] = ["<<", ">>", "+", "-", "*", "/", "&", "|", "^", "~", "<", ">", "(", ")"];
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+        let rest: String = chars[index..].iter().collect();
+        if c.is_whitespace() {
+            index += 1;
+        } else if c == '$' || c == '%' || c.is_ascii_digit() {
+            let (radix, start) = match c {
+                '$' => (16, index + 1),
+                '%' => (2, index + 1),
+                _ => (10, index),
+            };
+            let mut end = start;
+            while end < chars.len() && chars[end].is_ascii_alphanumeric() {
+                end += 1;
+            }
+            let digits: String = chars[start..end].iter().collect();
+            let value = i64::from_str_radix(&digits, radix)
+                .or_else(|_| syntax_error(&format!("invalid number `{}`", &rest[..end - index])))?;
+            tokens.push(Token::Number(value));
+            index = end;
+        } else if c == '\'' {
+            match (chars.get(index + 1), chars.get(index + 2)) {
+                (Some(value), Some('\'')) => tokens.push(Token::Number(*value as i64)),
+                _ => return syntax_error("invalid character literal"),
+            }
+            index += 3;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut end = index;
+            while end < chars.len() && (chars[end].is_ascii_alphanumeric() || chars[end] == '_') {
+                end += 1;
+            }
+            tokens.push(Token::Symbol(chars[index..end].iter().collect()));
+            index = end;
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            index += op.len();
+        } else {
+            return syntax_error(&format!("unexpected `{}`", c));
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_expr(text: &str) -> Result<Expr, AssemblyErrorKind> {
+    let tokens = tokenize(text)?;
+    if tokens.is_empty() {
+        return syntax_error("missing expression");
+    }
+    let mut parser = Parser { tokens, position: 0 };
+    let expr = parser.binary(0)?;
+    if parser.position != parser.tokens.len() {
+        return syntax_error(&format!("invalid expression `{}`", text.trim()));
+    }
+    Ok(expr)
+}
+
+/// Recursive descent parser of expressions.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    /// Binary operators, from the lowest to the highest precedence.
+    const LEVELS: [&'static [&'static str]; 6] = [&["|"], &["^"], &["&"], &["<<", ">>"], &["+", "-"], &["*", "/"]];
+
+    fn binary(&mut self, level: usize) -> Result<Expr, AssemblyErrorKind> {
+        if level == Parser::LEVELS.len() {
+            return self.unary();
+        }
+        let mut expr = self.binary(level + 1)?;
+        while let Some(Token::Op(op)) = self.tokens.get(self.position) {
+            let op: &'static str = op;
+            if !Parser::LEVELS[level].contains(&op) {
+                break;
+            }
+            self.position += 1;
+            let right = self.binary(level + 1)?;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, AssemblyErrorKind> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        match token {
+            Some(Token::Number(value)) => Ok(Expr::Number(value)),
+            Some(Token::Symbol(name)) => Ok(Expr::Symbol(name)),
+            Some(Token::Op("*")) => Ok(Expr::Pc),
+            Some(Token::Op("(")) => {
+                let expr = self.binary(0)?;
+                if self.tokens.get(self.position) != Some(&Token::Op(")")) {
+                    return syntax_error("missing `)`");
+                }
+                self.position += 1;
+                Ok(expr)
+            },
+            Some(Token::Op(op)) if ["-", "~", "<", ">"].contains(&op) => {
+                Ok(Expr::Unary(op.chars().next().unwrap(), Box::new(self.unary()?)))
+            },
+            _ => syntax_error("invalid expression"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cpu;
+    use crate::bus::tests::DummyBus;
+
+    fn assemble(variant: Variant, source: &str) -> Vec<u8> {
+        let assembly = Assembler::new(variant).assemble(source).unwrap();
+        assert_eq!(assembly.segments.len(), 1);
+        assembly.segments[0].bytes.clone()
+    }
+
+    fn error(source: &str) -> AssemblyError {
+        Assembler::new(Variant::Nmos6502).assemble(source).unwrap_err()
+    }
+
+    #[test]
+    fn test_addressing_modes() {
+        let bytes = assemble(Variant::Nmos6502, "
+            lda #$12
+            lda $34,x
+            ldx $34,Y
+            lda $1234
+            lda $12,y       ; no zero page form
+            lda a:$12
+            lda ($20,X)
+            lda ($20),y
+            jmp ($fffc)
+            asl a
+            lsr
+            nop
+        ");
+        assert_eq!(bytes, [
+            0xA9, 0x12, 0xB5, 0x34, 0xB6, 0x34, 0xAD, 0x34, 0x12, 0xB9, 0x12, 0x00,
+            0xAD, 0x12, 0x00, 0xA1, 0x20, 0xB1, 0x20, 0x6C, 0xFC, 0xFF, 0x0A, 0x4A, 0xEA,
+        ]);
+    }
+
+    #[test]
+    fn test_labels_and_forward_references() {
+        let assembly = Assembler::new(Variant::Nmos6502).assemble("
+            * = $C000
+            start:  ldx #count
+            loop:   lda table-1,x
+                    sta data,x
+                    dex
+                    bne loop
+                    beq done
+            count = end - table
+            table:  .byte 1, 2, \"ab\"
+            end:
+            done:   jmp start
+                    .org $10
+            data:   .word done, $1234
+        ").unwrap();
+
+        assert_eq!(assembly.symbol("count"), Some(4));
+        assert_eq!(assembly.symbol("table"), Some(0xC00D));
+        assert_eq!(assembly.segments.len(), 2);
+        assert_eq!(assembly.segments[0].bytes, [
+            0xA2, 0x04, 0xBD, 0x0C, 0xC0, 0x9D, 0x10, 0x00, 0xCA, 0xD0, 0xF7, 0xF0, 0x04,
+            0x01, 0x02, 0x61, 0x62, 0x4C, 0x00, 0xC0,
+        ]);
+        // data is a forward reference, so STA keeps the absolute form.
+        assert_eq!(assembly.segments[1], Segment { origin: 0x0010, bytes: vec![0x11, 0xC0, 0x34, 0x12] });
+    }
+
+    #[test]
+    fn test_expressions() {
+        let mut assembler = Assembler::new(Variant::Nmos6502);
+        assembler.define("VECTOR", 0xFFFE);
+        let assembly = assembler.assemble("
+            .org $0200
+            .byte <VECTOR, >VECTOR, 2+3*4, (2+3)*4, %1010 | $F0, 'A', -1, ~0 & $FF, 1 << 4
+            .word * - 2, VECTOR / 2
+        ").unwrap();
+        assert_eq!(assembly.segments[0].bytes, [
+            0xFE, 0xFF, 14, 20, 0xFA, 0x41, 0xFF, 0xFF, 0x10, 0x07, 0x02, 0xFF, 0x7F,
+        ]);
+    }
+
+    #[test]
+    fn test_variants() {
+        assert_eq!(assemble(Variant::Wdc65c02, "stz $10\nbra *\nlda ($12)\njmp ($1234,x)\nbbs7 $10,*"), [
+            0x64, 0x10, 0x80, 0xFE, 0xB2, 0x12, 0x7C, 0x34, 0x12, 0xFF, 0x10, 0xFD,
+        ]);
+        assert_eq!(assemble(Variant::Nmos6502Undocumented, "lax $10\nasr #$0f\naxs #1\nsbc #1\nnop"), [
+            0xA7, 0x10, 0x4B, 0x0F, 0xCB, 0x01, 0xE9, 0x01, 0xEA,
+        ]);
+
+        let error = Assembler::new(Variant::Nmos6502).assemble("stz $10").unwrap_err();
+        assert_eq!(error.kind, AssemblyErrorKind::UnknownMnemonic("STZ".to_string()));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(error("lda ($10)").kind, AssemblyErrorKind::InvalidAddressingMode("LDA".to_string()));
+        assert_eq!(error("\n  jmp nowhere").line, 2);
+        assert_eq!(error("jmp nowhere").kind, AssemblyErrorKind::UndefinedSymbol("nowhere".to_string()));
+        assert_eq!(error("a: nop\na: nop").kind, AssemblyErrorKind::DuplicateSymbol("a".to_string()));
+        assert_eq!(error("lda #$100").kind, AssemblyErrorKind::ValueOutOfRange(0x100));
+        assert_eq!(error("bne * + 200").kind, AssemblyErrorKind::BranchOutOfRange(198));
+        assert!(matches!(error(".fill 1").kind, AssemblyErrorKind::Syntax(_)));
+        assert!(matches!(error("lda #1 +").kind, AssemblyErrorKind::Syntax(_)));
+    }
+
+    #[test]
+    fn test_run_assembled_program() {
+        let assembly = Assembler::new(Variant::Nmos6502).assemble("
+            .org $0200
+                    ldx #5
+                    lda #0
+            loop:   clc
+                    adc #3
+                    dex
+                    bne loop
+                    sta result
+            halt:   jmp halt
+            result = $10
+        ").unwrap();
+        let mut bus = DummyBus::new();
+        assembly.load(&mut bus);
+
+        let mut cpu = Cpu::new();
+        cpu.set_program_counter(0x0200);
+        while cpu.program_counter() != assembly.symbol("halt").unwrap() {
+            cpu.single_step(&mut bus).unwrap();
+        }
+        assert_eq!(bus.read(0x0010), 15);
+    }
+}
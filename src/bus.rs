@@ -4,8 +4,25 @@
 pub trait Bus {
     /// Writes a byte to the given address in the bus
     fn write(&mut self, addr: u16, value: u8);
-    /// Reads a byte from the given address in the bus
-    fn read(&self, addr: u16) -> u8;
+    /// Reads a byte from the given address in the bus. Devices may change
+    /// their state when read, e.g. to acknowledge a flag.
+    fn read(&mut self, addr: u16) -> u8;
+    /// Reads a byte from the given address without side effects, for use by
+    /// debuggers and disassemblers. Implementations whose reads change the
+    /// state of a device must override it.
+    fn peek(&mut self, addr: u16) -> u8 {
+        self.read(addr)
+    }
+    /// Writes a byte to a 24 bit address, as issued by the 65816. Buses that
+    /// only decode 16 bits can rely on the default, which ignores the bank.
+    fn write_long(&mut self, addr: u32, value: u8) {
+        self.write(addr as u16, value)
+    }
+    /// Reads a byte from a 24 bit address, as issued by the 65816. Buses that
+    /// only decode 16 bits can rely on the default, which ignores the bank.
+    fn read_long(&mut self, addr: u32) -> u8 {
+        self.read(addr as u16)
+    }
 }
 
 #[cfg(test)]
@@ -24,7 +41,7 @@ pub mod tests {
     }
 
     impl Bus for DummyBus {
-        fn read(&self, addr: u16) -> u8 {
+        fn read(&mut self, addr: u16) -> u8 {
             self.mem[addr as usize]
         }
 
@@ -32,4 +49,43 @@ pub mod tests {
             self.mem[addr as usize] = value;
         }
     }
-}
\ No newline at end of file
+
+    /// Device with a status register that is acknowledged when read.
+    struct ClearOnReadBus {
+        status: u8,
+    }
+
+    impl Bus for ClearOnReadBus {
+        fn read(&mut self, _addr: u16) -> u8 {
+            let status = self.status;
+            self.status = 0;
+            status
+        }
+
+        fn write(&mut self, _addr: u16, value: u8) {
+            self.status = value;
+        }
+
+        fn peek(&mut self, _addr: u16) -> u8 {
+            self.status
+        }
+    }
+
+    #[test]
+    fn test_default_peek() {
+        let mut bus = DummyBus::new();
+        bus.write(0x1234, 0x56);
+
+        assert_eq!(bus.peek(0x1234), 0x56);
+        assert_eq!(bus.read(0x1234), 0x56);
+    }
+
+    #[test]
+    fn test_read_side_effects() {
+        let mut bus = ClearOnReadBus { status: 0x80 };
+
+        assert_eq!(bus.peek(0x0000), 0x80);
+        assert_eq!(bus.read(0x0000), 0x80);
+        assert_eq!(bus.peek(0x0000), 0x00);
+    }
+}
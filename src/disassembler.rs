@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::addressing_modes::AddressingMode;
+use crate::bus::Bus;
+use crate::instruction::Instruction;
+use crate::opcodes::decode;
+use crate::variant::Variant;
+
+/// Assembler dialect of the disassembly.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Syntax {
+    /// ca65, from the cc65 suite: `ASL A`, `.byte` and `a:` to force
+    /// absolute addressing of zero page addresses.
+    #[default]
+    Ca65,
+    /// ACME: `ASL` without operand, `!byte` and a `+2` suffix to force
+    /// absolute addressing of zero page addresses.
+    Acme,
+}
+
+/// A single disassembled instruction, or a data byte that doesn't decode
+/// to one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Disassembly {
+    /// Address of the first byte.
+    pub addr: u16,
+    /// Opcode and operand bytes.
+    pub bytes: Vec<u8>,
+    /// Label defined at `addr`, if any.
+    pub label: Option<String>,
+    /// Mnemonic, or the byte directive of the syntax for data.
+    pub mnemonic: String,
+    /// Operand text, empty for implied instructions.
+    pub operand: String,
+    /// Base cycle count, without page crossing and branch penalties. Zero
+    /// for data and JAM opcodes.
+    pub cycles: u8,
+}
+
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.operand.is_empty() {
+            write!(f, "{}", self.mnemonic)
+        } else {
+            write!(f, "{} {}", self.mnemonic, self.operand)
+        }
+    }
+}
+
+/// Turns machine code back into assembly text, using the opcode table of
+/// a `Variant`.
+///
+/// # Example
+///```
+///    let mut disassembler = mos6502::Disassembler::new(mos6502::Variant::Nmos6502);
+///    disassembler.add_label(0xC000, "start");
+///    let lines = disassembler.disassemble_slice(&[0xB1, 0x12, 0x4C, 0x00, 0xC0], 0xC000);
+///    assert_eq!(lines[0].to_string(), "LDA ($12),Y");
+///    assert_eq!(lines[1].to_string(), "JMP start");
+///```
+pub struct Disassembler {
+    variant: Variant,
+    syntax: Syntax,
+    lowercase: bool,
+    labels: HashMap<u16, String>,
+}
+
+impl Disassembler {
+    ///
+    /// Constructs a Disassembler for the given variant, using the ca65
+    /// syntax in upper case.
+    ///
+    pub fn new(variant: Variant) -> Disassembler {
+        Disassembler {
+            variant,
+            syntax: Syntax::Ca65,
+            lowercase: false,
+            labels: HashMap::new(),
+        }
+    }
+
+    /// Selects the assembler dialect.
+    pub fn set_syntax(&mut self, syntax: Syntax) {
+        self.syntax = syntax;
+    }
+
+    ///
+    /// Writes mnemonics, registers and hexadecimal numbers in lower case.
+    /// Labels are kept as given.
+    ///
+    pub fn set_lowercase(&mut self, lowercase: bool) {
+        self.lowercase = lowercase;
+    }
+
+    ///
+    /// Names an address. Operands that point to it, branch targets included,
+    /// use the name instead of the number.
+    ///
+    pub fn add_label<S: Into<String>>(&mut self, addr: u16, name: S) {
+        self.labels.insert(addr, name.into());
+    }
+
+    /// Returns the name given to an address, if any.
+    pub fn label(&self, addr: u16) -> Option<&str> {
+        self.labels.get(&addr).map(String::as_str)
+    }
+
+    ///
+    /// Disassembles the instruction at the given address. Memory is read
+    /// with `Bus::peek`, so devices are left untouched.
+    ///
+    pub fn disassemble_one<T: Bus>(&self, bus: &mut T, addr: u16) -> Disassembly {
+        let opcode = bus.peek(addr);
+        match decode(opcode, self.variant) {
+            Some((instruction, mode, cycles)) => {
+                let mut bytes = vec![opcode];
+                for offset in 1..=mode.operand_length() {
+                    bytes.push(bus.peek(addr.wrapping_add(offset)));
+                }
+                self.instruction(addr, bytes, instruction, mode, cycles)
+            },
+            None => self.data(addr, opcode),
+        }
+    }
+
+    ///
+    /// Disassembles `count` instructions starting at the given address.
+    ///
+    pub fn disassemble<T: Bus>(&self, bus: &mut T, addr: u16, count: usize) -> Vec<Disassembly> {
+        let mut addr = addr;
+        let mut lines = Vec::with_capacity(count);
+        for _ in 0..count {
+            let line = self.disassemble_one(bus, addr);
+            addr = addr.wrapping_add(line.bytes.len() as u16);
+            lines.push(line);
+        }
+        lines
+    }
+
+    ///
+    /// Disassembles a whole slice of machine code loaded at `origin`. An
+    /// instruction cut by the end of the slice is listed as data bytes.
+    ///
+    pub fn disassemble_slice(&self, bytes: &[u8], origin: u16) -> Vec<Disassembly> {
+        let mut lines = Vec::new();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let addr = origin.wrapping_add(offset as u16);
+            let opcode = bytes[offset];
+            let line = match decode(opcode, self.variant) {
+                Some((instruction, mode, cycles)) if offset + 1 + mode.operand_length() as usize <= bytes.len() => {
+                    let end = offset + 1 + mode.operand_length() as usize;
+                    self.instruction(addr, bytes[offset..end].to_vec(), instruction, mode, cycles)
+                },
+                _ => self.data(addr, opcode),
+            };
+            offset += line.bytes.len();
+            lines.push(line);
+        }
+        lines
+    }
+
+    fn instruction(&self, addr: u16, bytes: Vec<u8>, instruction: Instruction, mode: AddressingMode, cycles: u8) -> Disassembly {
+        let byte = bytes.get(1).copied().unwrap_or(0);
+        let word = u16::from_le_bytes([byte, bytes.get(2).copied().unwrap_or(0)]);
+        let next = addr.wrapping_add(bytes.len() as u16);
+        let mut mnemonic = self.mnemonic(instruction);
+
+        let operand = match mode {
+            AddressingMode::Implied => String::new(),
+            AddressingMode::Accumulator => match self.syntax {
+                Syntax::Ca65 => self.text("A"),
+                Syntax::Acme => String::new(),
+            },
+            AddressingMode::Immediate => format!("#{}", self.hex8(byte)),
+            AddressingMode::Zeropage => self.address(byte as u16, false, "", ""),
+            AddressingMode::ZeropageXIndexed => self.address(byte as u16, false, "", ",X"),
+            AddressingMode::ZeropageYIndexed => self.address(byte as u16, false, "", ",Y"),
+            AddressingMode::ZeropageIndirect => self.address(byte as u16, false, "(", ")"),
+            AddressingMode::XIndexedIndirect => self.address(byte as u16, false, "(", ",X)"),
+            AddressingMode::IndirectYIndexed => self.address(byte as u16, false, "(", "),Y"),
+            AddressingMode::Indirect => self.address(word, true, "(", ")"),
+            AddressingMode::AbsoluteXIndexedIndirect => self.address(word, true, "(", ",X)"),
+            AddressingMode::Absolute | AddressingMode::AbsoluteXIndexed | AddressingMode::AbsoluteYIndexed => {
+                let suffix = match mode {
+                    AddressingMode::AbsoluteXIndexed => ",X",
+                    AddressingMode::AbsoluteYIndexed => ",Y",
+                    _ => "",
+                };
+                // Make the assembler keep the absolute form of zero page addresses.
+                if word < 0x0100 && self.syntax == Syntax::Acme {
+                    mnemonic.push_str("+2");
+                    self.address(word, true, "", suffix)
+                } else if word < 0x0100 {
+                    self.address(word, true, "a:", suffix)
+                } else {
+                    self.address(word, true, "", suffix)
+                }
+            },
+            AddressingMode::Relative => self.target(next.wrapping_add(byte as i8 as u16)),
+            AddressingMode::ZeropageRelative => {
+                let offset = bytes.get(2).copied().unwrap_or(0) as i8;
+                format!("{},{}", self.address(byte as u16, false, "", ""), self.target(next.wrapping_add(offset as u16)))
+            },
+        };
+
+        Disassembly {
+            addr,
+            bytes,
+            label: self.labels.get(&addr).cloned(),
+            mnemonic,
+            operand,
+            cycles,
+        }
+    }
+
+    fn data(&self, addr: u16, value: u8) -> Disassembly {
+        let directive = match self.syntax {
+            Syntax::Ca65 => ".byte",
+            Syntax::Acme => "!byte",
+        };
+        Disassembly {
+            addr,
+            bytes: vec![value],
+            label: self.labels.get(&addr).cloned(),
+            mnemonic: self.text(directive),
+            operand: self.hex8(value),
+            cycles: 0,
+        }
+    }
+
+    fn mnemonic(&self, instruction: Instruction) -> String {
+        let mnemonic = match instruction {
+            // The dialects disagree on a couple of undocumented opcodes.
+            Instruction::Alr if self.syntax == Syntax::Acme => "ASR".to_string(),
+            Instruction::Sbx if self.syntax == Syntax::Ca65 => "AXS".to_string(),
+            _ => instruction.mnemonic(),
+        };
+        self.text(&mnemonic)
+    }
+
+    ///
+    /// Formats an address operand, using its label when there is one.
+    ///
+    fn address(&self, addr: u16, wide: bool, prefix: &str, suffix: &str) -> String {
+        let value = match self.labels.get(&addr) {
+            Some(label) => label.clone(),
+            None if wide => self.hex16(addr),
+            None => self.hex8(addr as u8),
+        };
+        format!("{}{}{}", self.text(prefix), value, self.text(suffix))
+    }
+
+    fn target(&self, addr: u16) -> String {
+        match self.labels.get(&addr) {
+            Some(label) => label.clone(),
+            None => self.hex16(addr),
+        }
+    }
+
+    fn hex8(&self, value: u8) -> String {
+        self.text(&format!("${:02X}", value))
+    }
+
+    fn hex16(&self, value: u16) -> String {
+        self.text(&format!("${:04X}", value))
+    }
+
+    fn text(&self, text: &str) -> String {
+        if self.lowercase { text.to_lowercase() } else { text.to_string() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::tests::DummyBus;
+
+    fn disassemble(disassembler: &Disassembler, bytes: &[u8]) -> Vec<String> {
+        disassembler.disassemble_slice(bytes, 0x1000).iter().map(Disassembly::to_string).collect()
+    }
+
+    #[test]
+    fn test_addressing_modes() {
+        let disassembler = Disassembler::new(Variant::Nmos6502);
+        let lines = disassemble(&disassembler, &[
+            0xA9, 0x12, 0xB5, 0x34, 0xB6, 0x34, 0xAD, 0x34, 0x12, 0xBD, 0x34, 0x12,
+            0xB9, 0x12, 0x00, 0xA1, 0x20, 0xB1, 0x20, 0x6C, 0xFC, 0xFF, 0x0A, 0xEA,
+            0xD0, 0xFE, 0x10, 0x80,
+        ]);
+        assert_eq!(lines, [
+            "LDA #$12", "LDA $34,X", "LDX $34,Y", "LDA $1234", "LDA $1234,X",
+            "LDA a:$0012,Y", "LDA ($20,X)", "LDA ($20),Y", "JMP ($FFFC)", "ASL A", "NOP",
+            "BNE $1018", "BPL $0F9C",
+        ]);
+    }
+
+    #[test]
+    fn test_length_and_cycles() {
+        let mut bus = DummyBus::new();
+        bus.write(0x0200, 0x7D);
+        bus.write(0x0201, 0x00);
+        bus.write(0x0202, 0x30);
+        bus.write(0x0203, 0x60);
+        let lines = Disassembler::new(Variant::Nmos6502).disassemble(&mut bus, 0x0200, 2);
+
+        assert_eq!(lines[0].bytes.len(), 3);
+        assert_eq!(lines[0].bytes, [0x7D, 0x00, 0x30]);
+        assert_eq!(lines[0].cycles, 4);
+        assert_eq!(lines[1].addr, 0x0203);
+        assert_eq!(lines[1].to_string(), "RTS");
+        assert_eq!(lines[1].cycles, 6);
+    }
+
+    #[test]
+    fn test_syntaxes() {
+        let mut disassembler = Disassembler::new(Variant::Nmos6502Undocumented);
+        let program = [0x4A, 0x4B, 0x12, 0xCB, 0x01, 0x8D, 0x80, 0x00];
+        assert_eq!(disassemble(&disassembler, &program), ["LSR A", "ALR #$12", "AXS #$01", "STA a:$0080"]);
+
+        disassembler.set_syntax(Syntax::Acme);
+        disassembler.set_lowercase(true);
+        assert_eq!(disassemble(&disassembler, &program), ["lsr", "asr #$12", "sbx #$01", "sta+2 $0080"]);
+    }
+
+    #[test]
+    fn test_labels() {
+        let mut disassembler = Disassembler::new(Variant::Wdc65c02);
+        disassembler.set_lowercase(true);
+        disassembler.add_label(0x1000, "Loop");
+        disassembler.add_label(0x0010, "Counter");
+        let lines = disassembler.disassemble_slice(&[0xC6, 0x10, 0x8F, 0x10, 0xFB, 0xB2, 0x10], 0x1000);
+
+        assert_eq!(lines[0].label.as_deref(), Some("Loop"));
+        assert_eq!(lines[0].to_string(), "dec Counter");
+        assert_eq!(lines[1].to_string(), "bbs0 Counter,Loop");
+        assert_eq!(lines[2].to_string(), "lda (Counter)");
+    }
+
+    #[test]
+    fn test_data_bytes() {
+        let mut disassembler = Disassembler::new(Variant::Nmos6502);
+        // An unknown opcode and a truncated JMP.
+        assert_eq!(disassemble(&disassembler, &[0x03, 0x4C, 0x00]), [".byte $03", ".byte $4C", "BRK"]);
+
+        disassembler.set_syntax(Syntax::Acme);
+        assert_eq!(disassemble(&disassembler, &[0x03]), ["!byte $03"]);
+    }
+}
@@ -0,0 +1,118 @@
+
+use std::fmt;
+
+/// Errors that stop the execution of an instruction.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The opcode at `addr` is not implemented by the emulated variant.
+    UnknownOpcode { addr: u16, opcode: u8 },
+    /// A JAM (also known as KIL) opcode at `addr` halted the processor.
+    /// Only a reset gets it running again.
+    Jam { addr: u16, opcode: u8 },
+    /// The opcode at `addr` was decoded with an operand its instruction
+    /// cannot work with.
+    InvalidOperand { addr: u16, opcode: u8 },
+    /// A STP instruction stopped the clock, `addr` is the address after it.
+    /// Only a reset gets it running again.
+    Stopped { addr: u16 },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnknownOpcode { addr, opcode } => {
+                write!(f, "unknown opcode ${:02X} at ${:04X}", opcode, addr)
+            },
+            Error::Jam { addr, opcode } => {
+                write!(f, "processor jammed by opcode ${:02X} at ${:04X}", opcode, addr)
+            },
+            Error::InvalidOperand { addr, opcode } => {
+                write!(f, "invalid operand for opcode ${:02X} at ${:04X}", opcode, addr)
+            },
+            Error::Stopped { addr } => {
+                write!(f, "processor stopped before ${:04X}", addr)
+            },
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Errors returned when decoding or restoring a `Snapshot`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The data does not start with the snapshot signature.
+    BadSignature,
+    /// The snapshot was written by an incompatible version of the format.
+    UnsupportedVersion { found: u16, supported: u16 },
+    /// The data ends before the snapshot is complete.
+    Truncated,
+    /// The data goes on after the end of the snapshot.
+    TrailingBytes { count: usize },
+    /// A field holds a value that can't be restored.
+    InvalidField { field: &'static str },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::BadSignature => write!(f, "not a processor snapshot"),
+            SnapshotError::UnsupportedVersion { found, supported } => {
+                write!(f, "unsupported snapshot version {}, expected {}", found, supported)
+            },
+            SnapshotError::Truncated => write!(f, "truncated snapshot"),
+            SnapshotError::TrailingBytes { count } => write!(f, "{} unexpected bytes after the snapshot", count),
+            SnapshotError::InvalidField { field } => {
+                write!(f, "invalid value for snapshot field `{}`", field)
+            },
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+/// Error found by the `Assembler`, with the number of the offending line,
+/// starting at 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssemblyError {
+    pub line: usize,
+    pub kind: AssemblyErrorKind,
+}
+
+/// Reasons why a line can't be assembled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AssemblyErrorKind {
+    /// The line is not valid assembly.
+    Syntax(String),
+    /// The mnemonic doesn't exist on the target variant.
+    UnknownMnemonic(String),
+    /// The instruction exists, but not with the given operand.
+    InvalidAddressingMode(String),
+    /// The expression uses a symbol that is never defined.
+    UndefinedSymbol(String),
+    /// The symbol is defined twice.
+    DuplicateSymbol(String),
+    /// The branch target is further than 128 bytes away.
+    BranchOutOfRange(i64),
+    /// The value doesn't fit in its operand.
+    ValueOutOfRange(i64),
+}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            AssemblyErrorKind::Syntax(message) => write!(f, "{}", message),
+            AssemblyErrorKind::UnknownMnemonic(mnemonic) => write!(f, "unknown mnemonic `{}`", mnemonic),
+            AssemblyErrorKind::InvalidAddressingMode(mnemonic) => {
+                write!(f, "invalid addressing mode for `{}`", mnemonic)
+            },
+            AssemblyErrorKind::UndefinedSymbol(name) => write!(f, "undefined symbol `{}`", name),
+            AssemblyErrorKind::DuplicateSymbol(name) => write!(f, "symbol `{}` is already defined", name),
+            AssemblyErrorKind::BranchOutOfRange(offset) => write!(f, "branch out of range ({} bytes)", offset),
+            AssemblyErrorKind::ValueOutOfRange(value) => write!(f, "value {} out of range", value),
+        }
+    }
+}
+
+impl std::error::Error for AssemblyError {}
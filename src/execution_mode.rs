@@ -0,0 +1,21 @@
+
+/// Controls which bus accesses the `Cpu` issues while executing instructions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExecutionMode {
+    /// Only the accesses needed to execute the instruction are issued.
+    #[default]
+    Fast,
+    /// Every bus cycle of the NMOS chip is issued in order, including the
+    /// dummy reads of indexed addressing and the double writes of
+    /// read-modify-write instructions. The number of accesses per step
+    /// matches the number of cycles.
+    CycleAccurate,
+}
+
+impl ExecutionMode {
+    /// Returns true if dummy bus cycles have to be issued.
+    pub fn is_cycle_accurate(&self) -> bool {
+        *self == ExecutionMode::CycleAccurate
+    }
+}
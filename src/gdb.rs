@@ -0,0 +1,781 @@
+use crate::bus::Bus;
+use crate::run::{RunUntil, StopReason};
+use crate::watch::{AccessKind, WatchBus, Watchpoint, WatchKind};
+use crate::Cpu;
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::fmt::Write as _;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+/// Target description sent to gdb, with the registers in the order of the
+/// `g` packet.
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.mos6502.core">
+    <flags id="status" size="1">
+      <field name="C" start="0" end="0"/>
+      <field name="Z" start="1" end="1"/>
+      <field name="I" start="2" end="2"/>
+      <field name="D" start="3" end="3"/>
+      <field name="B" start="4" end="4"/>
+      <field name="V" start="6" end="6"/>
+      <field name="N" start="7" end="7"/>
+    </flags>
+    <reg name="a" bitsize="8" type="uint8" regnum="0"/>
+    <reg name="x" bitsize="8" type="uint8" regnum="1"/>
+    <reg name="y" bitsize="8" type="uint8" regnum="2"/>
+    <reg name="sp" bitsize="8" type="uint8" regnum="3"/>
+    <reg name="pc" bitsize="16" type="code_ptr" regnum="4"/>
+    <reg name="p" bitsize="8" type="status" regnum="5"/>
+  </feature>
+</target>
+"#;
+
+/// Number of instructions run between two checks for an interrupt from
+/// gdb while continuing.
+const CONTINUE_CHUNK: u64 = 10_000;
+/// Byte sent by gdb to interrupt a continue.
+const INTERRUPT: u8 = 0x03;
+
+/// Stream a `GdbStub` talks to gdb over.
+pub trait Connection: Read + Write {
+    ///
+    /// Checks without blocking whether gdb asked to interrupt the target
+    /// while it runs.
+    ///
+    fn poll_interrupt(&mut self) -> io::Result<bool>;
+}
+
+impl Connection for TcpStream {
+    fn poll_interrupt(&mut self) -> io::Result<bool> {
+        self.set_nonblocking(true)?;
+        let mut byte = [0];
+        let result = self.read(&mut byte);
+        self.set_nonblocking(false)?;
+        match result {
+            Ok(0) => Err(ErrorKind::UnexpectedEof.into()),
+            Ok(_) => Ok(byte[0] == INTERRUPT),
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Server for the gdb remote serial protocol, debugging the program run by
+/// a `Cpu` on a bus.
+///
+/// The registers are A, X, Y, SP, PC and P, as described by the target
+/// description sent to gdb. Software and hardware breakpoints both become
+/// breakpoints of the `Cpu`, and watchpoints are set on the `WatchBus`.
+/// Memory accesses from gdb go to the wrapped bus, so they don't trigger
+/// the watchpoints.
+///
+/// ```no_run
+/// use mos6502::{Cpu, GdbStub, WatchBus};
+/// # use mos6502::Bus;
+/// # struct Ram([u8; 0x10000]);
+/// # impl Bus for Ram {
+/// #     fn read(&mut self, addr: u16) -> u8 { self.0[addr as usize] }
+/// #     fn write(&mut self, addr: u16, value: u8) { self.0[addr as usize] = value; }
+/// # }
+///
+/// let mut cpu = Cpu::new();
+/// let mut bus = WatchBus::new(Ram([0; 0x10000]));
+/// // Then `target remote localhost:1234` in gdb.
+/// GdbStub::new(&mut cpu, &mut bus).listen("127.0.0.1:1234").unwrap();
+/// ```
+pub struct GdbStub<'a, T: Bus> {
+    cpu: &'a mut Cpu,
+    bus: &'a mut WatchBus<T>,
+    software_breakpoints: BTreeSet<u16>,
+    hardware_breakpoints: BTreeSet<u16>,
+    /// Breakpoints added to the `Cpu` by gdb, which it didn't have before.
+    cpu_breakpoints: BTreeSet<u16>,
+    /// Watchpoints added to the `WatchBus` by gdb.
+    watchpoints: Vec<Watchpoint>,
+    no_ack: bool,
+}
+
+/// Outcome of a packet.
+enum Reply {
+    Packet(String),
+    /// The session ends, after sending the packet if any.
+    Close(Option<String>),
+}
+
+impl<'a, T: Bus> GdbStub<'a, T> {
+    ///
+    /// Constructs a stub driving the given `Cpu` and bus.
+    ///
+    pub fn new(cpu: &'a mut Cpu, bus: &'a mut WatchBus<T>) -> Self {
+        GdbStub {
+            cpu,
+            bus,
+            software_breakpoints: BTreeSet::new(),
+            hardware_breakpoints: BTreeSet::new(),
+            cpu_breakpoints: BTreeSet::new(),
+            watchpoints: Vec::new(),
+            no_ack: false,
+        }
+    }
+
+    ///
+    /// Listens on the given address, and serves the first gdb that
+    /// connects until it detaches or kills the target.
+    ///
+    pub fn listen<A: ToSocketAddrs>(&mut self, addr: A) -> io::Result<()> {
+        let listener = TcpListener::bind(addr)?;
+        self.accept(&listener)
+    }
+
+    ///
+    /// Waits for a connection on the listener and serves it until gdb
+    /// detaches or kills the target.
+    ///
+    pub fn accept(&mut self, listener: &TcpListener) -> io::Result<()> {
+        let (mut stream, _) = listener.accept()?;
+        stream.set_nodelay(true)?;
+        self.serve(&mut stream)
+    }
+
+    ///
+    /// Serves a session over the given connection. Returns once gdb
+    /// detaches, kills the target or closes the connection. The breakpoints
+    /// and watchpoints set by gdb are left in place when the session ends
+    /// without a detach.
+    ///
+    pub fn serve<C: Connection>(&mut self, conn: &mut C) -> io::Result<()> {
+        self.no_ack = false;
+        while let Some(packet) = self.read_packet(conn)? {
+            match self.handle(&packet, conn)? {
+                Reply::Packet(reply) => send_packet(conn, &reply)?,
+                Reply::Close(reply) => {
+                    if let Some(reply) = reply {
+                        send_packet(conn, &reply)?;
+                    }
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Reads the next packet, acknowledging it. Returns `None` at the end of
+    /// the stream.
+    ///
+    fn read_packet<C: Connection>(&mut self, conn: &mut C) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            match read_byte(conn)? {
+                None => return Ok(None),
+                Some(b'$') => {}
+                // Acks, and interrupts received while stopped.
+                Some(_) => continue,
+            }
+            let mut data = Vec::new();
+            loop {
+                match read_byte(conn)? {
+                    None => return Ok(None),
+                    Some(b'#') => break,
+                    Some(byte) => data.push(byte),
+                }
+            }
+            let mut checksum = [0; 2];
+            conn.read_exact(&mut checksum)?;
+            let expected = std::str::from_utf8(&checksum).ok()
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok());
+            let valid = expected == Some(checksum_of(&data));
+            if !self.no_ack {
+                conn.write_all(if valid { b"+" } else { b"-" })?;
+                conn.flush()?;
+            }
+            if valid {
+                return Ok(Some(data));
+            }
+        }
+    }
+
+    fn handle<C: Connection>(&mut self, packet: &[u8], conn: &mut C) -> io::Result<Reply> {
+        // Binary data only comes after the colon of an X packet.
+        if packet.first() == Some(&b'X') {
+            return Ok(Reply::Packet(self.write_binary(packet).unwrap_or_else(error)));
+        }
+        let packet = match std::str::from_utf8(packet) {
+            Ok(packet) => packet,
+            Err(_) => return Ok(Reply::Packet(error())),
+        };
+
+        let reply = match packet {
+            "?" => "S05".to_string(),
+            "g" => self.read_registers(),
+            "k" => return Ok(Reply::Close(None)),
+            "D" => {
+                self.clear_debug_state();
+                return Ok(Reply::Close(Some("OK".to_string())));
+            }
+            "QStartNoAckMode" => {
+                self.no_ack = true;
+                "OK".to_string()
+            }
+            "qAttached" => "1".to_string(),
+            "qfThreadInfo" => "m1".to_string(),
+            "qsThreadInfo" => "l".to_string(),
+            "qC" => "QC1".to_string(),
+            _ if packet.starts_with("qSupported") => {
+                "PacketSize=4000;QStartNoAckMode+;qXfer:features:read+;swbreak+;hwbreak+".to_string()
+            }
+            _ if packet.starts_with("qXfer:features:read:") => {
+                read_target_xml(&packet["qXfer:features:read:".len()..]).unwrap_or_else(error)
+            }
+            _ if packet.starts_with('H') || packet.starts_with('T') => "OK".to_string(),
+            _ => match packet.as_bytes().first() {
+                Some(b'G') => self.write_registers(&packet[1..]).unwrap_or_else(error),
+                Some(b'p') => self.read_register(&packet[1..]).unwrap_or_else(error),
+                Some(b'P') => self.write_register(&packet[1..]).unwrap_or_else(error),
+                Some(b'm') => self.read_memory(&packet[1..]).unwrap_or_else(error),
+                Some(b'M') => self.write_memory(&packet[1..]).unwrap_or_else(error),
+                Some(b'Z') => self.set_breakpoint(&packet[1..], true).unwrap_or_else(error),
+                Some(b'z') => self.set_breakpoint(&packet[1..], false).unwrap_or_else(error),
+                Some(b's') | Some(b'c') => {
+                    if packet.len() > 1 {
+                        match parse_addr(&packet[1..]) {
+                            Some(addr) => self.cpu.set_program_counter(addr),
+                            None => return Ok(Reply::Packet(error())),
+                        }
+                    }
+                    self.resume(conn, packet.starts_with('s'))?
+                }
+                // Unsupported and empty packets get an empty reply.
+                _ => String::new(),
+            },
+        };
+        Ok(Reply::Packet(reply))
+    }
+
+    fn read_registers(&self) -> String {
+        let cpu = &self.cpu;
+        let pc = cpu.program_counter().to_le_bytes();
+        hex(&[cpu.accumulator(), cpu.x_index(), cpu.y_index(), cpu.stack_pointer(), pc[0], pc[1], cpu.status()])
+    }
+
+    fn write_registers(&mut self, data: &str) -> Option<String> {
+        let bytes = parse_bytes(data)?;
+        if bytes.len() != 7 {
+            return None;
+        }
+        self.cpu.set_accumulator(bytes[0]);
+        self.cpu.set_x_index(bytes[1]);
+        self.cpu.set_y_index(bytes[2]);
+        self.cpu.set_stack_pointer(bytes[3]);
+        self.cpu.set_program_counter(u16::from_le_bytes([bytes[4], bytes[5]]));
+        self.cpu.set_status(bytes[6]);
+        Some("OK".to_string())
+    }
+
+    fn read_register(&self, data: &str) -> Option<String> {
+        let cpu = &self.cpu;
+        let value = match parse_hex(data)? {
+            0 => vec![cpu.accumulator()],
+            1 => vec![cpu.x_index()],
+            2 => vec![cpu.y_index()],
+            3 => vec![cpu.stack_pointer()],
+            4 => cpu.program_counter().to_le_bytes().to_vec(),
+            5 => vec![cpu.status()],
+            _ => return None,
+        };
+        Some(hex(&value))
+    }
+
+    fn write_register(&mut self, data: &str) -> Option<String> {
+        let (register, value) = data.split_once('=')?;
+        let value = parse_bytes(value)?;
+        match (parse_hex(register)?, value.as_slice()) {
+            (0, &[value]) => self.cpu.set_accumulator(value),
+            (1, &[value]) => self.cpu.set_x_index(value),
+            (2, &[value]) => self.cpu.set_y_index(value),
+            (3, &[value]) => self.cpu.set_stack_pointer(value),
+            (4, &[low, high]) => self.cpu.set_program_counter(u16::from_le_bytes([low, high])),
+            (5, &[value]) => self.cpu.set_status(value),
+            _ => return None,
+        }
+        Some("OK".to_string())
+    }
+
+    fn read_memory(&mut self, data: &str) -> Option<String> {
+        let (addr, len) = parse_range(data)?;
+        let bytes: Vec<u8> = (0..len)
+            .map(|offset| self.bus.bus_mut().peek(addr.wrapping_add(offset as u16)))
+            .collect();
+        Some(hex(&bytes))
+    }
+
+    fn write_memory(&mut self, data: &str) -> Option<String> {
+        let (range, bytes) = data.split_once(':')?;
+        let bytes = parse_bytes(bytes)?;
+        self.store(range, &bytes)
+    }
+
+    fn write_binary(&mut self, packet: &[u8]) -> Option<String> {
+        let colon = packet.iter().position(|&b| b == b':')?;
+        let range = std::str::from_utf8(&packet[1..colon]).ok()?;
+        let mut bytes = Vec::new();
+        let mut escaped = false;
+        for &byte in &packet[colon + 1..] {
+            if escaped {
+                bytes.push(byte ^ 0x20);
+                escaped = false;
+            } else if byte == b'}' {
+                escaped = true;
+            } else {
+                bytes.push(byte);
+            }
+        }
+        self.store(range, &bytes)
+    }
+
+    fn store(&mut self, range: &str, bytes: &[u8]) -> Option<String> {
+        let (addr, len) = parse_range(range)?;
+        if len != bytes.len() {
+            return None;
+        }
+        for (offset, &byte) in bytes.iter().enumerate() {
+            self.bus.bus_mut().write(addr.wrapping_add(offset as u16), byte);
+        }
+        Some("OK".to_string())
+    }
+
+    ///
+    /// Handles `Z` and `z` packets: `type,addr,kind`, where kind is the
+    /// length of a watchpoint.
+    ///
+    fn set_breakpoint(&mut self, data: &str, insert: bool) -> Option<String> {
+        let mut fields = data.splitn(3, ',');
+        let kind = fields.next()?;
+        let addr = parse_addr(fields.next()?)?;
+        let len = fields.next()?.split(';').next().and_then(parse_hex)?;
+
+        let watch_kind = match kind {
+            "0" | "1" => {
+                let (set, other) = if kind == "0" {
+                    (&mut self.software_breakpoints, &self.hardware_breakpoints)
+                } else {
+                    (&mut self.hardware_breakpoints, &self.software_breakpoints)
+                };
+                if insert {
+                    set.insert(addr);
+                    // Breakpoints the Cpu already had are left to their owner.
+                    if !self.cpu.breakpoints().any(|b| b == addr) {
+                        self.cpu.add_breakpoint(addr);
+                        self.cpu_breakpoints.insert(addr);
+                    }
+                } else if set.remove(&addr) && !other.contains(&addr) && self.cpu_breakpoints.remove(&addr) {
+                    self.cpu.remove_breakpoint(addr);
+                }
+                return Some("OK".to_string());
+            }
+            "2" => WatchKind::Write,
+            "3" => WatchKind::Read,
+            "4" => WatchKind::Access,
+            _ => return Some(String::new()),
+        };
+        // Watched ranges stop at the end of the address space.
+        let end = addr.saturating_add((len.clamp(1, 0x10000) - 1) as u16);
+        let watchpoint = Watchpoint::range(addr, end, watch_kind);
+        if insert {
+            if !self.bus.watchpoints().contains(&watchpoint) {
+                self.bus.add_watchpoint(watchpoint);
+                self.watchpoints.push(watchpoint);
+            }
+        } else if let Some(index) = self.watchpoints.iter().position(|w| *w == watchpoint) {
+            self.watchpoints.remove(index);
+            self.bus.remove_watchpoint(&watchpoint);
+        }
+        Some("OK".to_string())
+    }
+
+    ///
+    /// Steps one instruction, or runs until a breakpoint, a watchpoint or an
+    /// interrupt from gdb. Returns the stop reply.
+    ///
+    fn resume<C: Connection>(&mut self, conn: &mut C, step: bool) -> io::Result<String> {
+        let until = RunUntil::instructions(if step { 1 } else { CONTINUE_CHUNK });
+        let mut first = true;
+        loop {
+            // A run doesn't stop on a breakpoint at its first instruction,
+            // which is only wanted when resuming from it.
+            let pc = self.cpu.program_counter();
+            if !first && self.is_breakpoint(pc) {
+                return Ok(self.stop_reply(StopReason::Breakpoint(pc)));
+            }
+            first = false;
+
+            match self.cpu.run_watched(self.bus, &until) {
+                Ok(StopReason::InstructionLimit) if !step => {
+                    if conn.poll_interrupt()? {
+                        return Ok("S02".to_string());
+                    }
+                }
+                Ok(reason) => return Ok(self.stop_reply(reason)),
+                // SIGILL for jams and opcodes the variant doesn't know.
+                Err(_) => return Ok("S04".to_string()),
+            }
+        }
+    }
+
+    fn is_breakpoint(&self, addr: u16) -> bool {
+        self.software_breakpoints.contains(&addr) || self.hardware_breakpoints.contains(&addr)
+    }
+
+    fn stop_reply(&self, reason: StopReason) -> String {
+        match reason {
+            StopReason::Breakpoint(addr) if self.software_breakpoints.contains(&addr) => "T05swbreak:;".to_string(),
+            StopReason::Breakpoint(addr) if self.hardware_breakpoints.contains(&addr) => "T05hwbreak:;".to_string(),
+            StopReason::Watchpoint(hit) => {
+                // An access watchpoint is only reported when no read or
+                // write watchpoint matches the access.
+                let (kind, name) = match hit.access {
+                    AccessKind::Write => (WatchKind::Write, "watch"),
+                    AccessKind::Read => (WatchKind::Read, "rwatch"),
+                };
+                let exact = self.bus.watchpoints().iter()
+                    .any(|w| w.kind == kind && w.matches(hit.addr, hit.access, hit.new_value));
+                let name = if exact { name } else { "awatch" };
+                format!("T05{}:{:x};", name, hit.addr)
+            }
+            _ => "S05".to_string(),
+        }
+    }
+
+    ///
+    /// Removes the breakpoints and watchpoints set by gdb. The ones the
+    /// `Cpu` and the `WatchBus` already had are kept.
+    ///
+    fn clear_debug_state(&mut self) {
+        for addr in std::mem::take(&mut self.cpu_breakpoints) {
+            self.cpu.remove_breakpoint(addr);
+        }
+        for watchpoint in std::mem::take(&mut self.watchpoints) {
+            self.bus.remove_watchpoint(&watchpoint);
+        }
+        self.software_breakpoints.clear();
+        self.hardware_breakpoints.clear();
+    }
+}
+
+fn read_byte<C: Connection>(conn: &mut C) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    loop {
+        match conn.read(&mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(byte[0])),
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn send_packet<C: Connection>(conn: &mut C, data: &str) -> io::Result<()> {
+    write!(conn, "${}#{:02x}", data, checksum_of(data.as_bytes()))?;
+    conn.flush()
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+}
+
+///
+/// Answers `target.xml:offset,length` with the requested part of the
+/// target description.
+///
+fn read_target_xml(args: &str) -> Option<String> {
+    let (annex, range) = args.split_once(':')?;
+    if annex != "target.xml" {
+        return None;
+    }
+    let (offset, len) = range.split_once(',')?;
+    let (offset, len) = (parse_hex(offset)? as usize, parse_hex(len)? as usize);
+    let rest = TARGET_XML.get(offset.min(TARGET_XML.len())..)?;
+    if rest.len() <= len {
+        Some(format!("l{}", rest))
+    } else {
+        Some(format!("m{}", &rest[..len]))
+    }
+}
+
+fn error() -> String {
+    "E01".to_string()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        write!(text, "{:02x}", byte).unwrap();
+    }
+    text
+}
+
+fn parse_hex(text: &str) -> Option<u32> {
+    u32::from_str_radix(text, 16).ok()
+}
+
+fn parse_addr(text: &str) -> Option<u16> {
+    u16::try_from(parse_hex(text)?).ok()
+}
+
+///
+/// Parses the `addr,length` of a memory access. Accesses wrap around the
+/// address space, so they are at most 64K long.
+///
+fn parse_range(text: &str) -> Option<(u16, usize)> {
+    let (addr, len) = text.split_once(',')?;
+    let len = parse_hex(len)? as usize;
+    if len > 0x10000 {
+        return None;
+    }
+    Some((parse_addr(addr)?, len))
+}
+
+// `usize::is_multiple_of` needs Rust 1.87.
+#[allow(clippy::manual_is_multiple_of)]
+fn parse_bytes(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::tests::DummyBus;
+    use crate::Assembler;
+    use crate::Variant;
+
+    /// Connection replaying the packets of a gdb session.
+    struct Session {
+        input: io::Cursor<Vec<u8>>,
+        output: Vec<u8>,
+        interrupt: bool,
+    }
+
+    impl Session {
+        fn new(packets: &[&str]) -> Session {
+            let mut input = Vec::new();
+            for packet in packets {
+                write!(input, "${}#{:02x}", packet, checksum_of(packet.as_bytes())).unwrap();
+            }
+            Session { input: io::Cursor::new(input), output: Vec::new(), interrupt: false }
+        }
+
+        /// Returns the packets sent back, without acks and framing.
+        fn replies(&self) -> Vec<String> {
+            let output = String::from_utf8(self.output.clone()).unwrap();
+            output.split('$').skip(1).map(|packet| packet.split('#').next().unwrap().to_string()).collect()
+        }
+    }
+
+    impl Read for Session {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Session {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Connection for Session {
+        fn poll_interrupt(&mut self) -> io::Result<bool> {
+            Ok(self.interrupt)
+        }
+    }
+
+    fn serve(cpu: &mut Cpu, bus: &mut WatchBus<DummyBus>, session: &mut Session) -> Vec<String> {
+        GdbStub::new(cpu, bus).serve(session).unwrap();
+        session.replies()
+    }
+
+    #[test]
+    fn test_registers_and_memory() {
+        let mut cpu = Cpu::with_variant(Variant::Wdc65c02);
+        let mut bus = WatchBus::new(DummyBus::new());
+        let mut session = Session::new(&[
+            "qSupported:multiprocess+;swbreak+",
+            "qXfer:features:read:target.xml:0,40",
+            "P4=0002",
+            "P0=7f",
+            "g",
+            "G0102030405060708",
+            "G010203fd3412c1",
+            "p4",
+            "M0010,3:aabbcc",
+            "X0013,2:}]}\u{3}",
+            "m000f,6",
+            "m",
+        ]);
+        let replies = serve(&mut cpu, &mut bus, &mut session);
+
+        assert!(replies[0].contains("qXfer:features:read+"));
+        assert_eq!(replies[1], format!("m{}", &TARGET_XML[..0x40]));
+        assert_eq!(&replies[2..4], ["OK", "OK"]);
+        let reset = Cpu::with_variant(Variant::Wdc65c02);
+        assert_eq!(replies[4], format!("7f0000{:02x}0002{:02x}", reset.stack_pointer(), reset.status()));
+        assert_eq!(&replies[5..8], ["E01", "OK", "3412"]);
+        assert_eq!(cpu.stack_pointer(), 0xFD);
+        assert_eq!(cpu.status() & 0xC1, 0xC1);
+        assert_eq!(&replies[8..], ["OK", "OK", "00aabbcc7d23", "E01"]);
+        assert!(session.output.starts_with(b"+$"));
+    }
+
+    #[test]
+    fn test_breakpoints_and_watchpoints() {
+        let program = Assembler::new(Variant::Nmos6502).assemble("
+            .org $0200
+            loop: INX
+            STX $40
+            LDA $41
+            JMP loop
+        ").unwrap();
+        let mut cpu = Cpu::new();
+        let mut bus = WatchBus::new(DummyBus::new());
+        program.load(bus.bus_mut());
+        cpu.set_program_counter(0x0200);
+
+        let mut session = Session::new(&[
+            "Z0,203,1",
+            "c",
+            "s",
+            "Z1,203,1",
+            "z0,203,1",
+            "c",
+            "z1,203,1",
+            "Z2,40,1",
+            "c",
+            "z2,40,1",
+            "Z3,41,1",
+            "c",
+            "D",
+            "?",
+        ]);
+        let replies = serve(&mut cpu, &mut bus, &mut session);
+        assert_eq!(replies, [
+            "OK", "T05swbreak:;", "S05",
+            "OK", "OK", "T05hwbreak:;",
+            "OK", "OK", "T05watch:40;",
+            "OK", "OK", "T05rwatch:41;",
+            "OK",
+        ]);
+        assert_eq!(cpu.program_counter(), 0x0205);
+        assert_eq!(cpu.breakpoints().count(), 0);
+        assert!(bus.watchpoints().is_empty());
+    }
+
+    #[test]
+    fn test_overlapping_watchpoints() {
+        let program = Assembler::new(Variant::Nmos6502).assemble("
+            .org $0200
+            loop: STX $40
+            LDA $40
+            LDA $41
+            JMP loop
+        ").unwrap();
+        let mut cpu = Cpu::new();
+        let mut bus = WatchBus::new(DummyBus::new());
+        program.load(bus.bus_mut());
+        cpu.set_program_counter(0x0200);
+        cpu.add_breakpoint(0x0206);
+        bus.add_watchpoint(Watchpoint::write(0x80));
+
+        let mut session = Session::new(&[
+            "Z2,40,1",
+            "Z3,40,1",
+            "c",
+            "c",
+            "Z4,41,1",
+            "c",
+            "Z0,206,1",
+            "z0,206,1",
+            "Z2,80,1",
+            "D",
+        ]);
+        let replies = serve(&mut cpu, &mut bus, &mut session);
+        assert_eq!(replies, [
+            "OK", "OK", "T05watch:40;", "T05rwatch:40;",
+            "OK", "T05awatch:41;",
+            "OK", "OK", "OK", "OK",
+        ]);
+        // The breakpoint and the watchpoint set before gdb connected stay.
+        assert_eq!(cpu.breakpoints().collect::<Vec<_>>(), [0x0206]);
+        assert_eq!(bus.watchpoints(), [Watchpoint::write(0x80)]);
+    }
+
+    #[test]
+    fn test_malformed_packets() {
+        let mut cpu = Cpu::new();
+        let mut bus = WatchBus::new(DummyBus::new());
+        bus.bus_mut().write(0x0000, 0x42);
+        let mut session = Session::new(&[
+            "",
+            "mffffffff,2",
+            "m0,10001",
+            "mffff,2",
+            "M10000,1:00",
+            "Z0,10000,1",
+            "Z2,fff0,10000",
+            "Z2,0,10000",
+            "c10000",
+        ]);
+        let replies = serve(&mut cpu, &mut bus, &mut session);
+
+        assert_eq!(replies, ["", "E01", "E01", "0042", "E01", "E01", "OK", "OK", "E01"]);
+        assert_eq!(bus.watchpoints(), [
+            Watchpoint::range(0xFFF0, 0xFFFF, WatchKind::Write),
+            Watchpoint::range(0x0000, 0xFFFF, WatchKind::Write),
+        ]);
+    }
+
+    #[test]
+    fn test_interrupt_and_no_ack() {
+        let mut cpu = Cpu::new();
+        let mut bus = WatchBus::new(DummyBus::new());
+        // JMP $0200
+        bus.bus_mut().write(0x0200, 0x4C);
+        bus.bus_mut().write(0x0202, 0x02);
+        cpu.set_program_counter(0x0200);
+
+        let mut session = Session::new(&["QStartNoAckMode", "c", "k"]);
+        session.interrupt = true;
+        let replies = serve(&mut cpu, &mut bus, &mut session);
+        assert_eq!(replies, ["OK", "S02"]);
+        assert!(session.output.starts_with(b"+$OK#9a$S02#b5"));
+        assert_eq!(cpu.program_counter(), 0x0200);
+    }
+
+    #[test]
+    fn test_tcp_session() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(b"$p3#a3$k#6b").unwrap();
+            let mut reply = Vec::new();
+            stream.read_to_end(&mut reply).unwrap();
+            reply
+        });
+
+        let mut cpu = Cpu::new();
+        let mut bus = WatchBus::new(DummyBus::new());
+        GdbStub::new(&mut cpu, &mut bus).accept(&listener).unwrap();
+        assert_eq!(client.join().unwrap(), b"+$ff#cc+");
+    }
+}
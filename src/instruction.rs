@@ -2,10 +2,31 @@
 use crate::bus::Bus;
 use crate::registers::Registers;
 use crate::registers::StatusRegister;
-use crate::registers::Stack;
 use crate::addressing_modes::Operand;
+use crate::execution_mode::ExecutionMode;
+use crate::variant::Variant;
+use crate::IRQ_VECTOR;
+
+/// Error raised when an instruction is given an operand it cannot work with.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct InvalidOperand;
+
+/// How an instruction accesses its memory operand.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Access {
+    /// The operand is only read.
+    Read,
+    /// The operand is only written.
+    Write,
+    /// The operand is read, modified and written back.
+    ReadModifyWrite,
+    /// JSR, which pushes the return address while fetching its operand.
+    Subroutine,
+    /// The instruction does not access memory through its operand.
+    None,
+}
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Instruction {
     Adc,
     And,
@@ -63,23 +84,110 @@ pub enum Instruction {
     Txa,
     Txs,
     Tya,
+
+    // 65C02 instructions
+    /// Branch always.
+    Bra,
+    Phx,
+    Phy,
+    Plx,
+    Ply,
+    /// Stores zero.
+    Stz,
+    /// Test and reset memory bits with A.
+    Trb,
+    /// Test and set memory bits with A.
+    Tsb,
+    /// Waits for an interrupt.
+    Wai,
+    /// Stops the clock until the processor is reset.
+    Stp,
+    /// Resets a bit of a zero page location (Rockwell).
+    Rmb(u8),
+    /// Sets a bit of a zero page location (Rockwell).
+    Smb(u8),
+    /// Branches if a bit of a zero page location is reset (Rockwell).
+    Bbr(u8),
+    /// Branches if a bit of a zero page location is set (Rockwell).
+    Bbs(u8),
+
+    // Undocumented NMOS instructions
+    /// ASL memory, then ORA with the result.
+    Slo,
+    /// ROL memory, then AND with the result.
+    Rla,
+    /// LSR memory, then EOR with the result.
+    Sre,
+    /// ROR memory, then ADC with the result.
+    Rra,
+    /// Stores A AND X.
+    Sax,
+    /// Loads A and X with the same value.
+    Lax,
+    /// DEC memory, then CMP with the result.
+    Dcp,
+    /// INC memory, then SBC with the result.
+    Isc,
+    /// AND immediate, copying N into C.
+    Anc,
+    /// AND immediate, then LSR A.
+    Alr,
+    /// AND immediate, then ROR A with its own flag rules.
+    Arr,
+    /// Unstable: A = (A OR $EE) AND X AND immediate.
+    Ane,
+    /// Unstable: A = X = (A OR $EE) AND immediate.
+    Lxa,
+    /// X = (A AND X) - immediate, setting flags like CMP.
+    Sbx,
+    /// Unstable: stores A AND X AND (high byte of the base address + 1).
+    Sha,
+    /// Unstable: stores X AND (high byte of the base address + 1).
+    Shx,
+    /// Unstable: stores Y AND (high byte of the base address + 1).
+    Shy,
+    /// Unstable: S = A AND X, then stores like SHA.
+    Tas,
+    /// A = X = S = memory AND S.
+    Las,
+    /// Halts the processor until it is reset. Never reaches `process`, the
+    /// `Cpu` stops before fetching its operand.
+    Jam,
 }
 
 impl Instruction {
-    pub fn process<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers) {
+    ///
+    /// Executes the instruction with the given operand. Returns the number of
+    /// cycles spent on top of the base cycle count of the opcode, which is
+    /// only non-zero for branches that are taken, or an error if the operand
+    /// is not valid for the instruction.
+    ///
+    pub fn process<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers, variant: Variant, mode: ExecutionMode) -> Result<u8, InvalidOperand> {
         match self {
             // Logical operations
-            Instruction::And => self.and(operand, bus, regs),
-            Instruction::Ora => self.or_accumulator(operand, bus, regs),
-            Instruction::Eor => self.exclusive_or(operand, bus, regs),
+            Instruction::And => self.and(operand, bus, regs)?,
+            Instruction::Ora => self.or_accumulator(operand, bus, regs)?,
+            Instruction::Eor => self.exclusive_or(operand, bus, regs)?,
             // Comparison operations
-            Instruction::Cmp => self.compare(operand, bus, regs, regs.accumulator),
-            Instruction::Cpx => self.compare(operand, bus, regs, regs.x_index),
-            Instruction::Cpy => self.compare(operand, bus, regs, regs.y_index),
-            Instruction::Nop => { },
+            Instruction::Cmp => self.compare(operand, bus, regs, regs.accumulator)?,
+            Instruction::Cpx => self.compare(operand, bus, regs, regs.x_index)?,
+            Instruction::Cpy => self.compare(operand, bus, regs, regs.y_index)?,
+            Instruction::Nop => {
+                // The undocumented NOPs with an operand still read it.
+                if let Operand::Addr(addr) = operand {
+                    bus.read(addr);
+                }
+            },
+            Instruction::Jam => { },
+            // The Cpu stops after the instruction, whose last cycle is idle.
+            Instruction::Wai | Instruction::Stp => {
+                if mode.is_cycle_accurate() {
+                    bus.read(regs.program_counter);
+                }
+            },
 
             // Bit manipulation operations
-            Instruction::Bit => self.bit(operand, bus, regs),
+            Instruction::Bit => self.bit(operand, bus, regs)?,
             Instruction::Clc => { regs.status_reg.carry = false; },
             Instruction::Cld => { regs.status_reg.decimal_mode = false; },
             Instruction::Cli => { regs.status_reg.irq_disable = false; },
@@ -89,203 +197,506 @@ impl Instruction {
             Instruction::Sei => { regs.status_reg.irq_disable = true; },
 
             // Arithmetic operations
-            Instruction::Adc => self.add_with_carry(operand, bus, regs),
-            Instruction::Sbc => self.subtract_with_carry(operand, bus, regs),
+            Instruction::Adc => return self.add_with_carry(operand, bus, regs, variant, mode),
+            Instruction::Sbc => return self.subtract_with_carry(operand, bus, regs, variant, mode),
 
             // Increment/Decrement operations
-            Instruction::Inc => self.increment_memory(operand, bus, regs),
+            Instruction::Inc => { self.increment_memory(operand, bus, regs, variant, mode)?; },
             Instruction::Inx => self.increment_register(&mut regs.status_reg, &mut regs.x_index),
             Instruction::Iny => self.increment_register(&mut regs.status_reg, &mut regs.y_index),
-            Instruction::Dec => self.decrement_memory(operand, bus, regs),
+            Instruction::Dec => { self.decrement_memory(operand, bus, regs, variant, mode)?; },
             Instruction::Dex => self.decrement_register(&mut regs.status_reg, &mut regs.x_index),
-            Instruction::Dey => self.decrement_register(&mut regs.status_reg, &mut regs.x_index),
+            Instruction::Dey => self.decrement_register(&mut regs.status_reg, &mut regs.y_index),
 
             // Data shifting instructions
-            Instruction::Lsr => self.logical_shift_right(operand, bus, regs),
-            Instruction::Asl => self.arithmetic_shift_left(operand, bus, regs),
-            Instruction::Rol => self.rotate_left(operand, bus, regs),
-            Instruction::Ror => self.rotate_right(operand, bus, regs),
+            Instruction::Lsr => { self.logical_shift_right(operand, bus, regs, variant, mode)?; },
+            Instruction::Asl => { self.arithmetic_shift_left(operand, bus, regs, variant, mode)?; },
+            Instruction::Rol => { self.rotate_left(operand, bus, regs, variant, mode)?; },
+            Instruction::Ror => { self.rotate_right(operand, bus, regs, variant, mode)?; },
 
             // Jump instructions
-            Instruction::Jmp => self.jump(operand, regs),
+            Instruction::Jmp => self.jump(operand, regs)?,
 
             // Branch instructions
-            Instruction::Bcs => if regs.status_reg.carry { self.jump(operand, regs) },
-            Instruction::Bcc => if !regs.status_reg.carry { self.jump(operand, regs) },
-            Instruction::Beq => if regs.status_reg.zero { self.jump(operand, regs) },
-            Instruction::Bne => if !regs.status_reg.zero { self.jump(operand, regs) },
-            Instruction::Bmi => if regs.status_reg.negative { self.jump(operand, regs) },
-            Instruction::Bpl => if !regs.status_reg.negative { self.jump(operand, regs) },
-            Instruction::Bvs => if regs.status_reg.overflow { self.jump(operand, regs) },
-            Instruction::Bvc => if !regs.status_reg.overflow { self.jump(operand, regs) },
+            Instruction::Bcs => return self.branch(regs.status_reg.carry, operand, bus, regs, mode),
+            Instruction::Bcc => return self.branch(!regs.status_reg.carry, operand, bus, regs, mode),
+            Instruction::Beq => return self.branch(regs.status_reg.zero, operand, bus, regs, mode),
+            Instruction::Bne => return self.branch(!regs.status_reg.zero, operand, bus, regs, mode),
+            Instruction::Bmi => return self.branch(regs.status_reg.negative, operand, bus, regs, mode),
+            Instruction::Bpl => return self.branch(!regs.status_reg.negative, operand, bus, regs, mode),
+            Instruction::Bvs => return self.branch(regs.status_reg.overflow, operand, bus, regs, mode),
+            Instruction::Bvc => return self.branch(!regs.status_reg.overflow, operand, bus, regs, mode),
+            Instruction::Bra => return self.branch(true, operand, bus, regs, mode),
+            Instruction::Bbr(bit) => return self.branch_on_bit(*bit, false, operand, bus, regs, mode),
+            Instruction::Bbs(bit) => return self.branch_on_bit(*bit, true, operand, bus, regs, mode),
 
             // Subroutine instructions
-            Instruction::Jsr => {
-                self.push_pc(regs.program_counter, &mut regs.stack, bus);
-                self.jump(operand, regs);
-            },
+            // The return address was pushed while fetching the operand.
+            Instruction::Jsr => self.jump(operand, regs)?,
             Instruction::Rts => {
-                let pc = self.pop_pc(&mut regs.stack, bus);
-                self.jump(Operand::Addr(pc), regs);
+                self.dummy_stack_read(bus, regs, mode);
+                // JSR pushes the address of the last byte of the instruction.
+                let pc = regs.stack.pop_address(bus);
+                if mode.is_cycle_accurate() {
+                    bus.read(pc);
+                }
+                self.jump(Operand::Addr(pc.wrapping_add(1)), regs)?;
             },
 
             // Interrupt instructions
-            Instruction::Brk => { panic!("Unimplemented Brk"); },
-            Instruction::Rti => { panic!("Unimplemented Rti"); },
+            Instruction::Brk => {
+                // BRK is followed by a padding byte, which is skipped on return.
+                let pc = regs.program_counter.wrapping_add(1);
+                regs.stack.push_address(pc, bus);
+                regs.stack.push(regs.status_reg.get() | 0x30, bus);
+                regs.status_reg.irq_disable = true;
+                if variant.is_cmos() {
+                    regs.status_reg.decimal_mode = false;
+                }
+
+                let lo = bus.read(IRQ_VECTOR) as u16;
+                let hi = bus.read(IRQ_VECTOR + 1) as u16;
+                self.jump(Operand::Addr((hi << 8) | lo), regs)?;
+            },
+            Instruction::Rti => {
+                self.dummy_stack_read(bus, regs, mode);
+                let status = regs.stack.pop(bus);
+                regs.status_reg.set(status);
+                let pc = regs.stack.pop_address(bus);
+                self.jump(Operand::Addr(pc), regs)?;
+            },
 
             // Memory transfer operations
-            Instruction::Lda => self.load_register(operand, bus, &mut regs.status_reg, &mut regs.accumulator),
-            Instruction::Ldx => self.load_register(operand, bus, &mut regs.status_reg, &mut regs.x_index),
-            Instruction::Ldy => self.load_register(operand, bus, &mut regs.status_reg, &mut regs.y_index),
-            Instruction::Sta => self.store_register(operand, bus, regs.accumulator),
-            Instruction::Stx => self.store_register(operand, bus, regs.x_index),
-            Instruction::Sty => self.store_register(operand, bus, regs.y_index),
+            Instruction::Lda => self.load_register(operand, bus, &mut regs.status_reg, &mut regs.accumulator)?,
+            Instruction::Ldx => self.load_register(operand, bus, &mut regs.status_reg, &mut regs.x_index)?,
+            Instruction::Ldy => self.load_register(operand, bus, &mut regs.status_reg, &mut regs.y_index)?,
+            Instruction::Sta => self.store_register(operand, bus, regs.accumulator)?,
+            Instruction::Stx => self.store_register(operand, bus, regs.x_index)?,
+            Instruction::Sty => self.store_register(operand, bus, regs.y_index)?,
+            Instruction::Stz => self.store_register(operand, bus, 0)?,
 
             // Register data transfer operations
-            Instruction::Tax => regs.x_index = regs.accumulator,
-            Instruction::Txa => regs.accumulator = regs.x_index,
-            Instruction::Tay => regs.y_index = regs.accumulator,
-            Instruction::Tya => regs.accumulator = regs.y_index,
-            Instruction::Txs => regs.x_index = regs.stack.get(),
-            Instruction::Tsx => regs.stack.set(regs.x_index),
+            Instruction::Tax => self.load_register(Operand::Value(regs.accumulator), bus, &mut regs.status_reg, &mut regs.x_index)?,
+            Instruction::Txa => self.load_register(Operand::Value(regs.x_index), bus, &mut regs.status_reg, &mut regs.accumulator)?,
+            Instruction::Tay => self.load_register(Operand::Value(regs.accumulator), bus, &mut regs.status_reg, &mut regs.y_index)?,
+            Instruction::Tya => self.load_register(Operand::Value(regs.y_index), bus, &mut regs.status_reg, &mut regs.accumulator)?,
+            Instruction::Tsx => self.load_register(Operand::Value(regs.stack.get()), bus, &mut regs.status_reg, &mut regs.x_index)?,
+            // The only transfer that leaves the flags alone.
+            Instruction::Txs => regs.stack.set(regs.x_index),
 
             // Stack operations
             Instruction::Pha => regs.stack.push(regs.accumulator, bus),
-            Instruction::Pla => regs.accumulator = regs.stack.pop(bus),
-            Instruction::Php => regs.stack.push(regs.status_reg.get(), bus),
-            Instruction::Plp => regs.status_reg.set(regs.stack.pop(bus)),
+            Instruction::Pla => {
+                self.dummy_stack_read(bus, regs, mode);
+                let value = regs.stack.pop(bus);
+                self.load_register(Operand::Value(value), bus, &mut regs.status_reg, &mut regs.accumulator)?;
+            },
+            Instruction::Php => regs.stack.push(regs.status_reg.get() | 0x30, bus),
+            Instruction::Phx => regs.stack.push(regs.x_index, bus),
+            Instruction::Phy => regs.stack.push(regs.y_index, bus),
+            Instruction::Plx => {
+                self.dummy_stack_read(bus, regs, mode);
+                let value = regs.stack.pop(bus);
+                self.load_register(Operand::Value(value), bus, &mut regs.status_reg, &mut regs.x_index)?;
+            },
+            Instruction::Ply => {
+                self.dummy_stack_read(bus, regs, mode);
+                let value = regs.stack.pop(bus);
+                self.load_register(Operand::Value(value), bus, &mut regs.status_reg, &mut regs.y_index)?;
+            },
+            Instruction::Plp => {
+                self.dummy_stack_read(bus, regs, mode);
+                regs.status_reg.set(regs.stack.pop(bus));
+            },
+
+            // Bit test and modify operations of the CMOS variants
+            Instruction::Tsb => self.test_bits(operand, bus, regs, variant, mode, true)?,
+            Instruction::Trb => self.test_bits(operand, bus, regs, variant, mode, false)?,
+            Instruction::Rmb(bit) => {
+                self.read_modify_write(operand, bus, regs, variant, mode, |value, _| *value &= !(1 << *bit))?;
+            },
+            Instruction::Smb(bit) => {
+                self.read_modify_write(operand, bus, regs, variant, mode, |value, _| *value |= 1 << *bit)?;
+            },
+
+            // Undocumented read-modify-write operations
+            Instruction::Slo => {
+                let value = self.arithmetic_shift_left(operand, bus, regs, variant, mode)?;
+                self.or_accumulator(Operand::Value(value), bus, regs)?;
+            },
+            Instruction::Rla => {
+                let value = self.rotate_left(operand, bus, regs, variant, mode)?;
+                self.and(Operand::Value(value), bus, regs)?;
+            },
+            Instruction::Sre => {
+                let value = self.logical_shift_right(operand, bus, regs, variant, mode)?;
+                self.exclusive_or(Operand::Value(value), bus, regs)?;
+            },
+            Instruction::Rra => {
+                let value = self.rotate_right(operand, bus, regs, variant, mode)?;
+                self.add_with_carry(Operand::Value(value), bus, regs, variant, mode)?;
+            },
+            Instruction::Dcp => {
+                let value = self.decrement_memory(operand, bus, regs, variant, mode)?;
+                self.compare(Operand::Value(value), bus, regs, regs.accumulator)?;
+            },
+            Instruction::Isc => {
+                let value = self.increment_memory(operand, bus, regs, variant, mode)?;
+                self.subtract_with_carry(Operand::Value(value), bus, regs, variant, mode)?;
+            },
+
+            // Undocumented load and store operations
+            Instruction::Sax => self.store_register(operand, bus, regs.accumulator & regs.x_index)?,
+            Instruction::Lax => {
+                self.load_register(operand, bus, &mut regs.status_reg, &mut regs.accumulator)?;
+                regs.x_index = regs.accumulator;
+            },
+            Instruction::Las => {
+                let value = self.read_operand(operand, bus)? & regs.stack.get();
+                regs.stack.set(value);
+                self.load_register(Operand::Value(value), bus, &mut regs.status_reg, &mut regs.accumulator)?;
+                regs.x_index = value;
+            },
+            Instruction::Sha => self.store_high_byte_and(operand, bus, regs.accumulator & regs.x_index, regs.y_index)?,
+            Instruction::Shx => self.store_high_byte_and(operand, bus, regs.x_index, regs.y_index)?,
+            Instruction::Shy => self.store_high_byte_and(operand, bus, regs.y_index, regs.x_index)?,
+            Instruction::Tas => {
+                regs.stack.set(regs.accumulator & regs.x_index);
+                self.store_high_byte_and(operand, bus, regs.stack.get(), regs.y_index)?;
+            },
+
+            // Undocumented immediate operations
+            Instruction::Anc => {
+                self.and(operand, bus, regs)?;
+                regs.status_reg.carry = regs.status_reg.negative;
+            },
+            Instruction::Alr => {
+                self.and(operand, bus, regs)?;
+                self.logical_shift_right(Operand::Accumulator, bus, regs, variant, mode)?;
+            },
+            Instruction::Arr => self.and_rotate_right(operand, bus, regs, variant)?,
+            Instruction::Ane => {
+                let value = self.read_operand(operand, bus)?;
+                let result = (regs.accumulator | 0xEE) & regs.x_index & value;
+                self.load_register(Operand::Value(result), bus, &mut regs.status_reg, &mut regs.accumulator)?;
+            },
+            Instruction::Lxa => {
+                let value = self.read_operand(operand, bus)?;
+                let result = (regs.accumulator | 0xEE) & value;
+                self.load_register(Operand::Value(result), bus, &mut regs.status_reg, &mut regs.accumulator)?;
+                regs.x_index = result;
+            },
+            Instruction::Sbx => {
+                let value = self.read_operand(operand, bus)?;
+                let argument = regs.accumulator & regs.x_index;
+                regs.status_reg.carry = argument >= value;
+                regs.x_index = argument.wrapping_sub(value);
+                regs.status_reg.negative = (regs.x_index & 0x80) != 0;
+                regs.status_reg.zero = regs.x_index == 0;
+            },
         }
+        Ok(0)
     }
 
-    fn push_pc<T: Bus>(&self, pc: u16, stack: &mut Stack, bus: &mut T) {
-        let hi = (pc >> 8) as u8;
-        stack.push(hi, bus);
-        let lo = (pc & 0xFF) as u8;
-        stack.push(lo, bus);
+    ///
+    /// Returns the upper case mnemonic of the instruction, with the bit
+    /// number of the Rockwell instructions appended.
+    ///
+    pub fn mnemonic(&self) -> String {
+        match self {
+            Instruction::Rmb(bit) => format!("RMB{}", bit),
+            Instruction::Smb(bit) => format!("SMB{}", bit),
+            Instruction::Bbr(bit) => format!("BBR{}", bit),
+            Instruction::Bbs(bit) => format!("BBS{}", bit),
+            _ => format!("{:?}", self).to_uppercase(),
+        }
     }
 
-    fn pop_pc<T: Bus>(&self, stack: &mut Stack, bus: &mut T) -> u16 {
-        let lo = stack.pop(bus) as u16;
-        let hi = stack.pop(bus) as u16;
-        (hi << 8) | lo
+    ///
+    /// Returns how the instruction accesses the memory pointed by its operand.
+    ///
+    pub fn access(&self) -> Access {
+        match self {
+            Instruction::Adc | Instruction::And | Instruction::Bit | Instruction::Cmp |
+            Instruction::Cpx | Instruction::Cpy | Instruction::Eor | Instruction::Lda |
+            Instruction::Ldx | Instruction::Ldy | Instruction::Ora | Instruction::Sbc |
+            Instruction::Nop | Instruction::Lax | Instruction::Las | Instruction::Anc |
+            Instruction::Alr | Instruction::Arr | Instruction::Ane | Instruction::Lxa |
+            Instruction::Sbx => Access::Read,
+            Instruction::Sta | Instruction::Stx | Instruction::Sty | Instruction::Stz | Instruction::Sax |
+            Instruction::Sha | Instruction::Shx | Instruction::Shy | Instruction::Tas => Access::Write,
+            Instruction::Asl | Instruction::Dec | Instruction::Inc | Instruction::Lsr |
+            Instruction::Rol | Instruction::Ror | Instruction::Slo | Instruction::Rla |
+            Instruction::Sre | Instruction::Rra | Instruction::Dcp | Instruction::Isc |
+            Instruction::Trb | Instruction::Tsb | Instruction::Rmb(_) | Instruction::Smb(_) => Access::ReadModifyWrite,
+            Instruction::Jsr => Access::Subroutine,
+            _ => Access::None,
+        }
     }
 
-    fn and<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers) {
-        let value = match operand {
-            Operand::Value(val) => val,
-            Operand::Addr(addr) => bus.read(addr),
-            Operand::None | Operand::Accumulator => { panic!("AND requires an argument!"); }
-        };
+    ///
+    /// Returns true if the instruction takes an extra cycle when its indexed
+    /// operand address crosses a page boundary. Stores and read-modify-write
+    /// instructions always spend that cycle, so it is part of their base count,
+    /// except for the shifts and rotates of the 65C02.
+    ///
+    pub fn has_page_cross_penalty(&self, variant: Variant) -> bool {
+        match self {
+            Instruction::Asl | Instruction::Lsr | Instruction::Rol | Instruction::Ror => variant.is_cmos(),
+            _ => self.access() == Access::Read,
+        }
+    }
+
+    ///
+    /// Pulling from the stack takes an extra cycle, in which the NMOS chip
+    /// reads the current top of the stack before incrementing the pointer.
+    ///
+    fn dummy_stack_read<T: Bus>(&self, bus: &mut T, regs: &Registers, mode: ExecutionMode) {
+        if mode.is_cycle_accurate() {
+            bus.read(regs.stack.address());
+        }
+    }
+
+    ///
+    /// Runs a read-modify-write operation on memory or on the accumulator.
+    /// When accessing memory, the NMOS chip writes back the unmodified value
+    /// before writing the result, while the CMOS chips read it again.
+    /// Returns the result.
+    ///
+    fn read_modify_write<T, F>(&self, operand: Operand, bus: &mut T, regs: &mut Registers, variant: Variant, mode: ExecutionMode, op: F) -> Result<u8, InvalidOperand>
+        where T: Bus, F: FnOnce(&mut u8, &mut Registers) {
+        match operand {
+            Operand::Addr(addr) => {
+                let mut val = bus.read(addr);
+                if mode.is_cycle_accurate() {
+                    if variant.is_cmos() {
+                        bus.read(addr);
+                    } else {
+                        bus.write(addr, val);
+                    }
+                }
+                op(&mut val, regs);
+                bus.write(addr, val);
+                Ok(val)
+            }
+            Operand::Accumulator => {
+                let mut val = regs.accumulator;
+                op(&mut val, regs);
+                regs.accumulator = val;
+                Ok(val)
+            },
+            _ => Err(InvalidOperand),
+        }
+    }
+
+    ///
+    /// Returns the value of an immediate operand or reads it from memory.
+    ///
+    fn read_operand<T: Bus>(&self, operand: Operand, bus: &mut T) -> Result<u8, InvalidOperand> {
+        match operand {
+            Operand::Value(val) => Ok(val),
+            Operand::Addr(addr) => Ok(bus.read(addr)),
+            _ => Err(InvalidOperand),
+        }
+    }
+
+    fn and<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers) -> Result<(), InvalidOperand> {
+        let value = self.read_operand(operand, bus)?;
 
         regs.accumulator &= value;
         regs.status_reg.zero = regs.accumulator == 0;
         regs.status_reg.negative = (regs.accumulator & 0x80) != 0;
+        Ok(())
     }
 
-    fn or_accumulator<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers) {
-        let argument = match operand {
-            Operand::Value(val) => val,
-            Operand::Addr(addr) => bus.read(addr),
-            Operand::None | Operand::Accumulator => { panic!("No argument for ORA!" ) }
-        };
+    fn or_accumulator<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers) -> Result<(), InvalidOperand> {
+        let argument = self.read_operand(operand, bus)?;
 
         regs.accumulator |= argument;
         regs.status_reg.negative = (regs.accumulator & 0x80) != 0;
         regs.status_reg.zero = regs.accumulator == 0x00;
+        Ok(())
     }
 
-    fn exclusive_or<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers) {
-        let argument = match operand {
-            Operand::Value(val) => val,
-            Operand::Addr(addr) => bus.read(addr),
-            Operand::None | Operand::Accumulator => { panic!("No argument for ORA!" ) }
-        };
+    fn exclusive_or<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers) -> Result<(), InvalidOperand> {
+        let argument = self.read_operand(operand, bus)?;
 
         regs.accumulator ^= argument;
         regs.status_reg.negative = (regs.accumulator & 0x80) != 0;
         regs.status_reg.zero = regs.accumulator == 0x00;
+        Ok(())
     }
 
-    fn compare<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers, val: u8) {
-        let argument = match operand {
-            Operand::Value(val) => val,
-            Operand::Addr(addr) => bus.read(addr),
-            Operand::None | Operand::Accumulator => { panic!("No argument for ORA!" ) }
-        };
+    fn compare<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers, val: u8) -> Result<(), InvalidOperand> {
+        let argument = self.read_operand(operand, bus)?;
 
         regs.status_reg.negative = (val.wrapping_sub(argument) & 0x80) != 0;
         regs.status_reg.zero = val == argument;
         regs.status_reg.carry = val >= argument;
+        Ok(())
     }
 
-    fn bit<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers) {
-        let argument = match operand {
-            Operand::Value(val) => val,
-            Operand::Addr(addr) => bus.read(addr),
-            Operand::None | Operand::Accumulator => { panic!("No argument for bit"); }
-        };
+    ///
+    /// BIT. The immediate form, only available on the CMOS variants, leaves
+    /// N and V untouched.
+    ///
+    fn bit<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers) -> Result<(), InvalidOperand> {
+        let argument = self.read_operand(operand, bus)?;
 
+        if let Operand::Value(_) = operand {
+            regs.status_reg.zero = (argument & regs.accumulator) == 0;
+            return Ok(());
+        }
         regs.status_reg.negative = (argument & 0x80) != 0;
         regs.status_reg.overflow = (argument & 0x40) != 0;
         regs.status_reg.zero = (argument & regs.accumulator) == 0;
+        Ok(())
+    }
+
+    ///
+    /// ADC. Returns the extra cycle the CMOS variants spend in decimal mode.
+    ///
+    fn add_with_carry<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers, variant: Variant, mode: ExecutionMode) -> Result<u8, InvalidOperand> {
+        let value = self.read_operand(operand, bus)?;
+
+        if regs.status_reg.decimal_mode && variant.has_decimal_mode() {
+            self.add_decimal(value, regs);
+            if variant.is_cmos() {
+                return Ok(self.fix_decimal_flags(operand, bus, regs, mode));
+            }
+        } else {
+            self.add_binary(value, regs);
+        }
+        Ok(0)
     }
 
-    fn add_with_carry<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers) {
-        let value = match operand {
-            Operand::Value(val) => val,
-            Operand::Addr(addr) => bus.read(addr),
-            Operand::None | Operand::Accumulator => { panic!("ADC requires an argument!"); }
-        } as u16;
+    ///
+    /// SBC. Returns the extra cycle the CMOS variants spend in decimal mode.
+    ///
+    fn subtract_with_carry<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers, variant: Variant, mode: ExecutionMode) -> Result<u8, InvalidOperand> {
+        let value = self.read_operand(operand, bus)?;
 
-        if regs.status_reg.decimal_mode {
-            panic!("Decimal mode not yet supported for ADC");
+        if regs.status_reg.decimal_mode && variant.has_decimal_mode() {
+            if variant.is_cmos() {
+                self.subtract_decimal_cmos(value, regs);
+                return Ok(self.fix_decimal_flags(operand, bus, regs, mode));
+            }
+            self.subtract_decimal(value, regs);
+        } else {
+            // In binary mode, subtracting is adding the one's complement of the argument.
+            self.add_binary(!value, regs);
         }
+        Ok(0)
+    }
 
-        let mut acc = regs.accumulator as u16 + value;
-        if regs.status_reg.carry {
-            acc += 1;
+    ///
+    /// The CMOS variants spend an extra cycle after a decimal operation to
+    /// set N and Z from the BCD result. Returns that cycle.
+    ///
+    fn fix_decimal_flags<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers, mode: ExecutionMode) -> u8 {
+        if mode.is_cycle_accurate() {
+            match operand {
+                Operand::Addr(addr) => bus.read(addr),
+                _ => bus.read(regs.program_counter),
+            };
         }
+        regs.status_reg.zero = regs.accumulator == 0;
+        regs.status_reg.negative = (regs.accumulator & 0x80) != 0;
+        1
+    }
+
+    fn add_binary(&self, value: u8, regs: &mut Registers) {
+        let acc = regs.accumulator as u16 + value as u16 + regs.status_reg.carry as u16;
+        let result = acc as u8;
 
-        regs.accumulator = acc as u8;
+        regs.status_reg.overflow = ((regs.accumulator ^ result) & (value ^ result) & 0x80) != 0;
         regs.status_reg.carry = acc > 0xFF;
+        regs.accumulator = result;
         regs.status_reg.zero = regs.accumulator == 0;
         regs.status_reg.negative = (regs.accumulator & 0x80) != 0;
     }
 
-    fn subtract_with_carry<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers) {
-        let mut value = match operand {
-            Operand::Value(val) => val,
-            Operand::Addr(addr) => bus.read(addr),
-            Operand::None | Operand::Accumulator => { panic!("SBC requires an argument!"); }
-        } as u16;
+    ///
+    /// NMOS decimal addition. The carry and the result are valid BCD, while
+    /// N and V are taken from the intermediate result before the high nibble
+    /// is adjusted and Z from the binary sum, as the original silicon does.
+    ///
+    fn add_decimal(&self, value: u8, regs: &mut Registers) {
+        let acc = regs.accumulator as u16;
+        let value = value as u16;
+        let carry = regs.status_reg.carry as u16;
+
+        let mut lo = (acc & 0x0F) + (value & 0x0F) + carry;
+        if lo > 0x09 {
+            lo += 0x06;
+        }
+        let mut hi = (acc >> 4) + (value >> 4) + if lo > 0x0F { 1 } else { 0 };
+
+        let intermediate = ((hi << 4) & 0xFF) as u8;
+        regs.status_reg.zero = ((acc + value + carry) & 0xFF) == 0;
+        regs.status_reg.negative = (intermediate & 0x80) != 0;
+        regs.status_reg.overflow = ((regs.accumulator ^ intermediate) & (value as u8 ^ intermediate) & 0x80) != 0;
 
-        if regs.status_reg.decimal_mode {
-            panic!("Decimal mode not yet supported for SBC");
+        if hi > 0x09 {
+            hi += 0x06;
         }
+        regs.status_reg.carry = hi > 0x0F;
+        regs.accumulator = (((hi << 4) | (lo & 0x0F)) & 0xFF) as u8;
+    }
 
-        if !regs.status_reg.carry {
-            value = value.wrapping_add(1);
+    ///
+    /// NMOS decimal subtraction. All flags behave as in binary mode, only the
+    /// result in the accumulator is adjusted to BCD.
+    ///
+    fn subtract_decimal(&self, value: u8, regs: &mut Registers) {
+        let acc = regs.accumulator as i16;
+        let borrow = !regs.status_reg.carry as i16;
+
+        let mut lo = (acc & 0x0F) - (value as i16 & 0x0F) - borrow;
+        let mut hi = (acc >> 4) - (value as i16 >> 4);
+        if lo < 0 {
+            lo -= 0x06;
+            hi -= 1;
+        }
+        if hi < 0 {
+            hi -= 0x06;
         }
+        let result = ((hi << 4) | (lo & 0x0F)) as u8;
 
-        let acc = regs.accumulator as u16 - value;
+        // The flags are set by the binary operation.
+        self.add_binary(!value, regs);
+        regs.accumulator = result;
+    }
 
-        regs.accumulator = acc as u8;
-        regs.status_reg.carry = acc > 0xFF;
-        regs.status_reg.zero = regs.accumulator == 0;
-        regs.status_reg.negative = (regs.accumulator & 0x80) != 0;
+    ///
+    /// CMOS decimal subtraction. The accumulator is adjusted after the binary
+    /// subtraction, which only differs from the NMOS result for invalid BCD
+    /// operands. C and V behave as in binary mode.
+    ///
+    fn subtract_decimal_cmos(&self, value: u8, regs: &mut Registers) {
+        let acc = regs.accumulator as i16;
+        let borrow = !regs.status_reg.carry as i16;
+
+        let lo = (acc & 0x0F) - (value as i16 & 0x0F) - borrow;
+        let mut result = acc - value as i16 - borrow;
+        if result < 0 {
+            result -= 0x60;
+        }
+        if lo < 0 {
+            result -= 0x06;
+        }
+
+        self.add_binary(!value, regs);
+        regs.accumulator = result as u8;
     }
 
-    fn increment_memory<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers) {
-        if let Operand::Addr(addr) = operand {
-            let mut value = bus.read(addr);
-            value = value.wrapping_add(1);
-            bus.write(addr, value);
+    fn increment_memory<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers, variant: Variant, mode: ExecutionMode) -> Result<u8, InvalidOperand> {
+        self.read_modify_write(operand, bus, regs, variant, mode, |value, regs| {
+            *value = value.wrapping_add(1);
 
-            regs.status_reg.negative = (value & 0x80) != 0;
-            regs.status_reg.zero = value == 0;
-        } else {
-            panic!("INC requires an address!")
-        }
+            regs.status_reg.negative = (*value & 0x80) != 0;
+            regs.status_reg.zero = *value == 0;
+        })
     }
 
     fn increment_register(&self, status_reg: &mut StatusRegister, reg: &mut u8) {
@@ -295,17 +706,13 @@ impl Instruction {
         status_reg.zero = *reg == 0;
     }
 
-    fn decrement_memory<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers) {
-        if let Operand::Addr(addr) = operand {
-            let mut value = bus.read(addr);
-            value = value.wrapping_sub(1);
-            bus.write(addr, value);
+    fn decrement_memory<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers, variant: Variant, mode: ExecutionMode) -> Result<u8, InvalidOperand> {
+        self.read_modify_write(operand, bus, regs, variant, mode, |value, regs| {
+            *value = value.wrapping_sub(1);
 
-            regs.status_reg.negative = (value & 0x80) != 0;
-            regs.status_reg.zero = value == 0;
-        } else {
-            panic!("INC requires an address!")
-        }
+            regs.status_reg.negative = (*value & 0x80) != 0;
+            regs.status_reg.zero = *value == 0;
+        })
     }
 
     fn decrement_register(&self, status_reg: &mut StatusRegister, reg: &mut u8) {
@@ -315,30 +722,18 @@ impl Instruction {
         status_reg.zero = *reg == 0;
     }
 
-    fn logical_shift_right<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers) {
-        let shift_op = | status_reg: &mut StatusRegister, value: &mut u8 | {
-            status_reg.carry = (*value & 0x01) != 0;
+    fn logical_shift_right<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers, variant: Variant, mode: ExecutionMode) -> Result<u8, InvalidOperand> {
+        let shift_op = | value: &mut u8, regs: &mut Registers | {
+            regs.status_reg.carry = (*value & 0x01) != 0;
             *value >>= 1;
-            status_reg.zero = *value == 0;
-            status_reg.negative = false;
+            regs.status_reg.zero = *value == 0;
+            regs.status_reg.negative = false;
         };
 
-        match operand {
-            Operand::Addr(addr) => {
-                let mut val = bus.read(addr);
-                shift_op(&mut regs.status_reg, &mut val);
-                bus.write(addr, val);
-            }
-            Operand::Accumulator => {
-                let mut val = regs.accumulator;
-                shift_op(&mut regs.status_reg, &mut val);
-                regs.accumulator = val;
-            },
-            Operand::None | Operand::Value(_) => { panic!("Invalid operand None for LSR"); }
-        };
+        self.read_modify_write(operand, bus, regs, variant, mode, shift_op)
     }
 
-    fn arithmetic_shift_left<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers) {
+    fn arithmetic_shift_left<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers, variant: Variant, mode: ExecutionMode) -> Result<u8, InvalidOperand> {
         let shift_op = | value: &mut u8, regs: &mut Registers| {
             regs.status_reg.carry = (*value & 0x80) != 0;
             *value <<= 1;
@@ -346,22 +741,10 @@ impl Instruction {
             regs.status_reg.negative = (*value & 0x80) != 0;
         };
 
-        match operand {
-            Operand::Addr(addr) => {
-                let mut val = bus.read(addr);
-                shift_op(&mut val, regs);
-                bus.write(addr, val);
-            }
-            Operand::Accumulator => {
-                let mut val = regs.accumulator;
-                shift_op(&mut val, regs);
-                regs.accumulator = val;
-            },
-            Operand::None | Operand::Value(_) => { panic!("Invalid operand None for ASL"); }
-        };
+        self.read_modify_write(operand, bus, regs, variant, mode, shift_op)
     }
 
-    fn rotate_left<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers) {
+    fn rotate_left<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers, variant: Variant, mode: ExecutionMode) -> Result<u8, InvalidOperand> {
         let shift_op = | value: &mut u8, regs: &mut Registers| {
             let carry = regs.status_reg.carry;
             regs.status_reg.carry = (*value & 0x80) != 0;
@@ -373,22 +756,10 @@ impl Instruction {
             regs.status_reg.negative = (*value & 0x80) != 0;
         };
 
-        match operand {
-            Operand::Addr(addr) => {
-                let mut val = bus.read(addr);
-                shift_op(&mut val, regs);
-                bus.write(addr, val);
-            }
-            Operand::Accumulator => {
-                let mut val = regs.accumulator;
-                shift_op(&mut val, regs);
-                regs.accumulator = val;
-            },
-            Operand::None | Operand::Value(_) => { panic!("Invalid operand None for ROL"); }
-        };
+        self.read_modify_write(operand, bus, regs, variant, mode, shift_op)
     }
 
-    fn rotate_right<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers) {
+    fn rotate_right<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers, variant: Variant, mode: ExecutionMode) -> Result<u8, InvalidOperand> {
         let shift_op = | value: &mut u8, regs: &mut Registers| {
             let carry = regs.status_reg.carry;
             regs.status_reg.carry = (*value & 0x01) != 0;
@@ -400,44 +771,142 @@ impl Instruction {
             regs.status_reg.negative = (*value & 0x80) != 0;
         };
 
-        match operand {
-            Operand::Addr(addr) => {
-                let mut val = bus.read(addr);
-                shift_op(&mut val, regs);
-                bus.write(addr, val);
+        self.read_modify_write(operand, bus, regs, variant, mode, shift_op)
+    }
+
+    ///
+    /// ARR: AND followed by ROR of the accumulator. C and V come from bits 6
+    /// and 5 of the result. In decimal mode, N and Z are taken before the
+    /// result is adjusted to BCD and C is set by the high nibble fix-up.
+    ///
+    fn and_rotate_right<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers, variant: Variant) -> Result<(), InvalidOperand> {
+        let value = regs.accumulator & self.read_operand(operand, bus)?;
+        let mut result = (value >> 1) | ((regs.status_reg.carry as u8) << 7);
+
+        regs.status_reg.negative = (result & 0x80) != 0;
+        regs.status_reg.zero = result == 0;
+        regs.status_reg.overflow = ((value ^ result) & 0x40) != 0;
+
+        if regs.status_reg.decimal_mode && variant.has_decimal_mode() {
+            if (value & 0x0F) + (value & 0x01) > 0x05 {
+                result = (result & 0xF0) | (result.wrapping_add(0x06) & 0x0F);
             }
-            Operand::Accumulator => {
-                let mut val = regs.accumulator;
-                shift_op(&mut val, regs);
-                regs.accumulator = val;
-            },
-            Operand::None | Operand::Value(_) => { panic!("Invalid operand None for ROL"); }
-        };
+            regs.status_reg.carry = (value & 0xF0) as u16 + (value & 0x10) as u16 > 0x50;
+            if regs.status_reg.carry {
+                result = result.wrapping_add(0x60);
+            }
+        } else {
+            regs.status_reg.carry = (result & 0x40) != 0;
+        }
+        regs.accumulator = result;
+        Ok(())
+    }
+
+    ///
+    /// Stores the value ANDed with the high byte of the base address plus one,
+    /// as SHA, SHX, SHY and TAS do. If indexing crosses a page, the high byte
+    /// of the target address is replaced by the stored value.
+    ///
+    fn store_high_byte_and<T: Bus>(&self, operand: Operand, bus: &mut T, value: u8, index: u8) -> Result<(), InvalidOperand> {
+        if let Operand::Addr(addr) = operand {
+            let base = addr.wrapping_sub(index as u16);
+            let value = value & ((base >> 8) as u8).wrapping_add(1);
+            let addr = if (base & 0xFF00) != (addr & 0xFF00) {
+                ((value as u16) << 8) | (addr & 0x00FF)
+            } else {
+                addr
+            };
+            bus.write(addr, value);
+            Ok(())
+        } else {
+            Err(InvalidOperand)
+        }
+    }
+
+    ///
+    /// TSB and TRB: Z is set from A AND memory, then the bits set in A are
+    /// set or reset in memory.
+    ///
+    fn test_bits<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers, variant: Variant, mode: ExecutionMode, set: bool) -> Result<(), InvalidOperand> {
+        self.read_modify_write(operand, bus, regs, variant, mode, |value, regs| {
+            regs.status_reg.zero = (*value & regs.accumulator) == 0;
+            if set {
+                *value |= regs.accumulator;
+            } else {
+                *value &= !regs.accumulator;
+            }
+        })?;
+        Ok(())
+    }
+
+    ///
+    /// BBR and BBS: reads the zero page location and branches if the bit
+    /// matches. The location is read twice, like the CMOS read-modify-write
+    /// instructions do.
+    ///
+    fn branch_on_bit<T: Bus>(&self, bit: u8, set: bool, operand: Operand, bus: &mut T, regs: &mut Registers, mode: ExecutionMode) -> Result<u8, InvalidOperand> {
+        if let Operand::ZeropageRelative(addr, target) = operand {
+            let value = bus.read(addr);
+            if mode.is_cycle_accurate() {
+                bus.read(addr);
+            }
+            let condition = ((value >> bit) & 0x01 != 0) == set;
+            self.branch(condition, Operand::Addr(target), bus, regs, mode)
+        } else {
+            Err(InvalidOperand)
+        }
     }
 
-    fn jump(&self, operand: Operand, regs: &mut Registers) {
+    fn jump(&self, operand: Operand, regs: &mut Registers) -> Result<(), InvalidOperand> {
         if let Operand::Addr(addr) = operand {
             regs.program_counter = addr;
+            Ok(())
         } else {
-            panic!("jump called with invalid operand");
+            Err(InvalidOperand)
         }
     }
 
-    fn load_register<T: Bus>(&self, operand: Operand, bus: &mut T, status_reg: &mut StatusRegister, reg: &mut u8) {
-        *reg = match operand {
-            Operand::Value(value) => value,
-            Operand::Addr(addr) => bus.read(addr),
-            Operand::Accumulator | Operand::None => { panic!("LD: invalid operand"); }
-        };
+    ///
+    /// Takes the branch if the condition holds. Returns the extra cycles
+    /// spent: one for a taken branch and another one if it lands in a
+    /// different page.
+    ///
+    fn branch<T: Bus>(&self, condition: bool, operand: Operand, bus: &mut T, regs: &mut Registers, mode: ExecutionMode) -> Result<u8, InvalidOperand> {
+        if !condition {
+            return Ok(0);
+        }
+
+        let pc = regs.program_counter;
+        self.jump(operand, regs)?;
+        let target = regs.program_counter;
+        // While adding the offset, the NMOS chip fetches the next opcode and,
+        // if the page changes, reads again before fixing the high byte.
+        if mode.is_cycle_accurate() {
+            bus.read(pc);
+        }
+        if (pc & 0xFF00) != (target & 0xFF00) {
+            if mode.is_cycle_accurate() {
+                bus.read((pc & 0xFF00) | (target & 0x00FF));
+            }
+            Ok(2)
+        } else {
+            Ok(1)
+        }
+    }
+
+    fn load_register<T: Bus>(&self, operand: Operand, bus: &mut T, status_reg: &mut StatusRegister, reg: &mut u8) -> Result<(), InvalidOperand> {
+        *reg = self.read_operand(operand, bus)?;
         status_reg.negative = (*reg & 0x80) != 0;
         status_reg.zero = *reg == 0;
+        Ok(())
     }
 
-    fn store_register<T: Bus>(&self, operand: Operand, bus: &mut T, reg: u8) {
+    fn store_register<T: Bus>(&self, operand: Operand, bus: &mut T, reg: u8) -> Result<(), InvalidOperand> {
         if let Operand::Addr(addr) = operand {
             bus.write(addr, reg);
+            Ok(())
         } else {
-            panic!("Store operation: Invalid operand");
+            Err(InvalidOperand)
         }
     }
 }
@@ -455,31 +924,30 @@ mod test {
         regs.accumulator = 10;
 
         let adc = Instruction::Adc;
-        adc.process(operand, &mut bus, &mut regs);
+        adc.process(operand, &mut bus, &mut regs, Variant::Nmos6502, ExecutionMode::Fast).unwrap();
 
         assert_eq!(regs.accumulator, 140);
-        assert_eq!(regs.status_reg.carry, false);
-        assert_eq!(regs.status_reg.negative, true);
-        assert_eq!(regs.status_reg.zero, false);
+        assert!(!regs.status_reg.carry);
+        assert!(regs.status_reg.negative);
+        assert!(!regs.status_reg.zero);
 
         let operand = Operand::Addr(0x1234u16);
         bus.write(0x1234u16, 240);
-        adc.process(operand, &mut bus, &mut regs);
+        adc.process(operand, &mut bus, &mut regs, Variant::Nmos6502, ExecutionMode::Fast).unwrap();
 
         assert_eq!(regs.accumulator, 124);
-        assert_eq!(regs.status_reg.carry, true);
-        assert_eq!(regs.status_reg.negative, false);
-        assert_eq!(regs.status_reg.zero, false);
+        assert!(regs.status_reg.carry);
+        assert!(!regs.status_reg.negative);
+        assert!(!regs.status_reg.zero);
     }
 
     #[test]
-    #[should_panic]
     fn adc_test_none_argument() {
         let mut regs = Registers::new();
         let mut bus = DummyBus::new();
         let operand = Operand::None;
         let adc = Instruction::Adc;
-        adc.process(operand, &mut bus, &mut regs);
+        assert_eq!(adc.process(operand, &mut bus, &mut regs, Variant::Nmos6502, ExecutionMode::Fast), Err(InvalidOperand));
     }
 
     #[test]
@@ -490,11 +958,11 @@ mod test {
         regs.accumulator = 0xF0;
         regs.status_reg.negative = true;
         let and = Instruction::And;
-        and.process(operand, &mut bus, &mut regs);
+        and.process(operand, &mut bus, &mut regs, Variant::Nmos6502, ExecutionMode::Fast).unwrap();
 
         assert_eq!(regs.accumulator, 0x70);
-        assert_eq!(regs.status_reg.negative, false);
-        assert_eq!(regs.status_reg.zero, false);
+        assert!(!regs.status_reg.negative);
+        assert!(!regs.status_reg.zero);
     }
 
     #[test]
@@ -505,10 +973,321 @@ mod test {
         regs.accumulator = 0xF0;
         regs.status_reg.negative = true;
         let and = Instruction::And;
-        and.process(operand, &mut bus, &mut regs);
+        and.process(operand, &mut bus, &mut regs, Variant::Nmos6502, ExecutionMode::Fast).unwrap();
 
         assert_eq!(regs.accumulator, 0x70);
-        assert_eq!(regs.status_reg.negative, false);
-        assert_eq!(regs.status_reg.zero, false);
+        assert!(!regs.status_reg.negative);
+        assert!(!regs.status_reg.zero);
+    }
+
+    #[test]
+    fn brk_test() {
+        let mut regs = Registers::new();
+        let mut bus = DummyBus::new();
+        bus.write(0xFFFE, 0x00);
+        bus.write(0xFFFF, 0x80);
+        // The program counter points to the padding byte after the opcode.
+        regs.program_counter = 0x1235;
+        regs.status_reg.carry = true;
+
+        let brk = Instruction::Brk;
+        brk.process(Operand::None, &mut bus, &mut regs, Variant::Nmos6502, ExecutionMode::Fast).unwrap();
+
+        assert_eq!(regs.program_counter, 0x8000);
+        assert!(regs.status_reg.irq_disable);
+        assert_eq!(regs.stack.get(), 0xFC);
+        assert_eq!(bus.read(0x1FF), 0x12);
+        assert_eq!(bus.read(0x1FE), 0x36);
+        assert_eq!(bus.read(0x1FD), 0x31);
+    }
+
+    #[test]
+    fn rti_test() {
+        let mut regs = Registers::new();
+        let mut bus = DummyBus::new();
+        regs.stack.push(0x12, &mut bus);
+        regs.stack.push(0x36, &mut bus);
+        regs.stack.push(0xC3, &mut bus);
+
+        let rti = Instruction::Rti;
+        rti.process(Operand::None, &mut bus, &mut regs, Variant::Nmos6502, ExecutionMode::Fast).unwrap();
+
+        assert_eq!(regs.program_counter, 0x1236);
+        assert_eq!(regs.stack.get(), 0xFF);
+        assert!(regs.status_reg.negative);
+        assert!(regs.status_reg.overflow);
+        assert!(regs.status_reg.zero);
+        assert!(regs.status_reg.carry);
+        assert!(!regs.status_reg.irq_disable);
+    }
+
+    #[test]
+    fn transfer_test() {
+        let mut regs = Registers::new();
+        let mut bus = DummyBus::new();
+        regs.accumulator = 0x80;
+
+        Instruction::Tax.process(Operand::None, &mut bus, &mut regs, Variant::Nmos6502, ExecutionMode::Fast).unwrap();
+        assert_eq!(regs.x_index, 0x80);
+        assert!(regs.status_reg.negative);
+
+        Instruction::Txs.process(Operand::None, &mut bus, &mut regs, Variant::Nmos6502, ExecutionMode::Fast).unwrap();
+        assert_eq!(regs.stack.get(), 0x80);
+
+        regs.stack.set(0x00);
+        Instruction::Tsx.process(Operand::None, &mut bus, &mut regs, Variant::Nmos6502, ExecutionMode::Fast).unwrap();
+        assert_eq!(regs.x_index, 0x00);
+        assert!(regs.status_reg.zero);
+        assert!(!regs.status_reg.negative);
+
+        regs.y_index = 0x01;
+        Instruction::Dey.process(Operand::None, &mut bus, &mut regs, Variant::Nmos6502, ExecutionMode::Fast).unwrap();
+        assert_eq!(regs.y_index, 0x00);
+        assert_eq!(regs.x_index, 0x00);
+        assert!(regs.status_reg.zero);
+    }
+
+    #[test]
+    fn pla_test() {
+        let mut regs = Registers::new();
+        let mut bus = DummyBus::new();
+        regs.stack.push(0xFE, &mut bus);
+
+        Instruction::Pla.process(Operand::None, &mut bus, &mut regs, Variant::Nmos6502, ExecutionMode::Fast).unwrap();
+        assert_eq!(regs.accumulator, 0xFE);
+        assert!(regs.status_reg.negative);
+        assert!(!regs.status_reg.zero);
+    }
+
+    #[test]
+    fn slo_test() {
+        let mut regs = Registers::new();
+        let mut bus = DummyBus::new();
+        bus.write(0x0010, 0x81);
+        regs.accumulator = 0x10;
+
+        Instruction::Slo.process(Operand::Addr(0x0010), &mut bus, &mut regs, Variant::Nmos6502Undocumented, ExecutionMode::Fast).unwrap();
+        assert_eq!(bus.read(0x0010), 0x02);
+        assert_eq!(regs.accumulator, 0x12);
+        assert!(regs.status_reg.carry);
+        assert!(!regs.status_reg.negative);
+    }
+
+    #[test]
+    fn isc_test() {
+        let mut regs = Registers::new();
+        let mut bus = DummyBus::new();
+        bus.write(0x0010, 0x0F);
+        regs.accumulator = 0x20;
+        regs.status_reg.carry = true;
+
+        Instruction::Isc.process(Operand::Addr(0x0010), &mut bus, &mut regs, Variant::Nmos6502Undocumented, ExecutionMode::Fast).unwrap();
+        assert_eq!(bus.read(0x0010), 0x10);
+        assert_eq!(regs.accumulator, 0x10);
+        assert!(regs.status_reg.carry);
+    }
+
+    #[test]
+    fn arr_test() {
+        let mut regs = Registers::new();
+        let mut bus = DummyBus::new();
+        regs.accumulator = 0xFF;
+        regs.status_reg.carry = true;
+
+        Instruction::Arr.process(Operand::Value(0xC0), &mut bus, &mut regs, Variant::Nmos6502Undocumented, ExecutionMode::Fast).unwrap();
+        assert_eq!(regs.accumulator, 0xE0);
+        assert!(regs.status_reg.carry);
+        assert!(!regs.status_reg.overflow);
+        assert!(regs.status_reg.negative);
+
+        // In decimal mode both nibbles of $99 AND $99 need fixing up.
+        let regs = decimal_op(Instruction::Arr, 0x99, 0x99, false);
+        assert_eq!(regs.accumulator, 0xA2);
+        assert!(regs.status_reg.carry);
+        assert!(!regs.status_reg.negative);
+    }
+
+    #[test]
+    fn sbx_test() {
+        let mut regs = Registers::new();
+        let mut bus = DummyBus::new();
+        regs.accumulator = 0xF3;
+        regs.x_index = 0x3F;
+
+        Instruction::Sbx.process(Operand::Value(0x34), &mut bus, &mut regs, Variant::Nmos6502Undocumented, ExecutionMode::Fast).unwrap();
+        assert_eq!(regs.x_index, 0xFF);
+        assert_eq!(regs.accumulator, 0xF3);
+        assert!(!regs.status_reg.carry);
+        assert!(regs.status_reg.negative);
+    }
+
+    #[test]
+    fn shx_test() {
+        let mut regs = Registers::new();
+        let mut bus = DummyBus::new();
+        regs.x_index = 0xFF;
+        regs.y_index = 0x10;
+
+        // No page crossing: the value is masked with $12 + 1.
+        Instruction::Shx.process(Operand::Addr(0x1210), &mut bus, &mut regs, Variant::Nmos6502Undocumented, ExecutionMode::Fast).unwrap();
+        assert_eq!(bus.read(0x1210), 0x13);
+
+        // Crossing from $12F8: the high byte of the address becomes the value.
+        regs.x_index = 0x05;
+        Instruction::Shx.process(Operand::Addr(0x1308), &mut bus, &mut regs, Variant::Nmos6502Undocumented, ExecutionMode::Fast).unwrap();
+        assert_eq!(bus.read(0x0108), 0x01);
+    }
+
+    fn decimal_op(instruction: Instruction, acc: u8, value: u8, carry: bool) -> Registers {
+        let mut regs = Registers::new();
+        let mut bus = DummyBus::new();
+        regs.accumulator = acc;
+        regs.status_reg.carry = carry;
+        regs.status_reg.decimal_mode = true;
+        instruction.process(Operand::Value(value), &mut bus, &mut regs, Variant::Nmos6502, ExecutionMode::Fast).unwrap();
+        regs
+    }
+
+    #[test]
+    fn adc_binary_overflow_test() {
+        let mut regs = Registers::new();
+        let mut bus = DummyBus::new();
+        regs.accumulator = 0x50;
+
+        Instruction::Adc.process(Operand::Value(0x50), &mut bus, &mut regs, Variant::Nmos6502, ExecutionMode::Fast).unwrap();
+        assert_eq!(regs.accumulator, 0xA0);
+        assert!(regs.status_reg.overflow);
+        assert!(!regs.status_reg.carry);
+
+        Instruction::Adc.process(Operand::Value(0x90), &mut bus, &mut regs, Variant::Nmos6502, ExecutionMode::Fast).unwrap();
+        assert_eq!(regs.accumulator, 0x30);
+        assert!(regs.status_reg.overflow);
+        assert!(regs.status_reg.carry);
+    }
+
+    #[test]
+    fn sbc_binary_test() {
+        let mut regs = Registers::new();
+        let mut bus = DummyBus::new();
+        regs.accumulator = 0x50;
+        regs.status_reg.carry = true;
+
+        Instruction::Sbc.process(Operand::Value(0xB0), &mut bus, &mut regs, Variant::Nmos6502, ExecutionMode::Fast).unwrap();
+        assert_eq!(regs.accumulator, 0xA0);
+        assert!(regs.status_reg.overflow);
+        assert!(!regs.status_reg.carry);
+        assert!(regs.status_reg.negative);
+
+        Instruction::Sbc.process(Operand::Value(0x9F), &mut bus, &mut regs, Variant::Nmos6502, ExecutionMode::Fast).unwrap();
+        assert_eq!(regs.accumulator, 0x00);
+        assert!(!regs.status_reg.overflow);
+        assert!(regs.status_reg.carry);
+        assert!(regs.status_reg.zero);
+    }
+
+    #[test]
+    fn adc_decimal_test() {
+        let regs = decimal_op(Instruction::Adc, 0x12, 0x34, false);
+        assert_eq!(regs.accumulator, 0x46);
+        assert!(!regs.status_reg.carry);
+
+        let regs = decimal_op(Instruction::Adc, 0x58, 0x46, true);
+        assert_eq!(regs.accumulator, 0x05);
+        assert!(regs.status_reg.carry);
+
+        let regs = decimal_op(Instruction::Adc, 0x81, 0x92, false);
+        assert_eq!(regs.accumulator, 0x73);
+        assert!(regs.status_reg.carry);
+        assert!(regs.status_reg.overflow);
+        assert!(!regs.status_reg.negative);
+
+        // Undocumented NMOS behaviour: Z comes from the binary sum and N
+        // from the intermediate result.
+        let regs = decimal_op(Instruction::Adc, 0x99, 0x01, false);
+        assert_eq!(regs.accumulator, 0x00);
+        assert!(regs.status_reg.carry);
+        assert!(!regs.status_reg.zero);
+        assert!(regs.status_reg.negative);
+    }
+
+    #[test]
+    fn sbc_decimal_test() {
+        let regs = decimal_op(Instruction::Sbc, 0x46, 0x12, true);
+        assert_eq!(regs.accumulator, 0x34);
+        assert!(regs.status_reg.carry);
+
+        let regs = decimal_op(Instruction::Sbc, 0x40, 0x13, true);
+        assert_eq!(regs.accumulator, 0x27);
+        assert!(regs.status_reg.carry);
+
+        let regs = decimal_op(Instruction::Sbc, 0x32, 0x02, false);
+        assert_eq!(regs.accumulator, 0x29);
+        assert!(regs.status_reg.carry);
+
+        let regs = decimal_op(Instruction::Sbc, 0x12, 0x21, true);
+        assert_eq!(regs.accumulator, 0x91);
+        assert!(!regs.status_reg.carry);
+        assert!(regs.status_reg.negative);
+    }
+
+    #[test]
+    fn sbc_decimal_cmos_test() {
+        let mut regs = Registers::new();
+        let mut bus = DummyBus::new();
+        regs.accumulator = 0x00;
+        regs.status_reg.carry = true;
+        regs.status_reg.decimal_mode = true;
+
+        let extra = Instruction::Sbc.process(Operand::Value(0x01), &mut bus, &mut regs, Variant::Wdc65c02, ExecutionMode::Fast).unwrap();
+        assert_eq!(extra, 1);
+        assert_eq!(regs.accumulator, 0x99);
+        assert!(!regs.status_reg.carry);
+        assert!(regs.status_reg.negative);
+        assert!(!regs.status_reg.zero);
+    }
+
+    ///
+    /// Checks every combination of operands against the reference sequences
+    /// used by Bruce Clark's decimal mode test for the NMOS 6502.
+    ///
+    #[test]
+    fn decimal_mode_exhaustive_test() {
+        for a in 0..=0xFFu8 {
+            for b in 0..=0xFFu8 {
+                for &c in [false, true].iter() {
+                    let (al, ah, bl, bh, ci) = (a as i16 & 0x0F, a as i16 & 0xF0, b as i16 & 0x0F, b as i16 & 0xF0, c as i16);
+
+                    // ADC, sequence 1 for A and C, sequence 2 for N and V.
+                    let mut lo = al + bl + ci;
+                    if lo >= 0x0A {
+                        lo = ((lo + 0x06) & 0x0F) + 0x10;
+                    }
+                    let mut sum = ah + bh + lo;
+                    let signed = (ah as u8 as i8) as i16 + (bh as u8 as i8) as i16 + lo;
+                    if sum >= 0xA0 {
+                        sum += 0x60;
+                    }
+                    let regs = decimal_op(Instruction::Adc, a, b, c);
+                    assert_eq!(regs.accumulator, sum as u8, "ADC {:02X} {:02X} {}", a, b, c);
+                    assert_eq!(regs.status_reg.carry, sum >= 0x100, "ADC {:02X} {:02X} {}", a, b, c);
+                    assert_eq!(regs.status_reg.negative, (signed & 0x80) != 0, "ADC {:02X} {:02X} {}", a, b, c);
+                    assert_eq!(regs.status_reg.overflow, !(-128..=127).contains(&signed), "ADC {:02X} {:02X} {}", a, b, c);
+                    assert_eq!(regs.status_reg.zero, a.wrapping_add(b).wrapping_add(c as u8) == 0);
+
+                    // SBC, sequence 3 for A, the flags come from binary mode.
+                    let mut lo = al - bl + ci - 1;
+                    if lo < 0 {
+                        lo = ((lo - 0x06) & 0x0F) - 0x10;
+                    }
+                    let mut diff = ah - bh + lo;
+                    if diff < 0 {
+                        diff -= 0x60;
+                    }
+                    let regs = decimal_op(Instruction::Sbc, a, b, c);
+                    assert_eq!(regs.accumulator, diff as u8, "SBC {:02X} {:02X} {}", a, b, c);
+                    assert_eq!(regs.status_reg.carry, a as i16 + ci > b as i16, "SBC {:02X} {:02X} {}", a, b, c);
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,293 @@
+use crate::bus::Bus;
+use crate::snapshot::IoPortSnapshot;
+
+/// Bits of the port. It is 6 bits wide, bits 6 and 7 read as zero.
+const PORT_BITS: u8 = 0x3F;
+/// Bits of the port wired to the memory configuration of the C64: LORAM,
+/// HIRAM and CHAREN.
+pub const BANKING_BITS: u8 = 0x07;
+/// Bits with a pull-up resistor on the C64, which read as set when they
+/// are configured as inputs.
+const PULL_UPS: u8 = 0x17;
+/// Number of cycles a floating input keeps the last value driven on it.
+const FADE_OUT_CYCLES: u64 = 350_000;
+
+/// On-chip 6 bit I/O port of the MOS 6510. The data direction register is
+/// mapped at $0000 and the data register at $0001.
+pub struct IoPort {
+    direction: u8,
+    data: u8,
+    pull_ups: u8,
+    floating: u8,
+    fade_out: [u64; 6],
+    banking: u8,
+    on_banking_change: Option<Box<dyn FnMut(u8)>>,
+}
+
+impl IoPort {
+    ///
+    /// Constructs a port in its power-on state, where every bit is an
+    /// input, with the pull-ups of the C64.
+    ///
+    pub fn new() -> IoPort {
+        IoPort {
+            direction: 0x00,
+            data: 0x00,
+            pull_ups: PULL_UPS,
+            floating: 0x00,
+            fade_out: [0; 6],
+            banking: PULL_UPS & BANKING_BITS,
+            on_banking_change: None,
+        }
+    }
+
+    /// Returns the data direction register. Set bits are outputs.
+    pub fn direction(&self) -> u8 {
+        self.direction
+    }
+
+    /// Returns the data register, the value driven on the output bits.
+    pub fn data(&self) -> u8 {
+        self.data
+    }
+
+    /// Returns the bits that read as set when configured as inputs.
+    pub fn pull_ups(&self) -> u8 {
+        self.pull_ups
+    }
+
+    ///
+    /// Selects the bits that read as set when configured as inputs. The
+    /// other input bits float: they keep the last value driven on them for
+    /// a while and then fade out to zero.
+    ///
+    pub fn set_pull_ups(&mut self, pull_ups: u8) {
+        self.pull_ups = pull_ups & PORT_BITS;
+    }
+
+    ///
+    /// Returns the LORAM, HIRAM and CHAREN bits, as last seen by the
+    /// memory configuration logic.
+    ///
+    pub fn banking(&self) -> u8 {
+        self.banking
+    }
+
+    ///
+    /// Registers a callback that receives the LORAM, HIRAM and CHAREN bits
+    /// whenever a write to the port changes them.
+    ///
+    pub fn set_banking_callback<F: FnMut(u8) + 'static>(&mut self, callback: F) {
+        self.on_banking_change = Some(Box::new(callback));
+    }
+
+    ///
+    /// Returns the level of the port pins at the given cycle, as read from
+    /// $0001.
+    ///
+    pub fn pins(&self, cycles: u64) -> u8 {
+        let mut value = (self.data & self.direction) | (!self.direction & self.pull_ups);
+        let floating = !self.direction & !self.pull_ups & PORT_BITS;
+        for bit in 0..6 {
+            let mask = 1 << bit;
+            if (floating & mask) != 0 && cycles < self.fade_out[bit] {
+                value |= self.floating & mask;
+            }
+        }
+        value
+    }
+
+    /// Reads one of the port registers.
+    pub fn read(&self, addr: u16, cycles: u64) -> u8 {
+        if addr == 0x0000 {
+            self.direction
+        } else {
+            self.pins(cycles)
+        }
+    }
+
+    ///
+    /// Writes one of the port registers. Outputs turned into inputs start
+    /// fading out from the value they were driving.
+    ///
+    pub fn write(&mut self, addr: u16, value: u8, cycles: u64) {
+        if addr == 0x0000 {
+            let released = self.direction & !value & PORT_BITS;
+            for bit in 0..6 {
+                let mask = 1 << bit;
+                if (released & mask) != 0 {
+                    self.floating = (self.floating & !mask) | (self.data & mask);
+                    self.fade_out[bit] = cycles + FADE_OUT_CYCLES;
+                }
+            }
+            self.direction = value & PORT_BITS;
+        } else {
+            self.data = value & PORT_BITS;
+        }
+        self.update_banking(cycles);
+    }
+
+    ///
+    /// Restores the power-on state, where every bit is an input. The
+    /// callback is kept.
+    ///
+    pub fn reset(&mut self, cycles: u64) {
+        self.direction = 0x00;
+        self.data = 0x00;
+        self.floating = 0x00;
+        self.fade_out = [0; 6];
+        self.update_banking(cycles);
+    }
+
+    ///
+    /// Returns the state of the port, for `Cpu::snapshot`.
+    ///
+    pub(crate) fn snapshot(&self) -> IoPortSnapshot {
+        IoPortSnapshot {
+            direction: self.direction,
+            data: self.data,
+            pull_ups: self.pull_ups,
+            floating: self.floating,
+            fade_out: self.fade_out,
+            banking: self.banking,
+        }
+    }
+
+    ///
+    /// Brings back a saved state. The callback is kept and not called: the
+    /// memory configuration is expected to be restored along with the port.
+    ///
+    pub(crate) fn restore(&mut self, snapshot: &IoPortSnapshot) {
+        self.direction = snapshot.direction & PORT_BITS;
+        self.data = snapshot.data & PORT_BITS;
+        self.pull_ups = snapshot.pull_ups & PORT_BITS;
+        self.floating = snapshot.floating & PORT_BITS;
+        self.fade_out = snapshot.fade_out;
+        self.banking = snapshot.banking;
+    }
+
+    fn update_banking(&mut self, cycles: u64) {
+        let banking = self.pins(cycles) & BANKING_BITS;
+        if banking != self.banking {
+            self.banking = banking;
+            if let Some(callback) = self.on_banking_change.as_mut() {
+                callback(banking);
+            }
+        }
+    }
+}
+
+impl Default for IoPort {
+    fn default() -> Self {
+        IoPort::new()
+    }
+}
+
+/// Bus seen by a 6510, which serves $0000 and $0001 from the I/O port
+/// without accessing the underlying bus.
+pub struct PortBus<'a, T: Bus> {
+    port: &'a mut IoPort,
+    bus: &'a mut T,
+    cycles: u64,
+}
+
+impl<'a, T: Bus> PortBus<'a, T> {
+    /// Puts the port in front of the bus, at the given cycle of the `Cpu`.
+    pub fn new(port: &'a mut IoPort, bus: &'a mut T, cycles: u64) -> Self {
+        PortBus { port, bus, cycles }
+    }
+}
+
+impl<'a, T: Bus> Bus for PortBus<'a, T> {
+    fn write(&mut self, addr: u16, value: u8) {
+        if addr <= 0x0001 {
+            self.port.write(addr, value, self.cycles);
+        } else {
+            self.bus.write(addr, value);
+        }
+    }
+
+    fn read(&mut self, addr: u16) -> u8 {
+        if addr <= 0x0001 {
+            self.port.read(addr, self.cycles)
+        } else {
+            self.bus.read(addr)
+        }
+    }
+
+    fn peek(&mut self, addr: u16) -> u8 {
+        if addr <= 0x0001 {
+            self.port.read(addr, self.cycles)
+        } else {
+            self.bus.peek(addr)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_pull_ups() {
+        let mut port = IoPort::new();
+        assert_eq!(port.pins(0), 0x17);
+
+        // Outputs drive the data register, inputs keep their pull-ups.
+        port.write(0x0001, 0x00, 0);
+        port.write(0x0000, 0x2F, 0);
+        assert_eq!(port.read(0x0000, 0), 0x2F);
+        assert_eq!(port.read(0x0001, 0), 0x10);
+    }
+
+    #[test]
+    fn test_fade_out() {
+        let mut port = IoPort::new();
+        port.write(0x0001, 0xE0, 0);
+        port.write(0x0000, 0xE0, 0);
+        assert_eq!((port.direction(), port.data()), (0x20, 0x20));
+        assert_eq!(port.pins(10), 0x37);
+
+        // Releasing the bits leaves them floating at their last value.
+        port.write(0x0000, 0x00, 100);
+        assert_eq!(port.pins(100 + FADE_OUT_CYCLES - 1), 0x37);
+        assert_eq!(port.pins(100 + FADE_OUT_CYCLES), 0x17);
+    }
+
+    #[test]
+    fn test_banking_callback() {
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let mut port = IoPort::new();
+        let log = changes.clone();
+        port.set_banking_callback(move |banking| log.borrow_mut().push(banking));
+
+        // Making the bits outputs drives them low.
+        port.write(0x0001, 0x05, 0);
+        port.write(0x0000, 0x07, 0);
+        port.write(0x0001, 0x05, 0);
+        port.write(0x0001, 0x07, 0);
+        port.reset(0);
+        assert_eq!(*changes.borrow(), vec![0x05, 0x07]);
+
+        port.write(0x0000, 0x07, 0);
+        assert_eq!(*changes.borrow(), vec![0x05, 0x07, 0x00]);
+        assert_eq!(port.banking(), 0x00);
+    }
+
+    #[test]
+    fn test_port_bus() {
+        let mut port = IoPort::new();
+        let mut bus = crate::bus::tests::DummyBus::new();
+        let mut port_bus = PortBus::new(&mut port, &mut bus, 0);
+        port_bus.write(0x0000, 0xFF);
+        port_bus.write(0x0001, 0x34);
+        port_bus.write(0x0002, 0x56);
+        assert_eq!(port_bus.read(0x0000), 0x3F);
+        assert_eq!(port_bus.read(0x0001), 0x34);
+        assert_eq!(port.data(), 0x34);
+        assert_eq!(bus.read(0x0001), 0x00);
+        assert_eq!(bus.read(0x0002), 0x56);
+    }
+}
@@ -4,32 +4,306 @@ mod bus;
 mod opcodes;
 mod addressing_modes;
 mod registers;
+mod variant;
+mod execution_mode;
+mod error;
+mod io_port;
+mod snapshot;
+mod disassembler;
+mod assembler;
+mod run;
+mod watch;
+mod gdb;
+mod trace;
+mod memory;
+mod memory_map;
+mod w65c816;
 
 pub use bus::Bus;
-use opcodes::OPCODES;
+pub use error::{Error, SnapshotError, AssemblyError, AssemblyErrorKind};
+pub use registers::Flag;
+pub use variant::Variant;
+pub use execution_mode::ExecutionMode;
+pub use io_port::{IoPort, BANKING_BITS};
+pub use w65c816::Cpu65816;
+pub use snapshot::{Snapshot, IoPortSnapshot, SNAPSHOT_VERSION};
+pub use disassembler::{Disassembler, Disassembly, Syntax};
+pub use assembler::{Assembler, Assembly, Segment};
+pub use run::{RunUntil, StopReason};
+pub use watch::{WatchBus, Watchpoint, WatchKind, WatchHit, AccessKind};
+pub use gdb::{GdbStub, Connection};
+pub use trace::{Tracer, TraceEntry, NestestTracer, BinaryTracer, BinaryTraceReader};
+pub use memory::{Ram, Rom, Mirror, OpenBus};
+pub use memory_map::{MemoryMap, Mapping, DeviceId, RegionId, Bank, BankSelector, RegionLayout};
+use addressing_modes::Operand;
+use instruction::Instruction;
+use opcodes::decode;
 use registers::Registers;
+use io_port::PortBus;
+use std::collections::BTreeSet;
+
+/// Address of the non-maskable interrupt vector.
+const NMI_VECTOR: u16 = 0xFFFA;
+/// Address of the interrupt request vector.
+const IRQ_VECTOR: u16 = 0xFFFE;
+/// Number of cycles taken by the reset and interrupt sequences.
+const INTERRUPT_CYCLES: u8 = 7;
+/// Opcode of JSR, on every variant.
+const JSR_OPCODE: u8 = 0x20;
+/// Opcodes of RTI and RTS, on every variant.
+const RETURN_OPCODES: [u8; 2] = [0x40, 0x60];
 
 /// MOS 6502 Processor emulator
 pub struct Cpu {
-    registers: Registers
+    registers: Registers,
+    variant: Variant,
+    mode: ExecutionMode,
+    cycles: u64,
+    jammed: bool,
+    waiting: bool,
+    stopped: bool,
+    io_port: Option<IoPort>,
+    breakpoints: BTreeSet<u16>,
+    tracer: Option<Box<dyn Tracer>>,
 }
 
 impl Cpu {
     ///
-    /// Constructs a Cpu struct.
+    /// Constructs a Cpu struct emulating the original NMOS 6502.
     ///
     pub fn new() -> Cpu {
+        Cpu::with_variant(Variant::Nmos6502)
+    }
+
+    ///
+    /// Constructs a Cpu struct emulating the given variant of the processor.
+    ///
+    pub fn with_variant(variant: Variant) -> Cpu {
         Cpu {
             registers: Registers::new(),
+            variant,
+            mode: ExecutionMode::Fast,
+            cycles: 0,
+            jammed: false,
+            waiting: false,
+            stopped: false,
+            io_port: if variant.has_io_port() { Some(IoPort::new()) } else { None },
+            breakpoints: BTreeSet::new(),
+            tracer: None,
         }
     }
 
+    ///
+    /// Returns the variant of the processor being emulated.
+    ///
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    ///
+    /// Returns the bus access mode used when executing instructions.
+    ///
+    pub fn execution_mode(&self) -> ExecutionMode {
+        self.mode
+    }
+
+    ///
+    /// Selects the bus access mode used when executing instructions. In
+    /// `ExecutionMode::CycleAccurate`, the `Bus` sees every access of the
+    /// original chip, including dummy reads and writes.
+    ///
+    pub fn set_execution_mode(&mut self, mode: ExecutionMode) {
+        self.mode = mode;
+    }
+
+    ///
+    /// Returns the number of cycles elapsed since the last reset.
+    ///
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    ///
+    /// Returns true while a WAI instruction is waiting for an interrupt.
+    ///
+    pub fn is_waiting(&self) -> bool {
+        self.waiting
+    }
+
+    ///
+    /// Returns the on-chip I/O port, only present on the 6510.
+    ///
+    pub fn io_port(&self) -> Option<&IoPort> {
+        self.io_port.as_ref()
+    }
+
+    ///
+    /// Returns the on-chip I/O port for configuration, only present on the
+    /// 6510.
+    ///
+    pub fn io_port_mut(&mut self) -> Option<&mut IoPort> {
+        self.io_port.as_mut()
+    }
+
+    /// Returns the value of the accumulator.
+    pub fn accumulator(&self) -> u8 {
+        self.registers.accumulator
+    }
+
+    /// Sets the value of the accumulator.
+    pub fn set_accumulator(&mut self, value: u8) {
+        self.registers.accumulator = value;
+    }
+
+    /// Returns the value of the X index register.
+    pub fn x_index(&self) -> u8 {
+        self.registers.x_index
+    }
+
+    /// Sets the value of the X index register.
+    pub fn set_x_index(&mut self, value: u8) {
+        self.registers.x_index = value;
+    }
+
+    /// Returns the value of the Y index register.
+    pub fn y_index(&self) -> u8 {
+        self.registers.y_index
+    }
+
+    /// Sets the value of the Y index register.
+    pub fn set_y_index(&mut self, value: u8) {
+        self.registers.y_index = value;
+    }
+
+    /// Returns the stack pointer, as an offset into page 1.
+    pub fn stack_pointer(&self) -> u8 {
+        self.registers.stack.get()
+    }
+
+    /// Sets the stack pointer, as an offset into page 1.
+    pub fn set_stack_pointer(&mut self, value: u8) {
+        self.registers.stack.set(value);
+    }
+
+    /// Returns the address of the next instruction to execute.
+    pub fn program_counter(&self) -> u16 {
+        self.registers.program_counter
+    }
+
+    /// Sets the address of the next instruction to execute.
+    pub fn set_program_counter(&mut self, value: u16) {
+        self.registers.program_counter = value;
+    }
+
+    ///
+    /// Returns the packed processor status register (P). The unused bit 5
+    /// always reads as set and the B bit, which only exists in the copies
+    /// pushed to the stack, as clear.
+    ///
+    pub fn status(&self) -> u8 {
+        (self.registers.status_reg.get() & !0x10) | 0x20
+    }
+
+    ///
+    /// Sets the processor status register from its packed form. Bits 4 and
+    /// 5 are ignored.
+    ///
+    pub fn set_status(&mut self, value: u8) {
+        self.registers.status_reg.set(value & !0x30);
+    }
+
+    /// Returns the value of a single flag of the status register.
+    pub fn flag(&self, flag: Flag) -> bool {
+        self.registers.status_reg.flag(flag)
+    }
+
+    /// Sets the value of a single flag of the status register.
+    pub fn set_flag(&mut self, flag: Flag, value: bool) {
+        self.registers.status_reg.set_flag(flag, value);
+    }
+
+    ///
+    /// Captures the whole state of the processor, pending interrupts and
+    /// I/O port included. The `Bus` is not part of it.
+    ///
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            variant: self.variant,
+            execution_mode: self.mode,
+            accumulator: self.registers.accumulator,
+            x_index: self.registers.x_index,
+            y_index: self.registers.y_index,
+            stack_pointer: self.registers.stack.get(),
+            program_counter: self.registers.program_counter,
+            status: self.registers.status_reg.get(),
+            irq_active: self.registers.irq_active,
+            nmi_active: self.registers.nmi_active,
+            cycles: self.cycles,
+            jammed: self.jammed,
+            waiting: self.waiting,
+            stopped: self.stopped,
+            io_port: self.io_port.as_ref().map(IoPort::snapshot),
+        }
+    }
+
+    ///
+    /// Brings back a state captured by `snapshot`, variant included.
+    ///
+    /// Snapshots from another version of the format are rejected, as are
+    /// snapshots whose I/O port doesn't match the variant. On error, the
+    /// processor is left untouched. The banking callback of the I/O port
+    /// is kept.
+    ///
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+        snapshot::check_version(snapshot.version)?;
+        if snapshot.io_port.is_some() != snapshot.variant.has_io_port() {
+            return Err(SnapshotError::InvalidField { field: "io_port" });
+        }
+
+        self.variant = snapshot.variant;
+        self.mode = snapshot.execution_mode;
+        self.registers.accumulator = snapshot.accumulator;
+        self.registers.x_index = snapshot.x_index;
+        self.registers.y_index = snapshot.y_index;
+        self.registers.stack.set(snapshot.stack_pointer);
+        self.registers.program_counter = snapshot.program_counter;
+        self.registers.status_reg.set(snapshot.status);
+        self.registers.irq_active = snapshot.irq_active;
+        self.registers.nmi_active = snapshot.nmi_active;
+        self.cycles = snapshot.cycles;
+        self.jammed = snapshot.jammed;
+        self.waiting = snapshot.waiting;
+        self.stopped = snapshot.stopped;
+        self.io_port = match &snapshot.io_port {
+            Some(state) => {
+                let mut port = self.io_port.take().unwrap_or_default();
+                port.restore(state);
+                Some(port)
+            },
+            None => None,
+        };
+        Ok(())
+    }
+
     ///
     /// Resets the processor, fetching the reset handler and jumping to it.
     ///
-    pub fn reset<T: Bus>(&mut self, bus: &T) {
+    pub fn reset<T: Bus>(&mut self, bus: &mut T) {
         // Restore original state
-        *self = Cpu::new();
+        let mode = self.mode;
+        let io_port = self.io_port.take();
+        let breakpoints = std::mem::take(&mut self.breakpoints);
+        let tracer = self.tracer.take();
+        *self = Cpu::with_variant(self.variant);
+        self.mode = mode;
+        self.breakpoints = breakpoints;
+        self.tracer = tracer;
+        if let Some(mut port) = io_port {
+            // The port keeps its callback across resets.
+            port.reset(INTERRUPT_CYCLES as u64);
+            self.io_port = Some(port);
+        }
 
         // Jump to the reset handler.
         let low_byte : u16 = self.step_program_counter(bus).into();
@@ -37,10 +311,29 @@ impl Cpu {
 
         let reset_vector = low_byte | (high_byte << 8);
         self.registers.program_counter = reset_vector;
+        self.cycles = INTERRUPT_CYCLES as u64;
     }
 
     ///
-    /// Runs a single instruction of the processor.
+    /// Runs a single instruction of the processor and returns the number of
+    /// cycles it took.
+    ///
+    /// If the opcode can't be executed, an error is returned and the program
+    /// counter is left pointing to it. After a JAM opcode, the processor is
+    /// halted and every step fails until it is reset. The same happens after
+    /// the STP instruction of the 65C02.
+    ///
+    /// After a WAI instruction, every step takes a single cycle until an
+    /// interrupt is signalled. A masked IRQ resumes execution without being
+    /// serviced.
+    ///
+    /// On the 6510, accesses to $0000 and $0001 are served by the I/O port
+    /// and never reach the bus.
+    ///
+    /// Pending interrupts are checked before fetching the next opcode. If
+    /// one has to be serviced, this step pushes the program counter and the
+    /// status register and jumps to the interrupt handler instead of running
+    /// an instruction.
     ///
     /// # Example
     ///```
@@ -50,7 +343,7 @@ impl Cpu {
     ///        fn write(&mut self, _addr: u16, _value: u8) {
     ///          // Can't write in the Gnd bus, everything is tied to 0.
     ///        }
-    ///        fn read(&self, _addr: u16) -> u8 {
+    ///        fn read(&mut self, _addr: u16) -> u8 {
     ///            0u8
     ///        }
     ///    }
@@ -58,14 +351,259 @@ impl Cpu {
     ///    let mut bus = GndBus {};
     ///    let mut mos6502 = mos6502::Cpu::new();
     ///    mos6502.reset(&mut bus);
-    ///    mos6502.single_step(&mut bus);
+    ///    assert_eq!(mos6502.single_step(&mut bus), Ok(7));
     ///```
     ///
-    pub fn single_step<T>(&mut self, bus: &mut T) where T: Bus {
+    pub fn single_step<T>(&mut self, bus: &mut T) -> Result<u8, Error> where T: Bus {
+        match self.io_port.take() {
+            Some(mut port) => {
+                let result = self.execute(&mut PortBus::new(&mut port, bus, self.cycles));
+                self.io_port = Some(port);
+                result
+            },
+            None => self.execute(bus),
+        }
+    }
+
+    ///
+    /// Runs a single instruction on the bus as seen by the processor core.
+    ///
+    fn execute<T: Bus>(&mut self, bus: &mut T) -> Result<u8, Error> {
+        if self.jammed {
+            let addr = self.registers.program_counter;
+            return Err(Error::Jam { addr, opcode: bus.peek(addr) });
+        }
+
+        if self.stopped {
+            return Err(Error::Stopped { addr: self.registers.program_counter });
+        }
+
+        if self.waiting {
+            if !self.registers.nmi_active && !self.registers.irq_active {
+                return Ok(self.count_cycles(1));
+            }
+            self.waiting = false;
+        }
+
+        if self.registers.nmi_active {
+            // NMI is edge triggered, servicing it acknowledges the request.
+            self.registers.nmi_active = false;
+            self.service_interrupt(bus, NMI_VECTOR);
+            return Ok(self.count_cycles(INTERRUPT_CYCLES));
+        }
+
+        // IRQ is level triggered, it stays active until the device releases the line.
+        if self.registers.irq_active && !self.registers.status_reg.irq_disable {
+            self.service_interrupt(bus, IRQ_VECTOR);
+            return Ok(self.count_cycles(INTERRUPT_CYCLES));
+        }
+
+        if self.tracer.is_some() {
+            self.trace(bus);
+        }
+
         // Fetch opcode
-        let (instruction, addressing_mode) = OPCODES[self.step_program_counter(bus) as usize].unwrap();
-        let operand = addressing_mode.get_operand(bus, &mut self.registers);
-        instruction.process(operand, bus, &mut self.registers);
+        let addr = self.registers.program_counter;
+        let opcode = self.step_program_counter(bus);
+        let (instruction, addressing_mode, cycles) = match decode(opcode, self.variant) {
+            Some(entry) => entry,
+            None => {
+                self.registers.program_counter = addr;
+                return Err(Error::UnknownOpcode { addr, opcode });
+            }
+        };
+
+        if instruction == Instruction::Jam {
+            self.registers.program_counter = addr;
+            self.jammed = true;
+            return Err(Error::Jam { addr, opcode });
+        }
+
+        let (operand, page_crossed) = if cycles == 1 {
+            // The single cycle NOPs of the 65C02 end with the opcode fetch.
+            (Operand::None, false)
+        } else if instruction == Instruction::Nop && cycles == 8 {
+            self.long_nop(bus);
+            (Operand::None, false)
+        } else {
+            addressing_mode.get_operand(bus, &mut self.registers, instruction, self.variant, self.mode)
+        };
+        let extra_cycles = instruction.process(operand, bus, &mut self.registers, self.variant, self.mode)
+            .map_err(|_| Error::InvalidOperand { addr, opcode })?;
+
+        match instruction {
+            Instruction::Wai => self.waiting = true,
+            Instruction::Stp => self.stopped = true,
+            _ => {},
+        }
+
+        let mut cycles = cycles + extra_cycles;
+        if page_crossed && instruction.has_page_cross_penalty(self.variant) {
+            cycles += 1;
+        }
+        Ok(self.count_cycles(cycles))
+    }
+
+    ///
+    /// Gives the state before the instruction at the program counter to
+    /// the tracer.
+    ///
+    fn trace<T: Bus>(&mut self, bus: &mut T) {
+        let pc = self.registers.program_counter;
+        let opcode = bus.peek(pc);
+        let len = decode(opcode, self.variant).map_or(1, |(_, mode, _)| 1 + mode.operand_length() as u8);
+        let mut bytes = [opcode, 0, 0];
+        for (offset, byte) in bytes.iter_mut().enumerate().take(len as usize).skip(1) {
+            *byte = bus.peek(pc.wrapping_add(offset as u16));
+        }
+        let entry = TraceEntry {
+            variant: self.variant,
+            pc,
+            bytes,
+            len,
+            accumulator: self.registers.accumulator,
+            x_index: self.registers.x_index,
+            y_index: self.registers.y_index,
+            status: self.status(),
+            stack_pointer: self.registers.stack.get(),
+            cycles: self.cycles,
+        };
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.trace(&entry, bus);
+        }
+    }
+
+    ///
+    /// Sets a tracer, called before each instruction with the state of the
+    /// processor, e.g. a `NestestTracer` to compare a run with a reference
+    /// log. It replaces the previous one and is kept across resets.
+    ///
+    pub fn set_tracer<T: Tracer + 'static>(&mut self, tracer: T) {
+        self.tracer = Some(Box::new(tracer));
+    }
+
+    ///
+    /// Removes the tracer and returns it, so it can be finished.
+    ///
+    pub fn take_tracer(&mut self) -> Option<Box<dyn Tracer>> {
+        self.tracer.take()
+    }
+
+    ///
+    /// Sets a breakpoint, which stops `run`, `step_over` and `step_out`
+    /// before the instruction at the address is executed. Breakpoints are
+    /// kept across resets.
+    ///
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    ///
+    /// Removes a breakpoint. Returns false if there was none at the address.
+    ///
+    pub fn remove_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    /// Removes every breakpoint.
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    /// Returns the addresses of the breakpoints, in ascending order.
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    ///
+    /// Runs instructions until a breakpoint or one of the conditions of
+    /// `until` is met, and returns the reason.
+    ///
+    /// Breakpoints and opcodes are checked before each instruction except
+    /// the first one, so a run can resume from where the previous one
+    /// stopped. Execution errors end the run.
+    ///
+    pub fn run<T: Bus>(&mut self, bus: &mut T, until: &RunUntil) -> Result<StopReason, Error> {
+        self.run_while(bus, until, |_, _| {}, |_, _, _| None)
+    }
+
+    ///
+    /// Same as `run`, also stopping after an instruction that triggers one
+    /// of the watchpoints of the bus. The hit reports the address of that
+    /// instruction.
+    ///
+    pub fn run_watched<T: Bus>(&mut self, bus: &mut WatchBus<T>, until: &RunUntil) -> Result<StopReason, Error> {
+        bus.take_hit();
+        self.run_while(bus, until, |bus, pc| bus.set_pc(pc), |_, bus, _| bus.take_hit().map(StopReason::Watchpoint))
+    }
+
+    ///
+    /// Runs a single instruction, or the whole subroutine if it is a JSR,
+    /// stopping when the call returns to the same stack depth. Breakpoints
+    /// and the conditions of `until` can stop it earlier.
+    ///
+    pub fn step_over<T: Bus>(&mut self, bus: &mut T, until: &RunUntil) -> Result<StopReason, Error> {
+        let pc = self.registers.program_counter;
+        if bus.peek(pc) != JSR_OPCODE {
+            return self.run_while(bus, until, |_, _| {}, |_, _, _| Some(StopReason::StepOver));
+        }
+        let return_addr = pc.wrapping_add(3);
+        let depth = self.registers.stack.get();
+        self.run_while(bus, until, |_, _| {}, |cpu, _, _| {
+            let returned = cpu.registers.program_counter == return_addr && cpu.registers.stack.get() == depth;
+            if returned { Some(StopReason::StepOver) } else { None }
+        })
+    }
+
+    ///
+    /// Runs until the current subroutine or interrupt handler returns: an
+    /// RTS or RTI that pops the stack above its depth at the start.
+    /// Breakpoints and the conditions of `until` can stop it earlier.
+    ///
+    pub fn step_out<T: Bus>(&mut self, bus: &mut T, until: &RunUntil) -> Result<StopReason, Error> {
+        let depth = self.registers.stack.get();
+        self.run_while(bus, until, |_, _| {}, |cpu, _, opcode| {
+            let returned = RETURN_OPCODES.contains(&opcode) && cpu.registers.stack.get() > depth;
+            if returned { Some(StopReason::StepOut) } else { None }
+        })
+    }
+
+    ///
+    /// Runs until a breakpoint, a condition of `until` or `done`, which is
+    /// called after every step with the opcode found at the program counter
+    /// before it. `before` gets the address of every instruction before it
+    /// runs.
+    ///
+    fn run_while<T, B, F>(&mut self, bus: &mut T, until: &RunUntil, mut before: B, mut done: F) -> Result<StopReason, Error>
+        where T: Bus, B: FnMut(&mut T, u16), F: FnMut(&Cpu, &mut T, u8) -> Option<StopReason> {
+        let start_cycles = self.cycles;
+        let mut instructions = 0;
+        loop {
+            let addr = self.registers.program_counter;
+            let opcode = bus.peek(addr);
+            if instructions > 0 {
+                if self.breakpoints.contains(&addr) {
+                    return Ok(StopReason::Breakpoint(addr));
+                }
+                if until.opcodes.contains(&opcode) {
+                    return Ok(StopReason::Opcode { addr, opcode });
+                }
+            }
+
+            before(bus, addr);
+            self.single_step(bus)?;
+            instructions += 1;
+
+            if let Some(reason) = done(self, bus, opcode) {
+                return Ok(reason);
+            }
+            if until.cycles.is_some_and(|cycles| self.cycles - start_cycles >= cycles) {
+                return Ok(StopReason::CycleLimit);
+            }
+            if until.instructions.is_some_and(|limit| instructions >= limit) {
+                return Ok(StopReason::InstructionLimit);
+            }
+        }
     }
 
     /// Signals an interrupt (IRQB signal) to the core.
@@ -73,22 +611,82 @@ impl Cpu {
         self.registers.irq_active = true;
     }
 
+    /// Releases the IRQB signal previously raised with `signal_irq`.
+    pub fn clear_irq(&mut self) {
+        self.registers.irq_active = false;
+    }
+
     /// Signals a NMI Interrupt to the core.
     pub fn signal_nmi(&mut self) {
         self.registers.nmi_active = true;
     }
 
+    ///
+    /// Pushes the program counter and the status register to the stack and
+    /// jumps to the handler stored in the given vector.
+    ///
+    fn service_interrupt<T: Bus>(&mut self, bus: &mut T, vector: u16) {
+        let pc = self.registers.program_counter;
+        if self.mode.is_cycle_accurate() {
+            // The opcode fetch is performed and discarded, twice.
+            bus.read(pc);
+            bus.read(pc);
+        }
+        self.registers.stack.push_address(pc, bus);
+
+        // Hardware interrupts push the status register with the B flag cleared.
+        let status = (self.registers.status_reg.get() & !0x10) | 0x20;
+        self.registers.stack.push(status, bus);
+        self.registers.status_reg.irq_disable = true;
+        if self.variant.is_cmos() {
+            self.registers.status_reg.decimal_mode = false;
+        }
+
+        let low_byte : u16 = bus.read(vector).into();
+        let high_byte : u16 = bus.read(vector.wrapping_add(1)).into();
+        self.registers.program_counter = low_byte | (high_byte << 8);
+    }
+
+    ///
+    /// Adds the cycles spent in the last step to the running counter.
+    ///
+    fn count_cycles(&mut self, cycles: u8) -> u8 {
+        self.cycles += cycles as u64;
+        cycles
+    }
+
+    ///
+    /// Runs NOP $5C of the 65C02, which fetches both operand bytes, reads
+    /// $FF00 plus the low byte and then spends four cycles reading $FFFF.
+    ///
+    fn long_nop<T: Bus>(&mut self, bus: &mut T) {
+        let low_byte = self.step_program_counter(bus);
+        self.step_program_counter(bus);
+        bus.read(0xFF00 | low_byte as u16);
+        if self.mode.is_cycle_accurate() {
+            for _ in 0..4 {
+                bus.read(0xFFFF);
+            }
+        }
+    }
+
     ///
     /// Steps the program counter and returns the value at
     /// the current PC in the supplied Bus
     ///
-    fn step_program_counter<T: Bus>(&mut self, bus: &T) -> u8 {
+    fn step_program_counter<T: Bus>(&mut self, bus: &mut T) -> u8 {
         let result = bus.read(self.registers.program_counter);
-        self.registers.program_counter += 1;
+        self.registers.program_counter = self.registers.program_counter.wrapping_add(1);
         result
     }
 }
 
+impl Default for Cpu {
+    fn default() -> Self {
+        Cpu::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -105,10 +703,12 @@ mod tests {
         }
     }
 
-    impl<'a> Bus for DummyBus {
-        fn write(&mut self, _addr: u16, _value: u8) { }
+    impl Bus for DummyBus {
+        fn write(&mut self, addr: u16, value: u8) {
+            self.data[addr as usize] = value;
+        }
 
-        fn read(&self, addr: u16) -> u8 {
+        fn read(&mut self, addr: u16) -> u8 {
             self.data[addr as usize]
         }
     }
@@ -119,9 +719,754 @@ mod tests {
         let mut bus = DummyBus::new();
         bus.data[0xFFFC] = 0x12;
         bus.data[0xFFFD] = 0x34;
-        cpu.reset(&bus);
+        cpu.reset(&mut bus);
 
         assert_eq!(cpu.registers.program_counter, 0x3412);
         assert_eq!(cpu.registers.accumulator, 0);
+        assert_eq!(cpu.cycles(), 7);
+    }
+
+    #[test]
+    fn test_irq() {
+        let mut cpu = Cpu::new();
+        let mut bus = DummyBus::new();
+        bus.data[0xFFFE] = 0x00;
+        bus.data[0xFFFF] = 0x80;
+        cpu.registers.program_counter = 0x1234;
+        cpu.registers.status_reg.carry = true;
+
+        cpu.signal_irq();
+        assert_eq!(cpu.single_step(&mut bus), Ok(7));
+
+        assert_eq!(cpu.registers.program_counter, 0x8000);
+        assert_eq!(cpu.registers.stack.get(), 0xFC);
+        assert_eq!(bus.data[0x1FF], 0x12);
+        assert_eq!(bus.data[0x1FE], 0x34);
+        assert_eq!(bus.data[0x1FD], 0x21);
+        assert!(cpu.registers.status_reg.irq_disable);
+
+        // The line is level triggered, it stays active until cleared.
+        assert!(cpu.registers.irq_active);
+        cpu.clear_irq();
+        assert!(!cpu.registers.irq_active);
+    }
+
+    #[test]
+    fn test_irq_masked() {
+        let mut cpu = Cpu::new();
+        let mut bus = DummyBus::new();
+        bus.data[0x1234] = 0xEA;
+        cpu.registers.program_counter = 0x1234;
+        cpu.registers.status_reg.irq_disable = true;
+
+        cpu.signal_irq();
+        cpu.single_step(&mut bus).unwrap();
+
+        assert_eq!(cpu.registers.program_counter, 0x1235);
+        assert_eq!(cpu.registers.stack.get(), 0xFF);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_nmi() {
+        let mut cpu = Cpu::new();
+        let mut bus = DummyBus::new();
+        bus.data[0xFFFA] = 0x00;
+        bus.data[0xFFFB] = 0x90;
+        bus.data[0x9000] = 0xEA;
+        cpu.registers.program_counter = 0x1234;
+        cpu.registers.status_reg.irq_disable = true;
+
+        cpu.signal_nmi();
+        cpu.single_step(&mut bus).unwrap();
+
+        assert_eq!(cpu.registers.program_counter, 0x9000);
+        assert!(!cpu.registers.nmi_active);
+
+        // NMI is edge triggered, the next step runs the handler.
+        cpu.single_step(&mut bus).unwrap();
+        assert_eq!(cpu.registers.program_counter, 0x9001);
+    }
+
+    fn run_program(cpu: &mut Cpu, bus: &mut DummyBus, program: &[u8]) -> u8 {
+        bus.data[0x0200..0x0200 + program.len()].copy_from_slice(program);
+        cpu.registers.program_counter = 0x0200;
+        cpu.single_step(bus).unwrap()
+    }
+
+    #[test]
+    fn test_cycles_page_crossing() {
+        let mut cpu = Cpu::new();
+        let mut bus = DummyBus::new();
+        cpu.registers.x_index = 0x10;
+
+        // LDA $12F0,X
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0xBD, 0xE0, 0x12]), 4);
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0xBD, 0xF0, 0x12]), 5);
+
+        // STA $12F0,X always takes the extra cycle.
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0x9D, 0xE0, 0x12]), 5);
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0x9D, 0xF0, 0x12]), 5);
+
+        // LDA ($10),Y
+        bus.data[0x10] = 0xF0;
+        bus.data[0x11] = 0x12;
+        cpu.registers.y_index = 0x0F;
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0xB1, 0x10]), 5);
+        cpu.registers.y_index = 0x10;
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0xB1, 0x10]), 6);
+
+        assert_eq!(cpu.cycles(), 30);
+    }
+
+    #[test]
+    fn test_cycles_branch() {
+        let mut cpu = Cpu::new();
+        let mut bus = DummyBus::new();
+
+        // BCS not taken
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0xB0, 0x10]), 2);
+        assert_eq!(cpu.registers.program_counter, 0x0202);
+
+        // BCC taken to the same page
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0x90, 0x10]), 3);
+        assert_eq!(cpu.registers.program_counter, 0x0212);
+
+        // BCC taken to the previous page
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0x90, 0xF0]), 4);
+        assert_eq!(cpu.registers.program_counter, 0x01F2);
+    }
+
+    /// Bus that logs every access, as (address, value, is_write).
+    struct RecordingBus {
+        bus: DummyBus,
+        accesses: Vec<(u16, u8, bool)>,
+    }
+
+    impl RecordingBus {
+        fn new() -> RecordingBus {
+            RecordingBus {
+                bus: DummyBus::new(),
+                accesses: vec![],
+            }
+        }
+
+        fn take(&mut self) -> Vec<(u16, u8, bool)> {
+            std::mem::take(&mut self.accesses)
+        }
+    }
+
+    impl Bus for RecordingBus {
+        fn write(&mut self, addr: u16, value: u8) {
+            self.accesses.push((addr, value, true));
+            self.bus.write(addr, value);
+        }
+
+        fn read(&mut self, addr: u16) -> u8 {
+            let value = self.bus.read(addr);
+            self.accesses.push((addr, value, false));
+            value
+        }
+
+        fn peek(&mut self, addr: u16) -> u8 {
+            self.bus.read(addr)
+        }
+    }
+
+    fn accurate_cpu() -> Cpu {
+        let mut cpu = Cpu::with_variant(Variant::Nmos6502Undocumented);
+        cpu.set_execution_mode(ExecutionMode::CycleAccurate);
+        cpu.registers.program_counter = 0x0200;
+        cpu
+    }
+
+    #[test]
+    fn test_accurate_accesses_match_cycles() {
+        for &variant in [Variant::Nmos6502Undocumented, Variant::Wdc65c02].iter() {
+            for opcode in 0..=0xFFu8 {
+                if decode(opcode, variant).is_none() {
+                    continue;
+                }
+                for &(operand, index) in [(0x10u8, 0x20u8), (0xF0, 0x20)].iter() {
+                    for &(flags, decimal) in [(0x00u8, false), (0xFF, false), (0xFF, true)].iter() {
+                        let mut cpu = accurate_cpu();
+                        let mut bus = RecordingBus::new();
+                        bus.bus.data[0x0200..0x0203].copy_from_slice(&[opcode, operand, 0x12]);
+                        cpu.variant = variant;
+                        cpu.registers.x_index = index;
+                        cpu.registers.y_index = index;
+                        cpu.registers.status_reg.set(flags);
+                        cpu.registers.status_reg.decimal_mode = decimal;
+
+                        match cpu.single_step(&mut bus) {
+                            Ok(cycles) => assert_eq!(bus.take().len(), cycles as usize, "{:?} opcode {:02X}", variant, opcode),
+                            Err(error) => assert_eq!(error, Error::Jam { addr: 0x0200, opcode }),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_accurate_read_modify_write() {
+        let mut cpu = accurate_cpu();
+        let mut bus = RecordingBus::new();
+        // INC $10,X
+        bus.bus.data[0x0200..0x0202].copy_from_slice(&[0xF6, 0x10]);
+        bus.bus.data[0x0015] = 0x41;
+        cpu.registers.x_index = 0x05;
+
+        assert_eq!(cpu.single_step(&mut bus), Ok(6));
+        assert_eq!(bus.take(), vec![
+            (0x0200, 0xF6, false),
+            (0x0201, 0x10, false),
+            (0x0010, 0x00, false),
+            (0x0015, 0x41, false),
+            (0x0015, 0x41, true),
+            (0x0015, 0x42, true),
+        ]);
+    }
+
+    #[test]
+    fn test_accurate_indexed_read() {
+        let mut cpu = accurate_cpu();
+        let mut bus = RecordingBus::new();
+        // LDA $12F0,X
+        bus.bus.data[0x0200..0x0203].copy_from_slice(&[0xBD, 0xF0, 0x12]);
+        cpu.registers.x_index = 0x20;
+
+        assert_eq!(cpu.single_step(&mut bus), Ok(5));
+        assert_eq!(bus.take(), vec![
+            (0x0200, 0xBD, false),
+            (0x0201, 0xF0, false),
+            (0x0202, 0x12, false),
+            (0x1210, 0x00, false),
+            (0x1310, 0x00, false),
+        ]);
+    }
+
+    #[test]
+    fn test_accurate_cmos_indexing() {
+        let mut cpu = accurate_cpu();
+        let mut bus = RecordingBus::new();
+        cpu.variant = Variant::Wdc65c02;
+        // LDA $12F0,X reads its last byte again while fixing the high byte.
+        bus.bus.data[0x0200..0x0203].copy_from_slice(&[0xBD, 0xF0, 0x12]);
+        cpu.registers.x_index = 0x20;
+        assert_eq!(cpu.single_step(&mut bus), Ok(5));
+        assert_eq!(bus.take()[3], (0x0202, 0x12, false));
+
+        // ASL $1210,X only spends that cycle when crossing a page.
+        bus.bus.data[0x0203..0x0209].copy_from_slice(&[0x1E, 0x10, 0x12, 0x1E, 0xF0, 0x12]);
+        assert_eq!(cpu.single_step(&mut bus), Ok(6));
+        assert_eq!(bus.take().len(), 6);
+        assert_eq!(cpu.single_step(&mut bus), Ok(7));
+        assert_eq!(bus.take()[3], (0x0208, 0x12, false));
+
+        // NOP $5C reads $FF00 plus its low byte, then $FFFF.
+        bus.bus.data[0x0209..0x020C].copy_from_slice(&[0x5C, 0x34, 0x12]);
+        assert_eq!(cpu.single_step(&mut bus), Ok(8));
+        assert_eq!(bus.take()[2..], [
+            (0x020B, 0x12, false),
+            (0xFF34, 0x00, false),
+            (0xFFFF, 0x00, false),
+            (0xFFFF, 0x00, false),
+            (0xFFFF, 0x00, false),
+            (0xFFFF, 0x00, false),
+        ]);
+        assert_eq!(cpu.program_counter(), 0x020C);
+    }
+
+    #[test]
+    fn test_accurate_subroutine() {
+        let mut cpu = accurate_cpu();
+        let mut bus = RecordingBus::new();
+        // JSR $1234, then RTS
+        bus.bus.data[0x0200..0x0203].copy_from_slice(&[0x20, 0x34, 0x12]);
+        bus.bus.data[0x1234] = 0x60;
+
+        assert_eq!(cpu.single_step(&mut bus), Ok(6));
+        assert_eq!(bus.take(), vec![
+            (0x0200, 0x20, false),
+            (0x0201, 0x34, false),
+            (0x01FF, 0x00, false),
+            (0x01FF, 0x02, true),
+            (0x01FE, 0x02, true),
+            (0x0202, 0x12, false),
+        ]);
+        assert_eq!(cpu.registers.program_counter, 0x1234);
+
+        assert_eq!(cpu.single_step(&mut bus), Ok(6));
+        assert_eq!(bus.take(), vec![
+            (0x1234, 0x60, false),
+            (0x1235, 0x00, false),
+            (0x01FD, 0x00, false),
+            (0x01FE, 0x02, false),
+            (0x01FF, 0x02, false),
+            (0x0202, 0x12, false),
+        ]);
+        assert_eq!(cpu.registers.program_counter, 0x0203);
+    }
+
+    #[test]
+    fn test_fast_mode_skips_dummy_accesses() {
+        let mut cpu = Cpu::new();
+        let mut bus = RecordingBus::new();
+        cpu.registers.program_counter = 0x0200;
+        // INC $10
+        bus.bus.data[0x0200..0x0202].copy_from_slice(&[0xE6, 0x10]);
+
+        assert_eq!(cpu.single_step(&mut bus), Ok(5));
+        assert_eq!(bus.take().len(), 4);
+    }
+
+    #[test]
+    fn test_unknown_opcode() {
+        let mut cpu = Cpu::new();
+        let mut bus = DummyBus::new();
+        bus.data[0x0200] = 0x03;
+        cpu.registers.program_counter = 0x0200;
+
+        assert_eq!(cpu.single_step(&mut bus), Err(Error::UnknownOpcode { addr: 0x0200, opcode: 0x03 }));
+        assert_eq!(cpu.registers.program_counter, 0x0200);
+        assert_eq!(cpu.cycles(), 0);
+    }
+
+    #[test]
+    fn test_undocumented_opcodes() {
+        // LAX $10 ; DCP $11
+        let program = [0xA7, 0x10, 0xC7, 0x11];
+        let mut bus = DummyBus::new();
+        bus.data[0x0200..0x0200 + program.len()].copy_from_slice(&program);
+        bus.data[0x0010] = 0x42;
+        bus.data[0x0011] = 0x43;
+
+        let mut cpu = Cpu::new();
+        cpu.registers.program_counter = 0x0200;
+        assert_eq!(cpu.single_step(&mut bus), Err(Error::UnknownOpcode { addr: 0x0200, opcode: 0xA7 }));
+
+        let mut cpu = Cpu::with_variant(Variant::Nmos6502Undocumented);
+        cpu.registers.program_counter = 0x0200;
+        assert_eq!(cpu.single_step(&mut bus), Ok(3));
+        assert_eq!(cpu.accumulator(), 0x42);
+        assert_eq!(cpu.x_index(), 0x42);
+        assert_eq!(cpu.single_step(&mut bus), Ok(5));
+        assert_eq!(bus.data[0x0011], 0x42);
+        assert!(cpu.flag(Flag::Zero));
+        assert!(cpu.flag(Flag::Carry));
+    }
+
+    #[test]
+    fn test_ricoh_2a03() {
+        let mut cpu = Cpu::with_variant(Variant::Ricoh2A03);
+        let mut bus = DummyBus::new();
+        cpu.registers.accumulator = 0x09;
+
+        // SED still sets the flag, but ADC #$01 stays binary.
+        run_program(&mut cpu, &mut bus, &[0xF8]);
+        assert!(cpu.flag(Flag::DecimalMode));
+        run_program(&mut cpu, &mut bus, &[0x69, 0x01]);
+        assert_eq!(cpu.accumulator(), 0x0A);
+
+        // Undocumented opcodes are executed, as nestest expects.
+        bus.data[0x0010] = 0x37;
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0xA7, 0x10]), 3);
+        assert_eq!(cpu.x_index(), 0x37);
+    }
+
+    #[test]
+    fn test_mos6510_io_port() {
+        let mut cpu = Cpu::with_variant(Variant::Mos6510);
+        let mut bus = DummyBus::new();
+        let banking = std::rc::Rc::new(std::cell::Cell::new(0xFF));
+        let seen = banking.clone();
+        cpu.io_port_mut().unwrap().set_banking_callback(move |bits| seen.set(bits));
+
+        // LDA #$2F ; STA $00 ; LDA #$35 ; STA $01
+        cpu.registers.accumulator = 0x2F;
+        run_program(&mut cpu, &mut bus, &[0x85, 0x00]);
+        assert_eq!(banking.get(), 0x00);
+        cpu.registers.accumulator = 0x35;
+        run_program(&mut cpu, &mut bus, &[0x85, 0x01]);
+        assert_eq!(banking.get(), 0x05);
+        assert_eq!(bus.data[0x0000], 0x00);
+        assert_eq!(bus.data[0x0001], 0x00);
+
+        // LDA $01
+        run_program(&mut cpu, &mut bus, &[0xA5, 0x01]);
+        assert_eq!(cpu.accumulator(), 0x35);
+
+        cpu.reset(&mut bus);
+        assert_eq!(cpu.io_port().unwrap().direction(), 0x00);
+        assert_eq!(banking.get(), 0x07);
+        assert!(Cpu::new().io_port().is_none());
+    }
+
+    fn load_program(bus: &mut DummyBus, source: &str) -> Assembly {
+        let assembly = Assembler::new(Variant::Nmos6502).assemble(source).unwrap();
+        assembly.load(bus);
+        assembly
+    }
+
+    const SUBROUTINES: &str = "
+            .org $0200
+    main:   jsr outer
+    after:  brk
+    outer:  jsr inner
+            inx
+    back:   rts
+    inner:  iny
+            rts
+    ";
+
+    #[test]
+    fn test_run_limits() {
+        let mut cpu = Cpu::new();
+        let mut bus = DummyBus::new();
+        let program = load_program(&mut bus, SUBROUTINES);
+        cpu.set_program_counter(0x0200);
+
+        assert_eq!(cpu.run(&mut bus, &RunUntil::instructions(2)), Ok(StopReason::InstructionLimit));
+        assert_eq!(cpu.program_counter(), program.symbol("inner").unwrap());
+        assert_eq!(cpu.run(&mut bus, &RunUntil::cycles(7)), Ok(StopReason::CycleLimit));
+        assert_eq!(cpu.y_index(), 1);
+        assert_eq!(cpu.run(&mut bus, &RunUntil::opcodes(&[0x00])),
+                   Ok(StopReason::Opcode { addr: program.symbol("after").unwrap(), opcode: 0x00 }));
+        assert_eq!(cpu.x_index(), 1);
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut cpu = Cpu::new();
+        let mut bus = DummyBus::new();
+        let program = load_program(&mut bus, SUBROUTINES);
+        let back = program.symbol("back").unwrap();
+        cpu.set_program_counter(0x0200);
+        cpu.add_breakpoint(back);
+
+        assert_eq!(cpu.run(&mut bus, &RunUntil::default()), Ok(StopReason::Breakpoint(back)));
+        // Running again executes the instruction under the breakpoint.
+        assert_eq!(cpu.run(&mut bus, &RunUntil::instructions(1)), Ok(StopReason::InstructionLimit));
+        assert_eq!(cpu.program_counter(), program.symbol("after").unwrap());
+
+        cpu.reset(&mut bus);
+        assert_eq!(cpu.breakpoints().collect::<Vec<_>>(), [back]);
+        assert!(cpu.remove_breakpoint(back));
+        assert!(!cpu.remove_breakpoint(back));
+    }
+
+    #[test]
+    fn test_step_over_and_out() {
+        let mut cpu = Cpu::new();
+        let mut bus = DummyBus::new();
+        let program = load_program(&mut bus, SUBROUTINES);
+        cpu.set_program_counter(0x0200);
+
+        // Stepping over the outer call runs both subroutines.
+        assert_eq!(cpu.step_over(&mut bus, &RunUntil::default()), Ok(StopReason::StepOver));
+        assert_eq!(cpu.program_counter(), program.symbol("after").unwrap());
+        assert_eq!((cpu.x_index(), cpu.y_index()), (1, 1));
+
+        // A plain instruction is single stepped.
+        cpu.set_program_counter(program.symbol("back").unwrap() - 1);
+        assert_eq!(cpu.step_over(&mut bus, &RunUntil::default()), Ok(StopReason::StepOver));
+        assert_eq!(cpu.x_index(), 2);
+
+        // Stepping out returns to the caller of the current subroutine only.
+        cpu.set_program_counter(0x0200);
+        cpu.run(&mut bus, &RunUntil::instructions(2)).unwrap();
+        assert_eq!(cpu.step_out(&mut bus, &RunUntil::default()), Ok(StopReason::StepOut));
+        assert_eq!(cpu.program_counter(), program.symbol("back").unwrap() - 1);
+        assert_eq!(cpu.step_out(&mut bus, &RunUntil::default()), Ok(StopReason::StepOut));
+        assert_eq!(cpu.program_counter(), program.symbol("after").unwrap());
+
+        // Breakpoints stop a step over.
+        cpu.set_program_counter(0x0200);
+        cpu.add_breakpoint(program.symbol("inner").unwrap());
+        assert_eq!(cpu.step_over(&mut bus, &RunUntil::default()),
+                   Ok(StopReason::Breakpoint(program.symbol("inner").unwrap())));
+    }
+
+    #[test]
+    fn test_run_watched() {
+        let mut cpu = Cpu::new();
+        let mut bus = WatchBus::new(DummyBus::new());
+        let program = Assembler::new(Variant::Nmos6502).assemble("
+                .org $0200
+                ldx #3
+        loop:   lda #0
+                sta $40,x
+        store:  dex
+                bne loop
+                brk
+        ").unwrap();
+        program.load(bus.bus_mut());
+        bus.bus_mut().data[0x42] = 0x55;
+        bus.add_watchpoint(Watchpoint::range(0x40, 0x4F, WatchKind::Write));
+        cpu.set_program_counter(0x0200);
+
+        let store = program.symbol("store").unwrap() - 2;
+        let hit = WatchHit { pc: store, addr: 0x43, access: AccessKind::Write, old_value: 0x00, new_value: 0x00 };
+        assert_eq!(cpu.run_watched(&mut bus, &RunUntil::default()), Ok(StopReason::Watchpoint(hit)));
+        assert_eq!(cpu.program_counter(), store + 2);
+
+        let hit = WatchHit { addr: 0x42, old_value: 0x55, ..hit };
+        assert_eq!(cpu.run_watched(&mut bus, &RunUntil::default()), Ok(StopReason::Watchpoint(hit)));
+    }
+
+    /// Writer whose output stays readable once it is given to a tracer.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+    impl std::io::Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_tracer() {
+        let mut cpu = Cpu::with_variant(Variant::Ricoh2A03);
+        let mut bus = DummyBus::new();
+        let program = Assembler::new(Variant::Ricoh2A03).assemble("
+                .org $C000
+                ldx #$01
+                stx $10
+                inc $10
+                jmp $C000
+        ").unwrap();
+        program.load(&mut bus);
+        bus.data[0xFFFC] = 0x00;
+        bus.data[0xFFFD] = 0xC0;
+        let output = SharedBuffer::default();
+        cpu.set_tracer(NestestTracer::new(output.clone()));
+        cpu.reset(&mut bus);
+        // The power up state of nestest.log.
+        cpu.set_stack_pointer(0xFD);
+        cpu.set_status(0x24);
+
+        assert_eq!(cpu.run(&mut bus, &RunUntil::instructions(4)), Ok(StopReason::InstructionLimit));
+        assert!(cpu.take_tracer().unwrap().finish().is_ok());
+        cpu.single_step(&mut bus).unwrap();
+
+        let log = String::from_utf8(output.0.borrow().clone()).unwrap();
+        assert_eq!(log, "\
+C000  A2 01     LDX #$01                        A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+C002  86 10     STX $10 = 00                    A:00 X:01 Y:00 P:24 SP:FD PPU:  0, 27 CYC:9
+C004  E6 10     INC $10 = 01                    A:00 X:01 Y:00 P:24 SP:FD PPU:  0, 36 CYC:12
+C006  4C 00 C0  JMP $C000                       A:00 X:01 Y:00 P:24 SP:FD PPU:  0, 51 CYC:17
+");
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut cpu = Cpu::with_variant(Variant::Mos6510);
+        let mut bus = DummyBus::new();
+        cpu.registers.accumulator = 0x2F;
+        run_program(&mut cpu, &mut bus, &[0x85, 0x00]);
+        cpu.signal_irq();
+        cpu.signal_nmi();
+        let snapshot = cpu.snapshot();
+
+        // Run the pending NMI and change the port before restoring.
+        cpu.single_step(&mut bus).unwrap();
+        cpu.io_port_mut().unwrap().write(0x0000, 0x00, 0);
+        cpu.restore(&snapshot).unwrap();
+        assert_eq!(cpu.snapshot(), snapshot);
+        assert_eq!(cpu.io_port().unwrap().direction(), 0x2F);
+        assert_eq!(cpu.program_counter(), 0x0202);
+
+        let mut cpu = Cpu::new();
+        cpu.restore(&Snapshot::from_bytes(&snapshot.to_bytes()).unwrap()).unwrap();
+        assert_eq!(cpu.variant(), Variant::Mos6510);
+        assert!(cpu.registers.nmi_active);
+        assert!(cpu.registers.irq_active);
+    }
+
+    #[test]
+    fn test_restore_rejects_incompatible_snapshots() {
+        let mut cpu = Cpu::new();
+        cpu.registers.accumulator = 0x42;
+
+        let snapshot = Snapshot { version: SNAPSHOT_VERSION + 1, ..Cpu::new().snapshot() };
+        assert_eq!(cpu.restore(&snapshot),
+                   Err(SnapshotError::UnsupportedVersion { found: SNAPSHOT_VERSION + 1, supported: SNAPSHOT_VERSION }));
+        let snapshot = Snapshot { variant: Variant::Mos6510, ..Cpu::new().snapshot() };
+        assert_eq!(cpu.restore(&snapshot), Err(SnapshotError::InvalidField { field: "io_port" }));
+        assert_eq!(cpu.accumulator(), 0x42);
+    }
+
+    #[test]
+    fn test_cmos_instructions() {
+        let mut cpu = Cpu::with_variant(Variant::Wdc65c02);
+        let mut bus = DummyBus::new();
+        bus.data[0x0010] = 0xFF;
+        cpu.registers.accumulator = 0x0F;
+        cpu.registers.x_index = 0x42;
+
+        // STZ $10
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0x64, 0x10]), 3);
+        assert_eq!(bus.data[0x0010], 0x00);
+        // TSB $10
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0x04, 0x10]), 5);
+        assert_eq!(bus.data[0x0010], 0x0F);
+        assert!(cpu.registers.status_reg.zero);
+        // SMB7 $10 ; RMB0 $10
+        run_program(&mut cpu, &mut bus, &[0xF7, 0x10]);
+        run_program(&mut cpu, &mut bus, &[0x07, 0x10]);
+        assert_eq!(bus.data[0x0010], 0x8E);
+        // BBS7 $10,+$10 ; BBR7 $10,+$10
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0xFF, 0x10, 0x10]), 6);
+        assert_eq!(cpu.registers.program_counter, 0x0213);
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0x7F, 0x10, 0x10]), 5);
+        assert_eq!(cpu.registers.program_counter, 0x0203);
+        // BRA -$10
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0x80, 0xF0]), 4);
+        assert_eq!(cpu.registers.program_counter, 0x01F2);
+        // PHX ; PLY
+        run_program(&mut cpu, &mut bus, &[0xDA]);
+        run_program(&mut cpu, &mut bus, &[0x7A]);
+        assert_eq!(cpu.registers.y_index, 0x42);
+        // INC A
+        run_program(&mut cpu, &mut bus, &[0x1A]);
+        assert_eq!(cpu.registers.accumulator, 0x10);
+        // BIT #$80 leaves N alone
+        run_program(&mut cpu, &mut bus, &[0x89, 0x80]);
+        assert!(cpu.registers.status_reg.zero);
+        assert!(!cpu.registers.status_reg.negative);
+        // LDA ($10)
+        bus.data[0x0010..0x0012].copy_from_slice(&[0x34, 0x12]);
+        bus.data[0x1234] = 0x99;
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0xB2, 0x10]), 5);
+        assert_eq!(cpu.registers.accumulator, 0x99);
+        // Single cycle NOP
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0x03]), 1);
+        assert_eq!(cpu.registers.program_counter, 0x0201);
+    }
+
+    #[test]
+    fn test_cmos_decimal_mode() {
+        let mut cpu = Cpu::with_variant(Variant::Wdc65c02);
+        let mut bus = DummyBus::new();
+        cpu.registers.status_reg.decimal_mode = true;
+        cpu.registers.accumulator = 0x99;
+
+        // ADC #$01 takes an extra cycle and sets Z from the BCD result.
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0x69, 0x01]), 3);
+        assert_eq!(cpu.registers.accumulator, 0x00);
+        assert!(cpu.registers.status_reg.zero);
+        assert!(!cpu.registers.status_reg.negative);
+        assert!(cpu.registers.status_reg.carry);
+    }
+
+    #[test]
+    fn test_cmos_interrupt_clears_decimal() {
+        for &variant in [Variant::Nmos6502, Variant::Wdc65c02].iter() {
+            let mut cpu = Cpu::with_variant(variant);
+            let mut bus = DummyBus::new();
+            cpu.registers.status_reg.decimal_mode = true;
+            cpu.signal_nmi();
+            cpu.single_step(&mut bus).unwrap();
+            assert_eq!(cpu.registers.status_reg.decimal_mode, !variant.is_cmos());
+
+            // BRK
+            cpu.registers.status_reg.decimal_mode = true;
+            run_program(&mut cpu, &mut bus, &[0x00]);
+            assert_eq!(cpu.registers.status_reg.decimal_mode, !variant.is_cmos());
+        }
+    }
+
+    #[test]
+    fn test_wai() {
+        let mut cpu = Cpu::with_variant(Variant::Wdc65c02);
+        let mut bus = DummyBus::new();
+        bus.data[0x0201] = 0xEA;
+        cpu.registers.status_reg.irq_disable = true;
+
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0xCB]), 3);
+        assert!(cpu.is_waiting());
+        assert_eq!(cpu.single_step(&mut bus), Ok(1));
+        assert_eq!(cpu.registers.program_counter, 0x0201);
+
+        // A masked IRQ resumes execution after WAI.
+        cpu.signal_irq();
+        assert_eq!(cpu.single_step(&mut bus), Ok(2));
+        assert!(!cpu.is_waiting());
+        assert_eq!(cpu.registers.program_counter, 0x0202);
+    }
+
+    #[test]
+    fn test_stp() {
+        let mut cpu = Cpu::with_variant(Variant::Wdc65c02);
+        let mut bus = DummyBus::new();
+        bus.data[0xFFFC] = 0x00;
+        bus.data[0xFFFD] = 0x03;
+        bus.data[0x0300] = 0xEA;
+
+        assert_eq!(run_program(&mut cpu, &mut bus, &[0xDB]), 3);
+        assert_eq!(cpu.single_step(&mut bus), Err(Error::Stopped { addr: 0x0201 }));
+        cpu.signal_nmi();
+        assert_eq!(cpu.single_step(&mut bus), Err(Error::Stopped { addr: 0x0201 }));
+
+        cpu.reset(&mut bus);
+        assert_eq!(cpu.single_step(&mut bus), Ok(2));
+    }
+
+    #[test]
+    fn test_jam() {
+        let mut cpu = Cpu::new();
+        let mut bus = DummyBus::new();
+        bus.data[0x0200] = 0x02;
+        bus.data[0x0300] = 0xEA;
+        bus.data[0xFFFC] = 0x00;
+        bus.data[0xFFFD] = 0x03;
+        cpu.registers.program_counter = 0x0200;
+
+        let jam = Err(Error::Jam { addr: 0x0200, opcode: 0x02 });
+        assert_eq!(cpu.single_step(&mut bus), jam);
+
+        // Interrupts are not serviced while jammed
+        cpu.signal_nmi();
+        assert_eq!(cpu.single_step(&mut bus), jam);
+        assert_eq!(cpu.registers.program_counter, 0x0200);
+
+        cpu.reset(&mut bus);
+        assert_eq!(cpu.single_step(&mut bus), Ok(2));
+        assert_eq!(cpu.registers.program_counter, 0x0301);
+    }
+
+    #[test]
+    fn test_register_api() {
+        let mut cpu = Cpu::new();
+        cpu.set_accumulator(0x12);
+        cpu.set_x_index(0x34);
+        cpu.set_y_index(0x56);
+        cpu.set_stack_pointer(0xFD);
+        cpu.set_program_counter(0xC000);
+
+        assert_eq!(cpu.accumulator(), 0x12);
+        assert_eq!(cpu.x_index(), 0x34);
+        assert_eq!(cpu.y_index(), 0x56);
+        assert_eq!(cpu.stack_pointer(), 0xFD);
+        assert_eq!(cpu.program_counter(), 0xC000);
+    }
+
+    #[test]
+    fn test_status_api() {
+        let mut cpu = Cpu::new();
+        assert_eq!(cpu.status(), 0x20);
+
+        cpu.set_status(0xFF);
+        assert_eq!(cpu.status(), 0xEF);
+        assert!(cpu.flag(Flag::DecimalMode));
+
+        cpu.set_flag(Flag::Negative, false);
+        cpu.set_flag(Flag::Carry, false);
+        assert!(!cpu.flag(Flag::Negative));
+        assert_eq!(cpu.status(), 0x6E);
+    }
+}
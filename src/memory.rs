@@ -0,0 +1,296 @@
+use crate::bus::Bus;
+use std::ops::RangeInclusive;
+
+/// Read/write memory. Addresses wrap around its size, so a chip smaller than
+/// 64K repeats over the whole address space.
+pub struct Ram {
+    mem: Vec<u8>,
+}
+
+impl Ram {
+    ///
+    /// Constructs 64K of RAM, filled with zeros.
+    ///
+    pub fn new() -> Ram {
+        Ram::with_size(0x10000)
+    }
+
+    ///
+    /// Constructs a RAM chip of the given size in bytes, at most 64K,
+    /// filled with zeros.
+    ///
+    pub fn with_size(size: usize) -> Ram {
+        assert!(size > 0 && size <= 0x10000, "RAM size must be between 1 and 64K");
+        Ram { mem: vec![0; size] }
+    }
+
+    ///
+    /// Copies the bytes to the given address, wrapping around the end of
+    /// the memory.
+    ///
+    pub fn load(&mut self, addr: u16, bytes: &[u8]) {
+        for (offset, byte) in bytes.iter().enumerate() {
+            let index = (addr as usize + offset) % self.mem.len();
+            self.mem[index] = *byte;
+        }
+    }
+
+    /// Returns the contents of the memory.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.mem
+    }
+
+    /// Returns the contents of the memory, for direct modification.
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.mem
+    }
+}
+
+impl Default for Ram {
+    fn default() -> Self {
+        Ram::new()
+    }
+}
+
+impl Bus for Ram {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.mem[addr as usize % self.mem.len()]
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        let index = addr as usize % self.mem.len();
+        self.mem[index] = value;
+    }
+}
+
+/// Read-only memory. Addresses wrap around its size, like `Ram`. Writes are
+/// ignored, and can be logged to spot programs writing to ROM.
+pub struct Rom {
+    data: Vec<u8>,
+    writes: Option<Vec<(u16, u8)>>,
+}
+
+impl Rom {
+    ///
+    /// Constructs a ROM holding the given bytes, at most 64K of them.
+    ///
+    pub fn new(data: Vec<u8>) -> Rom {
+        assert!(!data.is_empty() && data.len() <= 0x10000, "ROM size must be between 1 and 64K");
+        Rom { data, writes: None }
+    }
+
+    ///
+    /// Starts or stops keeping the writes, which are ignored either way.
+    /// Stopping forgets the writes kept so far.
+    ///
+    pub fn set_write_logging(&mut self, enabled: bool) {
+        self.writes = if enabled { Some(self.writes.take().unwrap_or_default()) } else { None };
+    }
+
+    /// Returns the writes kept since logging started, as address and value.
+    pub fn writes(&self) -> &[(u16, u8)] {
+        self.writes.as_deref().unwrap_or(&[])
+    }
+
+    /// Returns the writes kept so far and forgets them.
+    pub fn take_writes(&mut self) -> Vec<(u16, u8)> {
+        self.writes.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Returns the contents of the ROM.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Bus for Rom {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.data[addr as usize % self.data.len()]
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        if let Some(writes) = self.writes.as_mut() {
+            writes.push((addr, value));
+        }
+    }
+}
+
+/// Repeats the first bytes of a range over the rest of it, the way a chip
+/// that doesn't decode every address line appears on the bus. Other
+/// addresses are passed through unchanged.
+///
+/// ```
+/// use mos6502::{Bus, Mirror, Ram};
+///
+/// // 2K of RAM mirrored across $0000-$1FFF.
+/// let mut bus = Mirror::new(Ram::new(), 0x0000..=0x1FFF, 0x0800);
+/// bus.write(0x1801, 0x42);
+/// assert_eq!(bus.read(0x0001), 0x42);
+/// assert_eq!(bus.bus().as_slice()[0x1801], 0x00);
+/// ```
+pub struct Mirror<T: Bus> {
+    bus: T,
+    range: RangeInclusive<u16>,
+    size: u16,
+}
+
+impl<T: Bus> Mirror<T> {
+    ///
+    /// Maps every address of the range to the address at the same offset
+    /// in its first `size` bytes.
+    ///
+    pub fn new(bus: T, range: RangeInclusive<u16>, size: u16) -> Self {
+        assert!(size > 0, "mirrored size must not be zero");
+        Mirror { bus, range, size }
+    }
+
+    /// Returns the wrapped bus.
+    pub fn bus(&self) -> &T {
+        &self.bus
+    }
+
+    /// Returns the wrapped bus.
+    pub fn bus_mut(&mut self) -> &mut T {
+        &mut self.bus
+    }
+
+    /// Unwraps the bus.
+    pub fn into_inner(self) -> T {
+        self.bus
+    }
+
+    fn translate(&self, addr: u16) -> u16 {
+        if self.range.contains(&addr) {
+            let start = *self.range.start();
+            start + (addr - start) % self.size
+        } else {
+            addr
+        }
+    }
+}
+
+impl<T: Bus> Bus for Mirror<T> {
+    fn read(&mut self, addr: u16) -> u8 {
+        let addr = self.translate(addr);
+        self.bus.read(addr)
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        let addr = self.translate(addr);
+        self.bus.write(addr, value)
+    }
+
+    fn peek(&mut self, addr: u16) -> u8 {
+        let addr = self.translate(addr);
+        self.bus.peek(addr)
+    }
+}
+
+/// Leaves some ranges of a bus unmapped. Reads there return the last value
+/// seen on the data bus, as no device drives it, and writes are dropped.
+pub struct OpenBus<T: Bus> {
+    bus: T,
+    unmapped: Vec<RangeInclusive<u16>>,
+    data_bus: u8,
+}
+
+impl<T: Bus> OpenBus<T> {
+    /// Wraps a bus, with every address mapped.
+    pub fn new(bus: T) -> Self {
+        OpenBus { bus, unmapped: Vec::new(), data_bus: 0 }
+    }
+
+    ///
+    /// Disconnects the addresses of the range from the wrapped bus.
+    ///
+    pub fn unmap(mut self, range: RangeInclusive<u16>) -> Self {
+        self.unmapped.push(range);
+        self
+    }
+
+    ///
+    /// Returns the last value read or written, which unmapped addresses
+    /// read as.
+    ///
+    pub fn data_bus(&self) -> u8 {
+        self.data_bus
+    }
+
+    /// Returns the wrapped bus.
+    pub fn bus(&self) -> &T {
+        &self.bus
+    }
+
+    /// Returns the wrapped bus.
+    pub fn bus_mut(&mut self) -> &mut T {
+        &mut self.bus
+    }
+
+    /// Unwraps the bus.
+    pub fn into_inner(self) -> T {
+        self.bus
+    }
+
+    fn is_mapped(&self, addr: u16) -> bool {
+        !self.unmapped.iter().any(|range| range.contains(&addr))
+    }
+}
+
+impl<T: Bus> Bus for OpenBus<T> {
+    fn read(&mut self, addr: u16) -> u8 {
+        if self.is_mapped(addr) {
+            self.data_bus = self.bus.read(addr);
+        }
+        self.data_bus
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        if self.is_mapped(addr) {
+            self.bus.write(addr, value);
+        }
+        self.data_bus = value;
+    }
+
+    fn peek(&mut self, addr: u16) -> u8 {
+        if self.is_mapped(addr) { self.bus.peek(addr) } else { self.data_bus }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cpu;
+
+    #[test]
+    fn test_ram_and_rom() {
+        let mut ram = Ram::with_size(0x0800);
+        ram.load(0x07FF, &[0x11, 0x22]);
+        assert_eq!(ram.read(0x0000), 0x22);
+        ram.write(0x0803, 0x33);
+        assert_eq!(ram.as_slice()[0x0003], 0x33);
+
+        let mut rom = Rom::new(vec![0xEA, 0x60]);
+        rom.write(0x0000, 0x00);
+        assert_eq!(rom.writes(), &[]);
+        rom.set_write_logging(true);
+        rom.write(0xC001, 0x42);
+        assert_eq!((rom.read(0x0000), rom.read(0xC001)), (0xEA, 0x60));
+        assert_eq!(rom.take_writes(), vec![(0xC001, 0x42)]);
+        assert_eq!(rom.writes(), &[]);
+    }
+
+    #[test]
+    fn test_open_bus() {
+        // LDA $4000 reads its own high operand byte back.
+        let mut bus = OpenBus::new(Ram::new()).unmap(0x4000..=0x5FFF);
+        bus.bus_mut().load(0x0200, &[0xAD, 0x00, 0x40]);
+        let mut cpu = Cpu::new();
+        cpu.set_program_counter(0x0200);
+        cpu.single_step(&mut bus).unwrap();
+        assert_eq!(cpu.accumulator(), 0x40);
+
+        bus.write(0x4000, 0x99);
+        assert_eq!(bus.bus().as_slice()[0x4000], 0x00);
+        assert_eq!((bus.data_bus(), bus.peek(0x5FFF), bus.peek(0x6000)), (0x99, 0x99, 0x00));
+    }
+}
@@ -0,0 +1,604 @@
+use crate::bus::Bus;
+use std::any::Any;
+use std::ops::RangeInclusive;
+
+/// Entry of the decoding table for addresses no region covers.
+const UNMAPPED: u16 = u16::MAX;
+
+/// Handle to a device added to a `MemoryMap`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DeviceId(usize);
+
+/// Handle to a region mapped in a `MemoryMap`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RegionId(usize);
+
+/// Range of addresses decoded to a device.
+///
+/// The device sees `base + ((addr - start) & mask)`: offsets from the start
+/// of the range by default, so the same device can be mapped anywhere. A
+/// mask repeats the device over the range, and a base of `start` gives the
+/// device the address as issued by the processor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mapping {
+    pub range: RangeInclusive<u16>,
+    pub device: DeviceId,
+    /// Among overlapping regions, the one with the highest priority is
+    /// decoded. Ties go to the region mapped last.
+    pub priority: i32,
+    pub mask: u16,
+    pub base: u16,
+}
+
+impl Mapping {
+    ///
+    /// Maps the range to the device, with priority 0 and no mirroring.
+    ///
+    pub fn new(range: RangeInclusive<u16>, device: DeviceId) -> Mapping {
+        Mapping { range, device, priority: 0, mask: 0xFFFF, base: 0 }
+    }
+
+    /// Sets the priority over overlapping regions.
+    pub fn with_priority(self, priority: i32) -> Mapping {
+        Mapping { priority, ..self }
+    }
+
+    ///
+    /// Masks the offsets into the range, e.g. `0x07FF` to mirror 2K of RAM
+    /// over a larger range.
+    ///
+    pub fn with_mask(self, mask: u16) -> Mapping {
+        Mapping { mask, ..self }
+    }
+
+    /// Sets the address the device sees at the start of the range.
+    pub fn with_base(self, base: u16) -> Mapping {
+        Mapping { base, ..self }
+    }
+}
+
+/// One of the alternatives a banked region switches between.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bank {
+    pub device: DeviceId,
+    /// Address the device sees at the start of the region.
+    pub base: u16,
+    /// Device and base taking the writes instead, e.g. the RAM under a ROM.
+    pub writes: Option<(DeviceId, u16)>,
+    /// Name shown to debuggers, possibly empty.
+    pub name: String,
+}
+
+impl Bank {
+    ///
+    /// Selects the device for reads and writes, from address 0.
+    ///
+    pub fn new(device: DeviceId) -> Bank {
+        Bank { device, base: 0, writes: None, name: String::new() }
+    }
+
+    /// Sets the address the device sees at the start of the region.
+    pub fn with_base(self, base: u16) -> Bank {
+        Bank { base, ..self }
+    }
+
+    ///
+    /// Sends the writes to another device, starting at the given address,
+    /// e.g. the RAM a C64 writes to under its ROMs.
+    ///
+    pub fn with_writes_to(self, device: DeviceId, base: u16) -> Bank {
+        Bank { writes: Some((device, base)), ..self }
+    }
+
+    /// Names the bank for debuggers.
+    pub fn with_name(self, name: &str) -> Bank {
+        Bank { name: name.to_string(), ..self }
+    }
+}
+
+/// State of a region, as returned by `MemoryMap::layout`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegionLayout {
+    pub region: RegionId,
+    /// Range, priority, mask, and device and base of the reads.
+    pub mapping: Mapping,
+    /// Device and base of the writes, when they go elsewhere.
+    pub writes: Option<(DeviceId, u16)>,
+    /// Index of the selected bank, for banked regions.
+    pub bank: Option<usize>,
+    /// Name of the selected bank, empty if none.
+    pub bank_name: String,
+    /// Number of banks of the region, 0 if it is not banked.
+    pub bank_count: usize,
+}
+
+/// Region of a `MemoryMap`, with its banks if it has some.
+struct Region {
+    mapping: Mapping,
+    banks: Vec<Bank>,
+    selected: Option<usize>,
+    writes: Option<(DeviceId, u16)>,
+}
+
+impl Region {
+    fn select(&mut self, bank: usize) {
+        let Bank { device, base, writes, .. } = *self.banks.get(bank)
+            .unwrap_or_else(|| panic!("no bank {} in the region", bank));
+        self.mapping.device = device;
+        self.mapping.base = base;
+        self.writes = writes;
+        self.selected = Some(bank);
+    }
+}
+
+/// Handle given to the bank switches of a `MemoryMap`, to read the state of
+/// the devices and select banks. The selections are applied together once
+/// the switch returns.
+pub struct BankSelector<'a> {
+    devices: &'a [Box<dyn Device>],
+    selections: Vec<(RegionId, usize)>,
+}
+
+impl<'a> BankSelector<'a> {
+    ///
+    /// Selects a bank of a region, once the switch returns.
+    ///
+    pub fn select(&mut self, region: RegionId, bank: usize) {
+        self.selections.push((region, bank));
+    }
+
+    ///
+    /// Returns a device of the map, if it has the given type.
+    ///
+    pub fn device<D: Bus + 'static>(&self, device: DeviceId) -> Option<&D> {
+        self.devices.get(device.0)?.as_any().downcast_ref()
+    }
+}
+
+/// Callback selecting banks after a write to a control register.
+type BankSwitch = Box<dyn FnMut(u16, u8, &mut BankSelector)>;
+
+/// Bus a `MemoryMap` can own and hand back.
+trait Device: Bus {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Bus + 'static> Device for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Bus decoding addresses to devices, declared as a list of ranges instead
+/// of a match in `Bus::read` and `Bus::write`.
+///
+/// Regions can overlap, the one with the highest priority wins, and can be
+/// mapped, moved and removed while the program runs. Addresses no region
+/// covers are open bus: reads return the last value on the data bus and
+/// writes are dropped.
+///
+/// Regions can also switch between banks, selected directly or by bank
+/// switches reacting to writes to control registers, as done by the C64
+/// PLA or NES mappers.
+///
+/// ```
+/// use mos6502::{Bus, MemoryMap, Ram, Rom};
+///
+/// let mut bus = MemoryMap::new()
+///     .with(0x0000..=0x1FFF, Ram::with_size(0x0800))
+///     .with(0x8000..=0xFFFF, Rom::new(vec![0xEA; 0x8000]));
+/// bus.write(0x0801, 0x42);
+/// assert_eq!(bus.read(0x0001), 0x42);
+/// assert_eq!(bus.read(0xC000), 0xEA);
+/// ```
+pub struct MemoryMap {
+    devices: Vec<Box<dyn Device>>,
+    regions: Vec<Option<Region>>,
+    /// Index of the region decoded at every address.
+    decode: Vec<u16>,
+    switches: Vec<(RangeInclusive<u16>, BankSwitch)>,
+    data_bus: u8,
+}
+
+impl MemoryMap {
+    ///
+    /// Constructs a map with no devices, where every address is open bus.
+    ///
+    pub fn new() -> MemoryMap {
+        MemoryMap {
+            devices: Vec::new(),
+            regions: Vec::new(),
+            decode: vec![UNMAPPED; 0x10000],
+            switches: Vec::new(),
+            data_bus: 0,
+        }
+    }
+
+    ///
+    /// Adds the device and maps it over the range, with the defaults of
+    /// `Mapping::new`.
+    ///
+    pub fn with<D: Bus + 'static>(mut self, range: RangeInclusive<u16>, device: D) -> Self {
+        let device = self.add_device(device);
+        self.map(Mapping::new(range, device));
+        self
+    }
+
+    ///
+    /// Adds a device, which is not visible until it is mapped.
+    ///
+    pub fn add_device<D: Bus + 'static>(&mut self, device: D) -> DeviceId {
+        self.devices.push(Box::new(device));
+        DeviceId(self.devices.len() - 1)
+    }
+
+    ///
+    /// Returns the device, if it has the given type.
+    ///
+    pub fn device<D: Bus + 'static>(&self, device: DeviceId) -> Option<&D> {
+        self.devices.get(device.0)?.as_any().downcast_ref()
+    }
+
+    ///
+    /// Returns the device, if it has the given type.
+    ///
+    pub fn device_mut<D: Bus + 'static>(&mut self, device: DeviceId) -> Option<&mut D> {
+        self.devices.get_mut(device.0)?.as_any_mut().downcast_mut()
+    }
+
+    ///
+    /// Maps a region. Panics if the device was not added to this map.
+    ///
+    pub fn map(&mut self, mapping: Mapping) -> RegionId {
+        assert!(mapping.device.0 < self.devices.len(), "unknown device {:?}", mapping.device);
+        assert!(self.regions.len() < UNMAPPED as usize, "too many regions");
+        self.regions.push(Some(Region { mapping, banks: Vec::new(), selected: None, writes: None }));
+        self.rebuild();
+        RegionId(self.regions.len() - 1)
+    }
+
+    ///
+    /// Replaces the mapping of a region, e.g. to move it or change its
+    /// device, and drops its banks. Returns the previous mapping, or `None`
+    /// if the region was removed.
+    ///
+    pub fn remap(&mut self, region: RegionId, mapping: Mapping) -> Option<Mapping> {
+        assert!(mapping.device.0 < self.devices.len(), "unknown device {:?}", mapping.device);
+        let slot = self.regions.get_mut(region.0)?.as_mut()?;
+        let previous = std::mem::replace(slot, Region { mapping, banks: Vec::new(), selected: None, writes: None });
+        self.rebuild();
+        Some(previous.mapping)
+    }
+
+    ///
+    /// Removes a region, uncovering the ones below it. Returns its mapping,
+    /// or `None` if it was already removed.
+    ///
+    pub fn unmap(&mut self, region: RegionId) -> Option<Mapping> {
+        let region = self.regions.get_mut(region.0)?.take()?;
+        self.rebuild();
+        Some(region.mapping)
+    }
+
+    ///
+    /// Returns the mapping of a region, unless it was removed. The device
+    /// and base of a banked region are the ones of its selected bank.
+    ///
+    pub fn mapping(&self, region: RegionId) -> Option<&Mapping> {
+        self.regions.get(region.0)?.as_ref().map(|region| &region.mapping)
+    }
+
+    /// Returns the regions, in the order they were mapped.
+    pub fn regions(&self) -> impl Iterator<Item = (RegionId, &Mapping)> + '_ {
+        self.regions.iter().enumerate()
+            .filter_map(|(index, region)| region.as_ref().map(|region| (RegionId(index), &region.mapping)))
+    }
+
+    ///
+    /// Turns a region into a banked one and selects its first bank. Its
+    /// range, priority and mask are kept. Panics if the region was removed,
+    /// there are no banks or a device is unknown.
+    ///
+    pub fn set_banks(&mut self, region: RegionId, banks: Vec<Bank>) {
+        assert!(!banks.is_empty(), "a banked region needs at least one bank");
+        for bank in &banks {
+            let write_device = bank.writes.map_or(bank.device, |(device, _)| device);
+            assert!(bank.device.0 < self.devices.len() && write_device.0 < self.devices.len(),
+                    "unknown device in bank {:?}", bank);
+        }
+        let region = self.region_mut(region);
+        region.banks = banks;
+        region.select(0);
+    }
+
+    /// Returns the banks of a region, empty if it is not banked.
+    pub fn banks(&self, region: RegionId) -> &[Bank] {
+        self.regions.get(region.0).and_then(Option::as_ref).map_or(&[], |region| &region.banks)
+    }
+
+    /// Returns the selected bank of a region, if it is banked.
+    pub fn selected_bank(&self, region: RegionId) -> Option<usize> {
+        self.regions.get(region.0)?.as_ref()?.selected
+    }
+
+    ///
+    /// Selects a bank of a region. Panics if the region was removed or
+    /// doesn't have the bank.
+    ///
+    pub fn select_bank(&mut self, region: RegionId, bank: usize) {
+        self.region_mut(region).select(bank);
+    }
+
+    ///
+    /// Selects banks of several regions at once, e.g. from a register
+    /// driving all the chip selects of a board.
+    ///
+    pub fn select_banks(&mut self, selections: &[(RegionId, usize)]) {
+        for &(region, bank) in selections {
+            self.select_bank(region, bank);
+        }
+    }
+
+    ///
+    /// Calls the switch after every write to the range, with the address
+    /// and value written. The switch selects banks through the
+    /// `BankSelector`, which also gives access to the devices, e.g. to a
+    /// latch holding the banking bits. Writes to the range still reach the
+    /// device mapped there, if any.
+    ///
+    pub fn add_bank_switch<F>(&mut self, range: RangeInclusive<u16>, switch: F)
+        where F: FnMut(u16, u8, &mut BankSelector) + 'static {
+        self.switches.push((range, Box::new(switch)));
+    }
+
+    ///
+    /// Describes every region, from the highest priority down, for
+    /// debuggers showing what is visible at each address.
+    ///
+    pub fn layout(&self) -> Vec<RegionLayout> {
+        let mut layout: Vec<RegionLayout> = self.regions.iter().enumerate()
+            .filter_map(|(index, region)| {
+                let region_ref = region.as_ref()?;
+                let bank = region_ref.selected.map(|bank| &region_ref.banks[bank]);
+                Some(RegionLayout {
+                    region: RegionId(index),
+                    mapping: region_ref.mapping.clone(),
+                    writes: region_ref.writes,
+                    bank: region_ref.selected,
+                    bank_name: bank.map(|bank| bank.name.clone()).unwrap_or_default(),
+                    bank_count: region_ref.banks.len(),
+                })
+            })
+            .collect();
+        // Later regions win ties, as when decoding.
+        layout.reverse();
+        layout.sort_by_key(|region| std::cmp::Reverse(region.mapping.priority));
+        layout
+    }
+
+    ///
+    /// Returns the region decoded at the address, or `None` if it is open
+    /// bus.
+    ///
+    pub fn region_at(&self, addr: u16) -> Option<RegionId> {
+        match self.decode[addr as usize] {
+            UNMAPPED => None,
+            index => Some(RegionId(index as usize)),
+        }
+    }
+
+    /// Returns the last value read or written.
+    pub fn data_bus(&self) -> u8 {
+        self.data_bus
+    }
+
+    fn region_mut(&mut self, region: RegionId) -> &mut Region {
+        self.regions.get_mut(region.0).and_then(Option::as_mut)
+            .unwrap_or_else(|| panic!("unknown region {:?}", region))
+    }
+
+    ///
+    /// Runs the bank switches watching the address, then applies their
+    /// selections.
+    ///
+    fn switch_banks(&mut self, addr: u16, value: u8) {
+        let mut selector = BankSelector { devices: &self.devices, selections: Vec::new() };
+        for (range, switch) in self.switches.iter_mut() {
+            if range.contains(&addr) {
+                switch(addr, value, &mut selector);
+            }
+        }
+        let selections = selector.selections;
+        self.select_banks(&selections);
+    }
+
+    ///
+    /// Fills the decoding table, painting the regions from the lowest
+    /// priority up.
+    ///
+    fn rebuild(&mut self) {
+        let mut order: Vec<usize> = (0..self.regions.len()).filter(|&i| self.regions[i].is_some()).collect();
+        order.sort_by_key(|&i| self.regions[i].as_ref().map(|region| region.mapping.priority));
+        self.decode.iter_mut().for_each(|entry| *entry = UNMAPPED);
+        for index in order {
+            let range = self.regions[index].as_ref().unwrap().mapping.range.clone();
+            for addr in range {
+                self.decode[addr as usize] = index as u16;
+            }
+        }
+    }
+
+    ///
+    /// Returns the device and the address it sees, or `None` for open bus.
+    /// Writes can go to another device than reads in banked regions.
+    ///
+    fn resolve(&mut self, addr: u16, write: bool) -> Option<(&mut Box<dyn Device>, u16)> {
+        let region = self.regions.get(self.decode[addr as usize] as usize)?.as_ref()?;
+        let mapping = &region.mapping;
+        let (device, base) = match region.writes {
+            Some(target) if write => target,
+            _ => (mapping.device, mapping.base),
+        };
+        let device_addr = base.wrapping_add(addr.wrapping_sub(*mapping.range.start()) & mapping.mask);
+        Some((&mut self.devices[device.0], device_addr))
+    }
+}
+
+impl Default for MemoryMap {
+    fn default() -> Self {
+        MemoryMap::new()
+    }
+}
+
+impl Bus for MemoryMap {
+    fn read(&mut self, addr: u16) -> u8 {
+        if let Some((device, addr)) = self.resolve(addr, false) {
+            let value = device.read(addr);
+            self.data_bus = value;
+        }
+        self.data_bus
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        if let Some((device, device_addr)) = self.resolve(addr, true) {
+            device.write(device_addr, value);
+        }
+        self.data_bus = value;
+        if !self.switches.is_empty() {
+            self.switch_banks(addr, value);
+        }
+    }
+
+    fn peek(&mut self, addr: u16) -> u8 {
+        let data_bus = self.data_bus;
+        match self.resolve(addr, false) {
+            Some((device, addr)) => device.peek(addr),
+            None => data_bus,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{Ram, Rom};
+
+    #[test]
+    fn test_priorities_and_mirroring() {
+        let mut bus = MemoryMap::new();
+        let ram = bus.add_device(Ram::new());
+        let rom = bus.add_device(Rom::new(vec![0x11, 0x22]));
+        let io = bus.add_device(Ram::with_size(0x10));
+        bus.map(Mapping::new(0x0000..=0xFFFF, ram));
+        let rom_region = bus.map(Mapping::new(0xE000..=0xFFFF, rom).with_priority(1));
+        let io_region = bus.map(Mapping::new(0xD000..=0xDFFF, io).with_mask(0x000F).with_priority(2));
+        // Lower priority, hidden under the I/O registers.
+        bus.map(Mapping::new(0xD000..=0xD7FF, rom));
+
+        bus.write(0xD013, 0x42);
+        assert_eq!(bus.read(0xD003), 0x42);
+        assert_eq!(bus.device::<Ram>(io).unwrap().as_slice()[0x03], 0x42);
+        assert_eq!(bus.device::<Ram>(ram).unwrap().as_slice()[0xD013], 0x00);
+        assert!(bus.device::<Rom>(io).is_none());
+
+        assert_eq!((bus.read(0xE000), bus.read(0xFFFF)), (0x11, 0x22));
+        bus.write(0xE000, 0x99);
+        assert_eq!(bus.read(0xE000), 0x11);
+        assert_eq!(bus.region_at(0xE000), Some(rom_region));
+        assert_eq!(bus.region_at(0xD7FF), Some(io_region));
+    }
+
+    #[test]
+    fn test_open_bus_and_remapping() {
+        let mut bus = MemoryMap::new().with(0x0000..=0x07FF, Ram::with_size(0x0800));
+        let (region, mapping) = bus.regions().next().map(|(id, mapping)| (id, mapping.clone())).unwrap();
+
+        bus.write(0x0010, 0x5A);
+        assert_eq!(bus.read(0x0010), 0x5A);
+        bus.write(0x4000, 0xA5);
+        assert_eq!((bus.read(0x4000), bus.peek(0x4001), bus.data_bus()), (0xA5, 0xA5, 0xA5));
+        assert_eq!(bus.region_at(0x4000), None);
+
+        // Move the RAM up, as a board with a relocatable chip select.
+        let moved = Mapping { range: 0x4000..=0x47FF, ..mapping.clone() };
+        assert_eq!(bus.remap(region, moved), Some(mapping));
+        assert_eq!(bus.read(0x4010), 0x5A);
+        bus.write(0x0010, 0x00);
+        assert_eq!(bus.read(0x4010), 0x5A);
+
+        assert!(bus.unmap(region).is_some());
+        assert_eq!(bus.unmap(region), None);
+        assert_eq!(bus.regions().count(), 0);
+        assert_eq!(bus.read(0x4010), 0x5A);
+    }
+
+    #[test]
+    fn test_c64_style_banking() {
+        let mut bus = MemoryMap::new();
+        let ram = bus.add_device(Ram::new());
+        let basic = bus.add_device(Rom::new(vec![0xBA; 0x2000]));
+        let kernal = bus.add_device(Rom::new(vec![0xEE; 0x2000]));
+        let port = bus.add_device(Ram::with_size(1));
+        bus.map(Mapping::new(0x0000..=0xFFFF, ram));
+        bus.map(Mapping::new(0x0001..=0x0001, port).with_priority(1));
+        let basic_region = bus.map(Mapping::new(0xA000..=0xBFFF, basic).with_priority(1));
+        let kernal_region = bus.map(Mapping::new(0xE000..=0xFFFF, kernal).with_priority(1));
+        // Writes under the ROMs go to the RAM.
+        bus.set_banks(basic_region, vec![
+            Bank::new(basic).with_writes_to(ram, 0xA000).with_name("BASIC"),
+            Bank::new(ram).with_base(0xA000).with_name("RAM"),
+        ]);
+        bus.set_banks(kernal_region, vec![
+            Bank::new(kernal).with_writes_to(ram, 0xE000).with_name("KERNAL"),
+            Bank::new(ram).with_base(0xE000).with_name("RAM"),
+        ]);
+        // LORAM and HIRAM, read back from the port register.
+        bus.add_bank_switch(0x0001..=0x0001, move |_, _, banks| {
+            let bits = banks.device::<Ram>(port).unwrap().as_slice()[0] & 0x03;
+            banks.select(basic_region, if bits == 0x03 { 0 } else { 1 });
+            banks.select(kernal_region, if bits & 0x02 != 0 { 0 } else { 1 });
+        });
+
+        bus.write(0xA000, 0x42);
+        assert_eq!((bus.read(0xA000), bus.read(0xE000)), (0xBA, 0xEE));
+        assert_eq!(bus.device::<Ram>(ram).unwrap().as_slice()[0xA000], 0x42);
+
+        bus.write(0x0001, 0x35);
+        assert_eq!((bus.read(0xA000), bus.read(0xE000)), (0x42, 0x00));
+        assert_eq!(bus.selected_bank(basic_region), Some(1));
+        bus.write(0x0001, 0x36);
+        assert_eq!((bus.read(0xA000), bus.read(0xE000)), (0x42, 0xEE));
+
+        let layout = bus.layout();
+        assert_eq!(layout.iter().map(|region| region.region).collect::<Vec<_>>(),
+                   [kernal_region, basic_region, RegionId(1), RegionId(0)]);
+        assert_eq!((layout[0].bank, layout[0].bank_name.as_str(), layout[0].bank_count), (Some(0), "KERNAL", 2));
+        assert_eq!((layout[1].bank, layout[1].bank_name.as_str()), (Some(1), "RAM"));
+        assert_eq!(layout[1].writes, None);
+        assert_eq!(layout[3].bank, None);
+    }
+
+    #[test]
+    fn test_mapper_style_banking() {
+        // 64K of PRG ROM in 16K banks, the last one fixed at $C000.
+        let mut bus = MemoryMap::new();
+        let banks: Vec<DeviceId> = (0..4).map(|bank| bus.add_device(Rom::new(vec![bank; 0x4000]))).collect();
+        let switchable = bus.map(Mapping::new(0x8000..=0xBFFF, banks[0]));
+        bus.map(Mapping::new(0xC000..=0xFFFF, banks[3]));
+        bus.set_banks(switchable, banks.iter().map(|&rom| Bank::new(rom)).collect());
+        bus.add_bank_switch(0x8000..=0xFFFF, move |_, value, selector| selector.select(switchable, (value & 0x03) as usize));
+
+        assert_eq!((bus.read(0x8000), bus.read(0xFFFF)), (0, 3));
+        bus.write(0xC123, 0x02);
+        assert_eq!((bus.read(0xBFFF), bus.read(0xC000)), (2, 3));
+        assert_eq!(bus.mapping(switchable).map(|mapping| mapping.device), Some(banks[2]));
+        bus.select_bank(switchable, 1);
+        assert_eq!(bus.read(0x8000), 1);
+        assert_eq!(bus.banks(switchable).len(), 4);
+    }
+}
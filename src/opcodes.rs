@@ -1,262 +1,800 @@
 
 use crate::instruction::Instruction;
 use crate::addressing_modes::AddressingMode;
+use crate::variant::Variant;
 
-pub static OPCODES : [Option<(Instruction, AddressingMode)>; 256 ] = [
-    Some((Instruction::Brk, AddressingMode::Implied)),                // 0x00
-    Some((Instruction::Ora, AddressingMode::XIndexedIndirect)),       // 0x01
-    None,                                                             // 0x02
-    None,                                                             // 0x03
-    None,                                                             // 0x04
-    Some((Instruction::Ora, AddressingMode::Zeropage)),               // 0x05
-    Some((Instruction::Asl, AddressingMode::Zeropage)),               // 0x06
-    None,                                                             // 0x07
-    Some((Instruction::Php, AddressingMode::Implied)),                // 0x08
-    Some((Instruction::Ora, AddressingMode::Immediate)),              // 0x09
-    Some((Instruction::Asl, AddressingMode::Accumulator)),            // 0x0A
-    None,                                                             // 0x0B
-    None,                                                             // 0x0C
-    Some((Instruction::Ora, AddressingMode::Absolute)),               // 0x0D
-    Some((Instruction::Asl, AddressingMode::Absolute)),               // 0x0E
-    None,                                                             // 0x0F
-    Some((Instruction::Bpl, AddressingMode::Relative)),               // 0x10
-    Some((Instruction::Ora, AddressingMode::AbsoluteYIndexed)),       // 0x11
-    None,                                                             // 0x12
-    None,                                                             // 0x13
-    None,                                                             // 0x14
-    Some((Instruction::Ora, AddressingMode::ZeropageXIndexed)),       // 0x15
-    Some((Instruction::Asl, AddressingMode::ZeropageXIndexed)),       // 0x16
-    None,                                                             // 0x17
-    Some((Instruction::Clc, AddressingMode::Implied)),                // 0x18
-    Some((Instruction::Ora, AddressingMode::AbsoluteYIndexed)),       // 0x19
-    None,                                                             // 0x1A
-    None,                                                             // 0x1B
-    None,                                                             // 0x1C
-    Some((Instruction::Ora, AddressingMode::AbsoluteXIndexed)),       // 0x1D
-    Some((Instruction::Asl, AddressingMode::AbsoluteXIndexed)),       // 0x1E
-    None,                                                             // 0x1F
-    Some((Instruction::Jsr, AddressingMode::Absolute)),               // 0x20
-    Some((Instruction::And, AddressingMode::XIndexedIndirect)),       // 0x21
-    None,                                                             // 0x22
-    None,                                                             // 0x23
-    Some((Instruction::Bit, AddressingMode::Zeropage)),               // 0x24
-    Some((Instruction::And, AddressingMode::Zeropage)),               // 0x25
-    Some((Instruction::Rol, AddressingMode::Zeropage)),               // 0x26
-    None,                                                             // 0x27
-    Some((Instruction::Plp, AddressingMode::Implied)),                // 0x28
-    Some((Instruction::And, AddressingMode::Immediate)),              // 0x29
-    Some((Instruction::Rol, AddressingMode::Accumulator)),            // 0x2A
-    None,                                                             // 0x2B
-    Some((Instruction::Bit, AddressingMode::Absolute)),               // 0x2C
-    Some((Instruction::And, AddressingMode::Absolute)),               // 0x2D
-    Some((Instruction::Rol, AddressingMode::Absolute)),               // 0x2E
-    None,                                                             // 0x2F
-    Some((Instruction::Bmi, AddressingMode::Relative)),               // 0x30
-    Some((Instruction::And, AddressingMode::IndirectYIndexed)),       // 0x31
-    None,                                                             // 0x32
-    None,                                                             // 0x33
-    None,                                                             // 0x34
-    Some((Instruction::And, AddressingMode::ZeropageXIndexed)),       // 0x35
-    Some((Instruction::Rol, AddressingMode::ZeropageXIndexed)),       // 0x36
-    None,                                                             // 0x37
-    Some((Instruction::Sec, AddressingMode::Implied)),                // 0x38
-    Some((Instruction::And, AddressingMode::AbsoluteYIndexed)),       // 0x39
-    None,                                                             // 0x3A
-    None,                                                             // 0x3B
-    None,                                                             // 0x3C
-    Some((Instruction::And, AddressingMode::AbsoluteXIndexed)),       // 0x3D
-    Some((Instruction::Rol, AddressingMode::AbsoluteXIndexed)),       // 0x3E
-    None,                                                             // 0x3F
-    Some((Instruction::Rti, AddressingMode::Implied)),                // 0x40
-    Some((Instruction::Eor, AddressingMode::XIndexedIndirect)),       // 0x41
-    None,                                                             // 0x42
-    None,                                                             // 0x43
-    None,                                                             // 0x44
-    Some((Instruction::Eor, AddressingMode::Zeropage)),               // 0x45
-    Some((Instruction::Lsr, AddressingMode::Zeropage)),               // 0x46
-    None,                                                             // 0x47
-    Some((Instruction::Pha, AddressingMode::Implied)),                // 0x48
-    Some((Instruction::Eor, AddressingMode::Immediate)),              // 0x49
-    Some((Instruction::Lsr, AddressingMode::Accumulator)),            // 0x4A
-    None,                                                             // 0x4B
-    Some((Instruction::Jmp, AddressingMode::Absolute)),               // 0x4C
-    Some((Instruction::Eor, AddressingMode::Absolute)),               // 0x4D
-    Some((Instruction::Lsr, AddressingMode::Absolute)),               // 0x4E
-    None,                                                             // 0x4F
-    Some((Instruction::Bvc, AddressingMode::Relative)),               // 0x50
-    Some((Instruction::Eor, AddressingMode::IndirectYIndexed)),       // 0x51
-    None,                                                             // 0x52
-    None,                                                             // 0x53
-    None,                                                             // 0x54
-    Some((Instruction::Eor, AddressingMode::ZeropageXIndexed)),       // 0x55
-    Some((Instruction::Lsr, AddressingMode::ZeropageXIndexed)),       // 0x56
-    None,                                                             // 0x57
-    Some((Instruction::Cli, AddressingMode::Implied)),                // 0x58
-    Some((Instruction::Eor, AddressingMode::AbsoluteYIndexed)),       // 0x59
-    None,                                                             // 0x5A
-    None,                                                             // 0x5B
-    None,                                                             // 0x5C
-    Some((Instruction::Eor, AddressingMode::AbsoluteXIndexed)),       // 0x5D
-    Some((Instruction::Lsr, AddressingMode::AbsoluteXIndexed)),       // 0x5E
-    None,                                                             // 0x5F
-    Some((Instruction::Rts, AddressingMode::Implied)),                // 0x60
-    Some((Instruction::Adc, AddressingMode::XIndexedIndirect)),       // 0x61
-    None,                                                             // 0x62
-    None,                                                             // 0x63
-    None,                                                             // 0x64
-    Some((Instruction::Adc, AddressingMode::Zeropage)),               // 0x65
-    Some((Instruction::Ror, AddressingMode::Zeropage)),               // 0x66
-    None,                                                             // 0x67
-    Some((Instruction::Pla, AddressingMode::Implied)),                // 0x68
-    Some((Instruction::Adc, AddressingMode::Immediate)),              // 0x69
-    Some((Instruction::Ror, AddressingMode::Accumulator)),            // 0x6A
-    None,                                                             // 0x6B
-    Some((Instruction::Jmp, AddressingMode::Indirect)),               // 0x6C
-    Some((Instruction::Adc, AddressingMode::Absolute)),               // 0x6D
-    Some((Instruction::Ror, AddressingMode::Absolute)),               // 0x6E
-    None,                                                             // 0x6F
-    Some((Instruction::Bvs, AddressingMode::Relative)),               // 0x70
-    Some((Instruction::Adc, AddressingMode::IndirectYIndexed)),       // 0x71
-    None,                                                             // 0x72
-    None,                                                             // 0x73
-    None,                                                             // 0x74
-    Some((Instruction::Adc, AddressingMode::ZeropageXIndexed)),       // 0x75
-    Some((Instruction::Ror, AddressingMode::ZeropageXIndexed)),       // 0x76
-    None,                                                             // 0x77
-    Some((Instruction::Sei, AddressingMode::Implied)),                // 0x78
-    Some((Instruction::Adc, AddressingMode::AbsoluteYIndexed)),       // 0x79
-    None,                                                             // 0x7A
-    None,                                                             // 0x7B
-    None,                                                             // 0x7C
-    Some((Instruction::Adc, AddressingMode::AbsoluteXIndexed)),       // 0x7D
-    Some((Instruction::Ror, AddressingMode::AbsoluteXIndexed)),       // 0x7E
-    None,                                                             // 0x7F
-    None,                                                             // 0x80
-    Some((Instruction::Sta, AddressingMode::IndirectYIndexed)),       // 0x81
-    None,                                                             // 0x82
-    None,                                                             // 0x83
-    Some((Instruction::Sty, AddressingMode::Zeropage)),               // 0x84
-    Some((Instruction::Sta, AddressingMode::Zeropage)),               // 0x85
-    Some((Instruction::Stx, AddressingMode::Zeropage)),               // 0x86
-    None,                                                             // 0x87
-    Some((Instruction::Dey, AddressingMode::Implied)),                // 0x88
-    None,                                                             // 0x89
-    Some((Instruction::Txa, AddressingMode::Implied)),                // 0x8A
-    None,                                                             // 0x8B
-    Some((Instruction::Sty, AddressingMode::Absolute)),               // 0x8C
-    Some((Instruction::Sta, AddressingMode::Absolute)),               // 0x8D
-    Some((Instruction::Stx, AddressingMode::Absolute)),               // 0x8E
-    None,                                                             // 0x8F
-    Some((Instruction::Bcc, AddressingMode::Relative)),               // 0x90
-    Some((Instruction::Sta, AddressingMode::IndirectYIndexed)),       // 0x91
-    None,                                                             // 0x92
-    None,                                                             // 0x93
-    Some((Instruction::Sty, AddressingMode::ZeropageXIndexed)),       // 0x94
-    Some((Instruction::Sta, AddressingMode::ZeropageXIndexed)),       // 0x95
-    Some((Instruction::Stx, AddressingMode::ZeropageXIndexed)),       // 0x96
-    None,                                                             // 0x97
-    Some((Instruction::Tya, AddressingMode::Implied)),                // 0x98
-    Some((Instruction::Sta, AddressingMode::AbsoluteYIndexed)),       // 0x99
-    Some((Instruction::Txs, AddressingMode::Implied)),                // 0x9A
-    None,                                                             // 0x9B
-    None,                                                             // 0x9C
-    Some((Instruction::Sta, AddressingMode::AbsoluteXIndexed)),       // 0x9D
-    None,                                                             // 0x9E
-    None,                                                             // 0x9F
-    Some((Instruction::Ldy, AddressingMode::Immediate)),              // 0xA0
-    Some((Instruction::Lda, AddressingMode::XIndexedIndirect)),       // 0xA1
-    Some((Instruction::Ldx, AddressingMode::Immediate)),              // 0xA2
-    None,                                                             // 0xA3
-    Some((Instruction::Ldy, AddressingMode::Zeropage)),               // 0xA4
-    Some((Instruction::Lda, AddressingMode::Zeropage)),               // 0xA5
-    Some((Instruction::Ldx, AddressingMode::Zeropage)),               // 0xA6
-    None,                                                             // 0xA7
-    Some((Instruction::Tay, AddressingMode::Implied)),                // 0xA8
-    Some((Instruction::Lda, AddressingMode::Immediate)),              // 0xA9
-    Some((Instruction::Tax, AddressingMode::Implied)),                // 0xAA
-    None,                                                             // 0xAB
-    Some((Instruction::Ldy, AddressingMode::Absolute)),               // 0xAC
-    Some((Instruction::Lda, AddressingMode::Absolute)),               // 0xAD
-    Some((Instruction::Ldx, AddressingMode::Absolute)),               // 0xAE
-    None,                                                             // 0xAF
-    Some((Instruction::Bcs, AddressingMode::Relative)),               // 0xB0
-    Some((Instruction::Lda, AddressingMode::IndirectYIndexed)),       // 0xB1
-    None,                                                             // 0xB2
-    None,                                                             // 0xB3
-    Some((Instruction::Ldy, AddressingMode::ZeropageXIndexed)),       // 0xB4
-    Some((Instruction::Lda, AddressingMode::ZeropageXIndexed)),       // 0xB5
-    Some((Instruction::Ldx, AddressingMode::ZeropageYIndexed)),       // 0xB6
-    None,                                                             // 0xB7
-    Some((Instruction::Clv, AddressingMode::Implied)),                // 0xB8
-    Some((Instruction::Lda, AddressingMode::AbsoluteYIndexed)),       // 0xB9
-    Some((Instruction::Tsx, AddressingMode::Implied)),                // 0xBA
-    None,                                                             // 0xBB
-    Some((Instruction::Ldy, AddressingMode::AbsoluteXIndexed)),       // 0xBC
-    Some((Instruction::Lda, AddressingMode::AbsoluteXIndexed)),       // 0xBD
-    Some((Instruction::Ldx, AddressingMode::AbsoluteYIndexed)),       // 0xBE
-    None,                                                             // 0xBF
-    Some((Instruction::Cpy, AddressingMode::Immediate)),              // 0xC0
-    Some((Instruction::Cmp, AddressingMode::XIndexedIndirect)),       // 0xC1
-    None,                                                             // 0xC2
-    None,                                                             // 0xC3
-    Some((Instruction::Cpy, AddressingMode::Zeropage)),               // 0xC4
-    Some((Instruction::Cmp, AddressingMode::Zeropage)),               // 0xC5
-    Some((Instruction::Dec, AddressingMode::Zeropage)),               // 0xC6
-    None,                                                             // 0xC7
-    Some((Instruction::Iny, AddressingMode::Implied)),                // 0xC8
-    Some((Instruction::Cmp, AddressingMode::Immediate)),              // 0xC9
-    Some((Instruction::Dex, AddressingMode::Implied)),                // 0xCA
-    None,                                                             // 0xCB
-    Some((Instruction::Cpy, AddressingMode::Absolute)),               // 0xCC
-    Some((Instruction::Cmp, AddressingMode::Absolute)),               // 0xCD
-    Some((Instruction::Dec, AddressingMode::Absolute)),               // 0xCE
-    None,                                                             // 0xCF
-    Some((Instruction::Bne, AddressingMode::Relative)),               // 0xD0
-    Some((Instruction::Cmp, AddressingMode::IndirectYIndexed)),       // 0xD1
-    None,                                                             // 0xD2
-    None,                                                             // 0xD3
-    None,                                                             // 0xD4
-    Some((Instruction::Cmp, AddressingMode::ZeropageXIndexed)),       // 0xD5
-    Some((Instruction::Dec, AddressingMode::ZeropageXIndexed)),       // 0xD6
-    None,                                                             // 0xD7
-    Some((Instruction::Cld, AddressingMode::Implied)),                // 0xD8
-    Some((Instruction::Cmp, AddressingMode::AbsoluteYIndexed)),       // 0xD9
-    None,                                                             // 0xDA
-    None,                                                             // 0xDB
-    None,                                                             // 0xDC
-    Some((Instruction::Cmp, AddressingMode::AbsoluteXIndexed)),       // 0xDD
-    Some((Instruction::Dec, AddressingMode::AbsoluteXIndexed)),       // 0xDE
-    None,                                                             // 0xDF
-    Some((Instruction::Cpx, AddressingMode::Immediate)),              // 0xE0
-    Some((Instruction::Sbc, AddressingMode::XIndexedIndirect)),       // 0xE1
-    None,                                                             // 0xE2
-    None,                                                             // 0xE3
-    Some((Instruction::Cpx, AddressingMode::Zeropage)),               // 0xE4
-    Some((Instruction::Sbc, AddressingMode::Zeropage)),               // 0xE5
-    Some((Instruction::Inc, AddressingMode::Zeropage)),               // 0xE6
-    None,                                                             // 0xE7
-    Some((Instruction::Inx, AddressingMode::Implied)),                // 0xE8
-    Some((Instruction::Sbc, AddressingMode::Immediate)),              // 0xE9
-    Some((Instruction::Nop, AddressingMode::Implied)),                // 0xEA
-    None,                                                             // 0xEB
-    Some((Instruction::Cpx, AddressingMode::Absolute)),               // 0xEC
-    Some((Instruction::Sbc, AddressingMode::Absolute)),               // 0xED
-    Some((Instruction::Inc, AddressingMode::Absolute)),               // 0xEE
-    None,                                                             // 0xEF
-    Some((Instruction::Beq, AddressingMode::Relative)),               // 0xF0
-    Some((Instruction::Sbc, AddressingMode::IndirectYIndexed)),       // 0xF1
-    None,                                                             // 0xF2
-    None,                                                             // 0xF3
-    None,                                                             // 0xF4
-    Some((Instruction::Sbc, AddressingMode::ZeropageXIndexed)),       // 0xF5
-    Some((Instruction::Inc, AddressingMode::ZeropageXIndexed)),       // 0xF6
-    None,                                                             // 0xF7
-    Some((Instruction::Sed, AddressingMode::Implied)),                // 0xF8
-    Some((Instruction::Sbc, AddressingMode::AbsoluteYIndexed)),       // 0xF9
-    None,                                                             // 0xFA
-    None,                                                             // 0xFB
-    None,                                                             // 0xFC
-    Some((Instruction::Sbc, AddressingMode::AbsoluteXIndexed)),       // 0xFD
-    Some((Instruction::Inc, AddressingMode::AbsoluteXIndexed)),       // 0xFE
-    None,                                                             // 0xFF
+/// Opcode table of the NMOS 6502: instruction, addressing mode and base cycle count.
+/// JAM opcodes never complete, so they are listed with no cycles.
+pub static OPCODES : [Option<(Instruction, AddressingMode, u8)>; 256 ] = [
+    Some((Instruction::Brk, AddressingMode::Implied, 7)),                 // 0x00
+    Some((Instruction::Ora, AddressingMode::XIndexedIndirect, 6)),        // 0x01
+    Some((Instruction::Jam, AddressingMode::Implied, 0)),                 // 0x02
+    None,                                                                 // 0x03
+    None,                                                                 // 0x04
+    Some((Instruction::Ora, AddressingMode::Zeropage, 3)),                // 0x05
+    Some((Instruction::Asl, AddressingMode::Zeropage, 5)),                // 0x06
+    None,                                                                 // 0x07
+    Some((Instruction::Php, AddressingMode::Implied, 3)),                 // 0x08
+    Some((Instruction::Ora, AddressingMode::Immediate, 2)),               // 0x09
+    Some((Instruction::Asl, AddressingMode::Accumulator, 2)),             // 0x0A
+    None,                                                                 // 0x0B
+    None,                                                                 // 0x0C
+    Some((Instruction::Ora, AddressingMode::Absolute, 4)),                // 0x0D
+    Some((Instruction::Asl, AddressingMode::Absolute, 6)),                // 0x0E
+    None,                                                                 // 0x0F
+    Some((Instruction::Bpl, AddressingMode::Relative, 2)),                // 0x10
+    Some((Instruction::Ora, AddressingMode::IndirectYIndexed, 5)),        // 0x11
+    Some((Instruction::Jam, AddressingMode::Implied, 0)),                 // 0x12
+    None,                                                                 // 0x13
+    None,                                                                 // 0x14
+    Some((Instruction::Ora, AddressingMode::ZeropageXIndexed, 4)),        // 0x15
+    Some((Instruction::Asl, AddressingMode::ZeropageXIndexed, 6)),        // 0x16
+    None,                                                                 // 0x17
+    Some((Instruction::Clc, AddressingMode::Implied, 2)),                 // 0x18
+    Some((Instruction::Ora, AddressingMode::AbsoluteYIndexed, 4)),        // 0x19
+    None,                                                                 // 0x1A
+    None,                                                                 // 0x1B
+    None,                                                                 // 0x1C
+    Some((Instruction::Ora, AddressingMode::AbsoluteXIndexed, 4)),        // 0x1D
+    Some((Instruction::Asl, AddressingMode::AbsoluteXIndexed, 7)),        // 0x1E
+    None,                                                                 // 0x1F
+    Some((Instruction::Jsr, AddressingMode::Absolute, 6)),                // 0x20
+    Some((Instruction::And, AddressingMode::XIndexedIndirect, 6)),        // 0x21
+    Some((Instruction::Jam, AddressingMode::Implied, 0)),                 // 0x22
+    None,                                                                 // 0x23
+    Some((Instruction::Bit, AddressingMode::Zeropage, 3)),                // 0x24
+    Some((Instruction::And, AddressingMode::Zeropage, 3)),                // 0x25
+    Some((Instruction::Rol, AddressingMode::Zeropage, 5)),                // 0x26
+    None,                                                                 // 0x27
+    Some((Instruction::Plp, AddressingMode::Implied, 4)),                 // 0x28
+    Some((Instruction::And, AddressingMode::Immediate, 2)),               // 0x29
+    Some((Instruction::Rol, AddressingMode::Accumulator, 2)),             // 0x2A
+    None,                                                                 // 0x2B
+    Some((Instruction::Bit, AddressingMode::Absolute, 4)),                // 0x2C
+    Some((Instruction::And, AddressingMode::Absolute, 4)),                // 0x2D
+    Some((Instruction::Rol, AddressingMode::Absolute, 6)),                // 0x2E
+    None,                                                                 // 0x2F
+    Some((Instruction::Bmi, AddressingMode::Relative, 2)),                // 0x30
+    Some((Instruction::And, AddressingMode::IndirectYIndexed, 5)),        // 0x31
+    Some((Instruction::Jam, AddressingMode::Implied, 0)),                 // 0x32
+    None,                                                                 // 0x33
+    None,                                                                 // 0x34
+    Some((Instruction::And, AddressingMode::ZeropageXIndexed, 4)),        // 0x35
+    Some((Instruction::Rol, AddressingMode::ZeropageXIndexed, 6)),        // 0x36
+    None,                                                                 // 0x37
+    Some((Instruction::Sec, AddressingMode::Implied, 2)),                 // 0x38
+    Some((Instruction::And, AddressingMode::AbsoluteYIndexed, 4)),        // 0x39
+    None,                                                                 // 0x3A
+    None,                                                                 // 0x3B
+    None,                                                                 // 0x3C
+    Some((Instruction::And, AddressingMode::AbsoluteXIndexed, 4)),        // 0x3D
+    Some((Instruction::Rol, AddressingMode::AbsoluteXIndexed, 7)),        // 0x3E
+    None,                                                                 // 0x3F
+    Some((Instruction::Rti, AddressingMode::Implied, 6)),                 // 0x40
+    Some((Instruction::Eor, AddressingMode::XIndexedIndirect, 6)),        // 0x41
+    Some((Instruction::Jam, AddressingMode::Implied, 0)),                 // 0x42
+    None,                                                                 // 0x43
+    None,                                                                 // 0x44
+    Some((Instruction::Eor, AddressingMode::Zeropage, 3)),                // 0x45
+    Some((Instruction::Lsr, AddressingMode::Zeropage, 5)),                // 0x46
+    None,                                                                 // 0x47
+    Some((Instruction::Pha, AddressingMode::Implied, 3)),                 // 0x48
+    Some((Instruction::Eor, AddressingMode::Immediate, 2)),               // 0x49
+    Some((Instruction::Lsr, AddressingMode::Accumulator, 2)),             // 0x4A
+    None,                                                                 // 0x4B
+    Some((Instruction::Jmp, AddressingMode::Absolute, 3)),                // 0x4C
+    Some((Instruction::Eor, AddressingMode::Absolute, 4)),                // 0x4D
+    Some((Instruction::Lsr, AddressingMode::Absolute, 6)),                // 0x4E
+    None,                                                                 // 0x4F
+    Some((Instruction::Bvc, AddressingMode::Relative, 2)),                // 0x50
+    Some((Instruction::Eor, AddressingMode::IndirectYIndexed, 5)),        // 0x51
+    Some((Instruction::Jam, AddressingMode::Implied, 0)),                 // 0x52
+    None,                                                                 // 0x53
+    None,                                                                 // 0x54
+    Some((Instruction::Eor, AddressingMode::ZeropageXIndexed, 4)),        // 0x55
+    Some((Instruction::Lsr, AddressingMode::ZeropageXIndexed, 6)),        // 0x56
+    None,                                                                 // 0x57
+    Some((Instruction::Cli, AddressingMode::Implied, 2)),                 // 0x58
+    Some((Instruction::Eor, AddressingMode::AbsoluteYIndexed, 4)),        // 0x59
+    None,                                                                 // 0x5A
+    None,                                                                 // 0x5B
+    None,                                                                 // 0x5C
+    Some((Instruction::Eor, AddressingMode::AbsoluteXIndexed, 4)),        // 0x5D
+    Some((Instruction::Lsr, AddressingMode::AbsoluteXIndexed, 7)),        // 0x5E
+    None,                                                                 // 0x5F
+    Some((Instruction::Rts, AddressingMode::Implied, 6)),                 // 0x60
+    Some((Instruction::Adc, AddressingMode::XIndexedIndirect, 6)),        // 0x61
+    Some((Instruction::Jam, AddressingMode::Implied, 0)),                 // 0x62
+    None,                                                                 // 0x63
+    None,                                                                 // 0x64
+    Some((Instruction::Adc, AddressingMode::Zeropage, 3)),                // 0x65
+    Some((Instruction::Ror, AddressingMode::Zeropage, 5)),                // 0x66
+    None,                                                                 // 0x67
+    Some((Instruction::Pla, AddressingMode::Implied, 4)),                 // 0x68
+    Some((Instruction::Adc, AddressingMode::Immediate, 2)),               // 0x69
+    Some((Instruction::Ror, AddressingMode::Accumulator, 2)),             // 0x6A
+    None,                                                                 // 0x6B
+    Some((Instruction::Jmp, AddressingMode::Indirect, 5)),                // 0x6C
+    Some((Instruction::Adc, AddressingMode::Absolute, 4)),                // 0x6D
+    Some((Instruction::Ror, AddressingMode::Absolute, 6)),                // 0x6E
+    None,                                                                 // 0x6F
+    Some((Instruction::Bvs, AddressingMode::Relative, 2)),                // 0x70
+    Some((Instruction::Adc, AddressingMode::IndirectYIndexed, 5)),        // 0x71
+    Some((Instruction::Jam, AddressingMode::Implied, 0)),                 // 0x72
+    None,                                                                 // 0x73
+    None,                                                                 // 0x74
+    Some((Instruction::Adc, AddressingMode::ZeropageXIndexed, 4)),        // 0x75
+    Some((Instruction::Ror, AddressingMode::ZeropageXIndexed, 6)),        // 0x76
+    None,                                                                 // 0x77
+    Some((Instruction::Sei, AddressingMode::Implied, 2)),                 // 0x78
+    Some((Instruction::Adc, AddressingMode::AbsoluteYIndexed, 4)),        // 0x79
+    None,                                                                 // 0x7A
+    None,                                                                 // 0x7B
+    None,                                                                 // 0x7C
+    Some((Instruction::Adc, AddressingMode::AbsoluteXIndexed, 4)),        // 0x7D
+    Some((Instruction::Ror, AddressingMode::AbsoluteXIndexed, 7)),        // 0x7E
+    None,                                                                 // 0x7F
+    None,                                                                 // 0x80
+    Some((Instruction::Sta, AddressingMode::XIndexedIndirect, 6)),        // 0x81
+    None,                                                                 // 0x82
+    None,                                                                 // 0x83
+    Some((Instruction::Sty, AddressingMode::Zeropage, 3)),                // 0x84
+    Some((Instruction::Sta, AddressingMode::Zeropage, 3)),                // 0x85
+    Some((Instruction::Stx, AddressingMode::Zeropage, 3)),                // 0x86
+    None,                                                                 // 0x87
+    Some((Instruction::Dey, AddressingMode::Implied, 2)),                 // 0x88
+    None,                                                                 // 0x89
+    Some((Instruction::Txa, AddressingMode::Implied, 2)),                 // 0x8A
+    None,                                                                 // 0x8B
+    Some((Instruction::Sty, AddressingMode::Absolute, 4)),                // 0x8C
+    Some((Instruction::Sta, AddressingMode::Absolute, 4)),                // 0x8D
+    Some((Instruction::Stx, AddressingMode::Absolute, 4)),                // 0x8E
+    None,                                                                 // 0x8F
+    Some((Instruction::Bcc, AddressingMode::Relative, 2)),                // 0x90
+    Some((Instruction::Sta, AddressingMode::IndirectYIndexed, 6)),        // 0x91
+    Some((Instruction::Jam, AddressingMode::Implied, 0)),                 // 0x92
+    None,                                                                 // 0x93
+    Some((Instruction::Sty, AddressingMode::ZeropageXIndexed, 4)),        // 0x94
+    Some((Instruction::Sta, AddressingMode::ZeropageXIndexed, 4)),        // 0x95
+    Some((Instruction::Stx, AddressingMode::ZeropageYIndexed, 4)),        // 0x96
+    None,                                                                 // 0x97
+    Some((Instruction::Tya, AddressingMode::Implied, 2)),                 // 0x98
+    Some((Instruction::Sta, AddressingMode::AbsoluteYIndexed, 5)),        // 0x99
+    Some((Instruction::Txs, AddressingMode::Implied, 2)),                 // 0x9A
+    None,                                                                 // 0x9B
+    None,                                                                 // 0x9C
+    Some((Instruction::Sta, AddressingMode::AbsoluteXIndexed, 5)),        // 0x9D
+    None,                                                                 // 0x9E
+    None,                                                                 // 0x9F
+    Some((Instruction::Ldy, AddressingMode::Immediate, 2)),               // 0xA0
+    Some((Instruction::Lda, AddressingMode::XIndexedIndirect, 6)),        // 0xA1
+    Some((Instruction::Ldx, AddressingMode::Immediate, 2)),               // 0xA2
+    None,                                                                 // 0xA3
+    Some((Instruction::Ldy, AddressingMode::Zeropage, 3)),                // 0xA4
+    Some((Instruction::Lda, AddressingMode::Zeropage, 3)),                // 0xA5
+    Some((Instruction::Ldx, AddressingMode::Zeropage, 3)),                // 0xA6
+    None,                                                                 // 0xA7
+    Some((Instruction::Tay, AddressingMode::Implied, 2)),                 // 0xA8
+    Some((Instruction::Lda, AddressingMode::Immediate, 2)),               // 0xA9
+    Some((Instruction::Tax, AddressingMode::Implied, 2)),                 // 0xAA
+    None,                                                                 // 0xAB
+    Some((Instruction::Ldy, AddressingMode::Absolute, 4)),                // 0xAC
+    Some((Instruction::Lda, AddressingMode::Absolute, 4)),                // 0xAD
+    Some((Instruction::Ldx, AddressingMode::Absolute, 4)),                // 0xAE
+    None,                                                                 // 0xAF
+    Some((Instruction::Bcs, AddressingMode::Relative, 2)),                // 0xB0
+    Some((Instruction::Lda, AddressingMode::IndirectYIndexed, 5)),        // 0xB1
+    Some((Instruction::Jam, AddressingMode::Implied, 0)),                 // 0xB2
+    None,                                                                 // 0xB3
+    Some((Instruction::Ldy, AddressingMode::ZeropageXIndexed, 4)),        // 0xB4
+    Some((Instruction::Lda, AddressingMode::ZeropageXIndexed, 4)),        // 0xB5
+    Some((Instruction::Ldx, AddressingMode::ZeropageYIndexed, 4)),        // 0xB6
+    None,                                                                 // 0xB7
+    Some((Instruction::Clv, AddressingMode::Implied, 2)),                 // 0xB8
+    Some((Instruction::Lda, AddressingMode::AbsoluteYIndexed, 4)),        // 0xB9
+    Some((Instruction::Tsx, AddressingMode::Implied, 2)),                 // 0xBA
+    None,                                                                 // 0xBB
+    Some((Instruction::Ldy, AddressingMode::AbsoluteXIndexed, 4)),        // 0xBC
+    Some((Instruction::Lda, AddressingMode::AbsoluteXIndexed, 4)),        // 0xBD
+    Some((Instruction::Ldx, AddressingMode::AbsoluteYIndexed, 4)),        // 0xBE
+    None,                                                                 // 0xBF
+    Some((Instruction::Cpy, AddressingMode::Immediate, 2)),               // 0xC0
+    Some((Instruction::Cmp, AddressingMode::XIndexedIndirect, 6)),        // 0xC1
+    None,                                                                 // 0xC2
+    None,                                                                 // 0xC3
+    Some((Instruction::Cpy, AddressingMode::Zeropage, 3)),                // 0xC4
+    Some((Instruction::Cmp, AddressingMode::Zeropage, 3)),                // 0xC5
+    Some((Instruction::Dec, AddressingMode::Zeropage, 5)),                // 0xC6
+    None,                                                                 // 0xC7
+    Some((Instruction::Iny, AddressingMode::Implied, 2)),                 // 0xC8
+    Some((Instruction::Cmp, AddressingMode::Immediate, 2)),               // 0xC9
+    Some((Instruction::Dex, AddressingMode::Implied, 2)),                 // 0xCA
+    None,                                                                 // 0xCB
+    Some((Instruction::Cpy, AddressingMode::Absolute, 4)),                // 0xCC
+    Some((Instruction::Cmp, AddressingMode::Absolute, 4)),                // 0xCD
+    Some((Instruction::Dec, AddressingMode::Absolute, 6)),                // 0xCE
+    None,                                                                 // 0xCF
+    Some((Instruction::Bne, AddressingMode::Relative, 2)),                // 0xD0
+    Some((Instruction::Cmp, AddressingMode::IndirectYIndexed, 5)),        // 0xD1
+    Some((Instruction::Jam, AddressingMode::Implied, 0)),                 // 0xD2
+    None,                                                                 // 0xD3
+    None,                                                                 // 0xD4
+    Some((Instruction::Cmp, AddressingMode::ZeropageXIndexed, 4)),        // 0xD5
+    Some((Instruction::Dec, AddressingMode::ZeropageXIndexed, 6)),        // 0xD6
+    None,                                                                 // 0xD7
+    Some((Instruction::Cld, AddressingMode::Implied, 2)),                 // 0xD8
+    Some((Instruction::Cmp, AddressingMode::AbsoluteYIndexed, 4)),        // 0xD9
+    None,                                                                 // 0xDA
+    None,                                                                 // 0xDB
+    None,                                                                 // 0xDC
+    Some((Instruction::Cmp, AddressingMode::AbsoluteXIndexed, 4)),        // 0xDD
+    Some((Instruction::Dec, AddressingMode::AbsoluteXIndexed, 7)),        // 0xDE
+    None,                                                                 // 0xDF
+    Some((Instruction::Cpx, AddressingMode::Immediate, 2)),               // 0xE0
+    Some((Instruction::Sbc, AddressingMode::XIndexedIndirect, 6)),        // 0xE1
+    None,                                                                 // 0xE2
+    None,                                                                 // 0xE3
+    Some((Instruction::Cpx, AddressingMode::Zeropage, 3)),                // 0xE4
+    Some((Instruction::Sbc, AddressingMode::Zeropage, 3)),                // 0xE5
+    Some((Instruction::Inc, AddressingMode::Zeropage, 5)),                // 0xE6
+    None,                                                                 // 0xE7
+    Some((Instruction::Inx, AddressingMode::Implied, 2)),                 // 0xE8
+    Some((Instruction::Sbc, AddressingMode::Immediate, 2)),               // 0xE9
+    Some((Instruction::Nop, AddressingMode::Implied, 2)),                 // 0xEA
+    None,                                                                 // 0xEB
+    Some((Instruction::Cpx, AddressingMode::Absolute, 4)),                // 0xEC
+    Some((Instruction::Sbc, AddressingMode::Absolute, 4)),                // 0xED
+    Some((Instruction::Inc, AddressingMode::Absolute, 6)),                // 0xEE
+    None,                                                                 // 0xEF
+    Some((Instruction::Beq, AddressingMode::Relative, 2)),                // 0xF0
+    Some((Instruction::Sbc, AddressingMode::IndirectYIndexed, 5)),        // 0xF1
+    Some((Instruction::Jam, AddressingMode::Implied, 0)),                 // 0xF2
+    None,                                                                 // 0xF3
+    None,                                                                 // 0xF4
+    Some((Instruction::Sbc, AddressingMode::ZeropageXIndexed, 4)),        // 0xF5
+    Some((Instruction::Inc, AddressingMode::ZeropageXIndexed, 6)),        // 0xF6
+    None,                                                                 // 0xF7
+    Some((Instruction::Sed, AddressingMode::Implied, 2)),                 // 0xF8
+    Some((Instruction::Sbc, AddressingMode::AbsoluteYIndexed, 4)),        // 0xF9
+    None,                                                                 // 0xFA
+    None,                                                                 // 0xFB
+    None,                                                                 // 0xFC
+    Some((Instruction::Sbc, AddressingMode::AbsoluteXIndexed, 4)),        // 0xFD
+    Some((Instruction::Inc, AddressingMode::AbsoluteXIndexed, 7)),        // 0xFE
+    None,                                                                 // 0xFF
 ];
+
+/// Opcodes left undefined by the NMOS 6502 documentation, which execute as a
+/// side effect of how the instruction decoder is wired.
+pub static UNDOCUMENTED_OPCODES : [Option<(Instruction, AddressingMode, u8)>; 256 ] = [
+    None,                                                                 // 0x00
+    None,                                                                 // 0x01
+    None,                                                                 // 0x02
+    Some((Instruction::Slo, AddressingMode::XIndexedIndirect, 8)),        // 0x03
+    Some((Instruction::Nop, AddressingMode::Zeropage, 3)),                // 0x04
+    None,                                                                 // 0x05
+    None,                                                                 // 0x06
+    Some((Instruction::Slo, AddressingMode::Zeropage, 5)),                // 0x07
+    None,                                                                 // 0x08
+    None,                                                                 // 0x09
+    None,                                                                 // 0x0A
+    Some((Instruction::Anc, AddressingMode::Immediate, 2)),               // 0x0B
+    Some((Instruction::Nop, AddressingMode::Absolute, 4)),                // 0x0C
+    None,                                                                 // 0x0D
+    None,                                                                 // 0x0E
+    Some((Instruction::Slo, AddressingMode::Absolute, 6)),                // 0x0F
+    None,                                                                 // 0x10
+    None,                                                                 // 0x11
+    None,                                                                 // 0x12
+    Some((Instruction::Slo, AddressingMode::IndirectYIndexed, 8)),        // 0x13
+    Some((Instruction::Nop, AddressingMode::ZeropageXIndexed, 4)),        // 0x14
+    None,                                                                 // 0x15
+    None,                                                                 // 0x16
+    Some((Instruction::Slo, AddressingMode::ZeropageXIndexed, 6)),        // 0x17
+    None,                                                                 // 0x18
+    None,                                                                 // 0x19
+    Some((Instruction::Nop, AddressingMode::Implied, 2)),                 // 0x1A
+    Some((Instruction::Slo, AddressingMode::AbsoluteYIndexed, 7)),        // 0x1B
+    Some((Instruction::Nop, AddressingMode::AbsoluteXIndexed, 4)),        // 0x1C
+    None,                                                                 // 0x1D
+    None,                                                                 // 0x1E
+    Some((Instruction::Slo, AddressingMode::AbsoluteXIndexed, 7)),        // 0x1F
+    None,                                                                 // 0x20
+    None,                                                                 // 0x21
+    None,                                                                 // 0x22
+    Some((Instruction::Rla, AddressingMode::XIndexedIndirect, 8)),        // 0x23
+    None,                                                                 // 0x24
+    None,                                                                 // 0x25
+    None,                                                                 // 0x26
+    Some((Instruction::Rla, AddressingMode::Zeropage, 5)),                // 0x27
+    None,                                                                 // 0x28
+    None,                                                                 // 0x29
+    None,                                                                 // 0x2A
+    Some((Instruction::Anc, AddressingMode::Immediate, 2)),               // 0x2B
+    None,                                                                 // 0x2C
+    None,                                                                 // 0x2D
+    None,                                                                 // 0x2E
+    Some((Instruction::Rla, AddressingMode::Absolute, 6)),                // 0x2F
+    None,                                                                 // 0x30
+    None,                                                                 // 0x31
+    None,                                                                 // 0x32
+    Some((Instruction::Rla, AddressingMode::IndirectYIndexed, 8)),        // 0x33
+    Some((Instruction::Nop, AddressingMode::ZeropageXIndexed, 4)),        // 0x34
+    None,                                                                 // 0x35
+    None,                                                                 // 0x36
+    Some((Instruction::Rla, AddressingMode::ZeropageXIndexed, 6)),        // 0x37
+    None,                                                                 // 0x38
+    None,                                                                 // 0x39
+    Some((Instruction::Nop, AddressingMode::Implied, 2)),                 // 0x3A
+    Some((Instruction::Rla, AddressingMode::AbsoluteYIndexed, 7)),        // 0x3B
+    Some((Instruction::Nop, AddressingMode::AbsoluteXIndexed, 4)),        // 0x3C
+    None,                                                                 // 0x3D
+    None,                                                                 // 0x3E
+    Some((Instruction::Rla, AddressingMode::AbsoluteXIndexed, 7)),        // 0x3F
+    None,                                                                 // 0x40
+    None,                                                                 // 0x41
+    None,                                                                 // 0x42
+    Some((Instruction::Sre, AddressingMode::XIndexedIndirect, 8)),        // 0x43
+    Some((Instruction::Nop, AddressingMode::Zeropage, 3)),                // 0x44
+    None,                                                                 // 0x45
+    None,                                                                 // 0x46
+    Some((Instruction::Sre, AddressingMode::Zeropage, 5)),                // 0x47
+    None,                                                                 // 0x48
+    None,                                                                 // 0x49
+    None,                                                                 // 0x4A
+    Some((Instruction::Alr, AddressingMode::Immediate, 2)),               // 0x4B
+    None,                                                                 // 0x4C
+    None,                                                                 // 0x4D
+    None,                                                                 // 0x4E
+    Some((Instruction::Sre, AddressingMode::Absolute, 6)),                // 0x4F
+    None,                                                                 // 0x50
+    None,                                                                 // 0x51
+    None,                                                                 // 0x52
+    Some((Instruction::Sre, AddressingMode::IndirectYIndexed, 8)),        // 0x53
+    Some((Instruction::Nop, AddressingMode::ZeropageXIndexed, 4)),        // 0x54
+    None,                                                                 // 0x55
+    None,                                                                 // 0x56
+    Some((Instruction::Sre, AddressingMode::ZeropageXIndexed, 6)),        // 0x57
+    None,                                                                 // 0x58
+    None,                                                                 // 0x59
+    Some((Instruction::Nop, AddressingMode::Implied, 2)),                 // 0x5A
+    Some((Instruction::Sre, AddressingMode::AbsoluteYIndexed, 7)),        // 0x5B
+    Some((Instruction::Nop, AddressingMode::AbsoluteXIndexed, 4)),        // 0x5C
+    None,                                                                 // 0x5D
+    None,                                                                 // 0x5E
+    Some((Instruction::Sre, AddressingMode::AbsoluteXIndexed, 7)),        // 0x5F
+    None,                                                                 // 0x60
+    None,                                                                 // 0x61
+    None,                                                                 // 0x62
+    Some((Instruction::Rra, AddressingMode::XIndexedIndirect, 8)),        // 0x63
+    Some((Instruction::Nop, AddressingMode::Zeropage, 3)),                // 0x64
+    None,                                                                 // 0x65
+    None,                                                                 // 0x66
+    Some((Instruction::Rra, AddressingMode::Zeropage, 5)),                // 0x67
+    None,                                                                 // 0x68
+    None,                                                                 // 0x69
+    None,                                                                 // 0x6A
+    Some((Instruction::Arr, AddressingMode::Immediate, 2)),               // 0x6B
+    None,                                                                 // 0x6C
+    None,                                                                 // 0x6D
+    None,                                                                 // 0x6E
+    Some((Instruction::Rra, AddressingMode::Absolute, 6)),                // 0x6F
+    None,                                                                 // 0x70
+    None,                                                                 // 0x71
+    None,                                                                 // 0x72
+    Some((Instruction::Rra, AddressingMode::IndirectYIndexed, 8)),        // 0x73
+    Some((Instruction::Nop, AddressingMode::ZeropageXIndexed, 4)),        // 0x74
+    None,                                                                 // 0x75
+    None,                                                                 // 0x76
+    Some((Instruction::Rra, AddressingMode::ZeropageXIndexed, 6)),        // 0x77
+    None,                                                                 // 0x78
+    None,                                                                 // 0x79
+    Some((Instruction::Nop, AddressingMode::Implied, 2)),                 // 0x7A
+    Some((Instruction::Rra, AddressingMode::AbsoluteYIndexed, 7)),        // 0x7B
+    Some((Instruction::Nop, AddressingMode::AbsoluteXIndexed, 4)),        // 0x7C
+    None,                                                                 // 0x7D
+    None,                                                                 // 0x7E
+    Some((Instruction::Rra, AddressingMode::AbsoluteXIndexed, 7)),        // 0x7F
+    Some((Instruction::Nop, AddressingMode::Immediate, 2)),               // 0x80
+    None,                                                                 // 0x81
+    Some((Instruction::Nop, AddressingMode::Immediate, 2)),               // 0x82
+    Some((Instruction::Sax, AddressingMode::XIndexedIndirect, 6)),        // 0x83
+    None,                                                                 // 0x84
+    None,                                                                 // 0x85
+    None,                                                                 // 0x86
+    Some((Instruction::Sax, AddressingMode::Zeropage, 3)),                // 0x87
+    None,                                                                 // 0x88
+    Some((Instruction::Nop, AddressingMode::Immediate, 2)),               // 0x89
+    None,                                                                 // 0x8A
+    Some((Instruction::Ane, AddressingMode::Immediate, 2)),               // 0x8B
+    None,                                                                 // 0x8C
+    None,                                                                 // 0x8D
+    None,                                                                 // 0x8E
+    Some((Instruction::Sax, AddressingMode::Absolute, 4)),                // 0x8F
+    None,                                                                 // 0x90
+    None,                                                                 // 0x91
+    None,                                                                 // 0x92
+    Some((Instruction::Sha, AddressingMode::IndirectYIndexed, 6)),        // 0x93
+    None,                                                                 // 0x94
+    None,                                                                 // 0x95
+    None,                                                                 // 0x96
+    Some((Instruction::Sax, AddressingMode::ZeropageYIndexed, 4)),        // 0x97
+    None,                                                                 // 0x98
+    None,                                                                 // 0x99
+    None,                                                                 // 0x9A
+    Some((Instruction::Tas, AddressingMode::AbsoluteYIndexed, 5)),        // 0x9B
+    Some((Instruction::Shy, AddressingMode::AbsoluteXIndexed, 5)),        // 0x9C
+    None,                                                                 // 0x9D
+    Some((Instruction::Shx, AddressingMode::AbsoluteYIndexed, 5)),        // 0x9E
+    Some((Instruction::Sha, AddressingMode::AbsoluteYIndexed, 5)),        // 0x9F
+    None,                                                                 // 0xA0
+    None,                                                                 // 0xA1
+    None,                                                                 // 0xA2
+    Some((Instruction::Lax, AddressingMode::XIndexedIndirect, 6)),        // 0xA3
+    None,                                                                 // 0xA4
+    None,                                                                 // 0xA5
+    None,                                                                 // 0xA6
+    Some((Instruction::Lax, AddressingMode::Zeropage, 3)),                // 0xA7
+    None,                                                                 // 0xA8
+    None,                                                                 // 0xA9
+    None,                                                                 // 0xAA
+    Some((Instruction::Lxa, AddressingMode::Immediate, 2)),               // 0xAB
+    None,                                                                 // 0xAC
+    None,                                                                 // 0xAD
+    None,                                                                 // 0xAE
+    Some((Instruction::Lax, AddressingMode::Absolute, 4)),                // 0xAF
+    None,                                                                 // 0xB0
+    None,                                                                 // 0xB1
+    None,                                                                 // 0xB2
+    Some((Instruction::Lax, AddressingMode::IndirectYIndexed, 5)),        // 0xB3
+    None,                                                                 // 0xB4
+    None,                                                                 // 0xB5
+    None,                                                                 // 0xB6
+    Some((Instruction::Lax, AddressingMode::ZeropageYIndexed, 4)),        // 0xB7
+    None,                                                                 // 0xB8
+    None,                                                                 // 0xB9
+    None,                                                                 // 0xBA
+    Some((Instruction::Las, AddressingMode::AbsoluteYIndexed, 4)),        // 0xBB
+    None,                                                                 // 0xBC
+    None,                                                                 // 0xBD
+    None,                                                                 // 0xBE
+    Some((Instruction::Lax, AddressingMode::AbsoluteYIndexed, 4)),        // 0xBF
+    None,                                                                 // 0xC0
+    None,                                                                 // 0xC1
+    Some((Instruction::Nop, AddressingMode::Immediate, 2)),               // 0xC2
+    Some((Instruction::Dcp, AddressingMode::XIndexedIndirect, 8)),        // 0xC3
+    None,                                                                 // 0xC4
+    None,                                                                 // 0xC5
+    None,                                                                 // 0xC6
+    Some((Instruction::Dcp, AddressingMode::Zeropage, 5)),                // 0xC7
+    None,                                                                 // 0xC8
+    None,                                                                 // 0xC9
+    None,                                                                 // 0xCA
+    Some((Instruction::Sbx, AddressingMode::Immediate, 2)),               // 0xCB
+    None,                                                                 // 0xCC
+    None,                                                                 // 0xCD
+    None,                                                                 // 0xCE
+    Some((Instruction::Dcp, AddressingMode::Absolute, 6)),                // 0xCF
+    None,                                                                 // 0xD0
+    None,                                                                 // 0xD1
+    None,                                                                 // 0xD2
+    Some((Instruction::Dcp, AddressingMode::IndirectYIndexed, 8)),        // 0xD3
+    Some((Instruction::Nop, AddressingMode::ZeropageXIndexed, 4)),        // 0xD4
+    None,                                                                 // 0xD5
+    None,                                                                 // 0xD6
+    Some((Instruction::Dcp, AddressingMode::ZeropageXIndexed, 6)),        // 0xD7
+    None,                                                                 // 0xD8
+    None,                                                                 // 0xD9
+    Some((Instruction::Nop, AddressingMode::Implied, 2)),                 // 0xDA
+    Some((Instruction::Dcp, AddressingMode::AbsoluteYIndexed, 7)),        // 0xDB
+    Some((Instruction::Nop, AddressingMode::AbsoluteXIndexed, 4)),        // 0xDC
+    None,                                                                 // 0xDD
+    None,                                                                 // 0xDE
+    Some((Instruction::Dcp, AddressingMode::AbsoluteXIndexed, 7)),        // 0xDF
+    None,                                                                 // 0xE0
+    None,                                                                 // 0xE1
+    Some((Instruction::Nop, AddressingMode::Immediate, 2)),               // 0xE2
+    Some((Instruction::Isc, AddressingMode::XIndexedIndirect, 8)),        // 0xE3
+    None,                                                                 // 0xE4
+    None,                                                                 // 0xE5
+    None,                                                                 // 0xE6
+    Some((Instruction::Isc, AddressingMode::Zeropage, 5)),                // 0xE7
+    None,                                                                 // 0xE8
+    None,                                                                 // 0xE9
+    None,                                                                 // 0xEA
+    Some((Instruction::Sbc, AddressingMode::Immediate, 2)),               // 0xEB
+    None,                                                                 // 0xEC
+    None,                                                                 // 0xED
+    None,                                                                 // 0xEE
+    Some((Instruction::Isc, AddressingMode::Absolute, 6)),                // 0xEF
+    None,                                                                 // 0xF0
+    None,                                                                 // 0xF1
+    None,                                                                 // 0xF2
+    Some((Instruction::Isc, AddressingMode::IndirectYIndexed, 8)),        // 0xF3
+    Some((Instruction::Nop, AddressingMode::ZeropageXIndexed, 4)),        // 0xF4
+    None,                                                                 // 0xF5
+    None,                                                                 // 0xF6
+    Some((Instruction::Isc, AddressingMode::ZeropageXIndexed, 6)),        // 0xF7
+    None,                                                                 // 0xF8
+    None,                                                                 // 0xF9
+    Some((Instruction::Nop, AddressingMode::Implied, 2)),                 // 0xFA
+    Some((Instruction::Isc, AddressingMode::AbsoluteYIndexed, 7)),        // 0xFB
+    Some((Instruction::Nop, AddressingMode::AbsoluteXIndexed, 4)),        // 0xFC
+    None,                                                                 // 0xFD
+    None,                                                                 // 0xFE
+    Some((Instruction::Isc, AddressingMode::AbsoluteXIndexed, 7)),        // 0xFF
+];
+
+/// Opcode table of the WDC 65C02, including the Rockwell bit instructions.
+/// Every opcode is defined, the reserved ones are NOPs of different lengths.
+pub static CMOS_OPCODES : [Option<(Instruction, AddressingMode, u8)>; 256 ] = [
+    Some((Instruction::Brk, AddressingMode::Implied, 7)),                     // 0x00
+    Some((Instruction::Ora, AddressingMode::XIndexedIndirect, 6)),            // 0x01
+    Some((Instruction::Nop, AddressingMode::Immediate, 2)),                   // 0x02
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x03
+    Some((Instruction::Tsb, AddressingMode::Zeropage, 5)),                    // 0x04
+    Some((Instruction::Ora, AddressingMode::Zeropage, 3)),                    // 0x05
+    Some((Instruction::Asl, AddressingMode::Zeropage, 5)),                    // 0x06
+    Some((Instruction::Rmb(0), AddressingMode::Zeropage, 5)),                 // 0x07
+    Some((Instruction::Php, AddressingMode::Implied, 3)),                     // 0x08
+    Some((Instruction::Ora, AddressingMode::Immediate, 2)),                   // 0x09
+    Some((Instruction::Asl, AddressingMode::Accumulator, 2)),                 // 0x0A
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x0B
+    Some((Instruction::Tsb, AddressingMode::Absolute, 6)),                    // 0x0C
+    Some((Instruction::Ora, AddressingMode::Absolute, 4)),                    // 0x0D
+    Some((Instruction::Asl, AddressingMode::Absolute, 6)),                    // 0x0E
+    Some((Instruction::Bbr(0), AddressingMode::ZeropageRelative, 5)),         // 0x0F
+    Some((Instruction::Bpl, AddressingMode::Relative, 2)),                    // 0x10
+    Some((Instruction::Ora, AddressingMode::IndirectYIndexed, 5)),            // 0x11
+    Some((Instruction::Ora, AddressingMode::ZeropageIndirect, 5)),            // 0x12
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x13
+    Some((Instruction::Trb, AddressingMode::Zeropage, 5)),                    // 0x14
+    Some((Instruction::Ora, AddressingMode::ZeropageXIndexed, 4)),            // 0x15
+    Some((Instruction::Asl, AddressingMode::ZeropageXIndexed, 6)),            // 0x16
+    Some((Instruction::Rmb(1), AddressingMode::Zeropage, 5)),                 // 0x17
+    Some((Instruction::Clc, AddressingMode::Implied, 2)),                     // 0x18
+    Some((Instruction::Ora, AddressingMode::AbsoluteYIndexed, 4)),            // 0x19
+    Some((Instruction::Inc, AddressingMode::Accumulator, 2)),                 // 0x1A
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x1B
+    Some((Instruction::Trb, AddressingMode::Absolute, 6)),                    // 0x1C
+    Some((Instruction::Ora, AddressingMode::AbsoluteXIndexed, 4)),            // 0x1D
+    Some((Instruction::Asl, AddressingMode::AbsoluteXIndexed, 6)),            // 0x1E
+    Some((Instruction::Bbr(1), AddressingMode::ZeropageRelative, 5)),         // 0x1F
+    Some((Instruction::Jsr, AddressingMode::Absolute, 6)),                    // 0x20
+    Some((Instruction::And, AddressingMode::XIndexedIndirect, 6)),            // 0x21
+    Some((Instruction::Nop, AddressingMode::Immediate, 2)),                   // 0x22
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x23
+    Some((Instruction::Bit, AddressingMode::Zeropage, 3)),                    // 0x24
+    Some((Instruction::And, AddressingMode::Zeropage, 3)),                    // 0x25
+    Some((Instruction::Rol, AddressingMode::Zeropage, 5)),                    // 0x26
+    Some((Instruction::Rmb(2), AddressingMode::Zeropage, 5)),                 // 0x27
+    Some((Instruction::Plp, AddressingMode::Implied, 4)),                     // 0x28
+    Some((Instruction::And, AddressingMode::Immediate, 2)),                   // 0x29
+    Some((Instruction::Rol, AddressingMode::Accumulator, 2)),                 // 0x2A
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x2B
+    Some((Instruction::Bit, AddressingMode::Absolute, 4)),                    // 0x2C
+    Some((Instruction::And, AddressingMode::Absolute, 4)),                    // 0x2D
+    Some((Instruction::Rol, AddressingMode::Absolute, 6)),                    // 0x2E
+    Some((Instruction::Bbr(2), AddressingMode::ZeropageRelative, 5)),         // 0x2F
+    Some((Instruction::Bmi, AddressingMode::Relative, 2)),                    // 0x30
+    Some((Instruction::And, AddressingMode::IndirectYIndexed, 5)),            // 0x31
+    Some((Instruction::And, AddressingMode::ZeropageIndirect, 5)),            // 0x32
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x33
+    Some((Instruction::Bit, AddressingMode::ZeropageXIndexed, 4)),            // 0x34
+    Some((Instruction::And, AddressingMode::ZeropageXIndexed, 4)),            // 0x35
+    Some((Instruction::Rol, AddressingMode::ZeropageXIndexed, 6)),            // 0x36
+    Some((Instruction::Rmb(3), AddressingMode::Zeropage, 5)),                 // 0x37
+    Some((Instruction::Sec, AddressingMode::Implied, 2)),                     // 0x38
+    Some((Instruction::And, AddressingMode::AbsoluteYIndexed, 4)),            // 0x39
+    Some((Instruction::Dec, AddressingMode::Accumulator, 2)),                 // 0x3A
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x3B
+    Some((Instruction::Bit, AddressingMode::AbsoluteXIndexed, 4)),            // 0x3C
+    Some((Instruction::And, AddressingMode::AbsoluteXIndexed, 4)),            // 0x3D
+    Some((Instruction::Rol, AddressingMode::AbsoluteXIndexed, 6)),            // 0x3E
+    Some((Instruction::Bbr(3), AddressingMode::ZeropageRelative, 5)),         // 0x3F
+    Some((Instruction::Rti, AddressingMode::Implied, 6)),                     // 0x40
+    Some((Instruction::Eor, AddressingMode::XIndexedIndirect, 6)),            // 0x41
+    Some((Instruction::Nop, AddressingMode::Immediate, 2)),                   // 0x42
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x43
+    Some((Instruction::Nop, AddressingMode::Zeropage, 3)),                    // 0x44
+    Some((Instruction::Eor, AddressingMode::Zeropage, 3)),                    // 0x45
+    Some((Instruction::Lsr, AddressingMode::Zeropage, 5)),                    // 0x46
+    Some((Instruction::Rmb(4), AddressingMode::Zeropage, 5)),                 // 0x47
+    Some((Instruction::Pha, AddressingMode::Implied, 3)),                     // 0x48
+    Some((Instruction::Eor, AddressingMode::Immediate, 2)),                   // 0x49
+    Some((Instruction::Lsr, AddressingMode::Accumulator, 2)),                 // 0x4A
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x4B
+    Some((Instruction::Jmp, AddressingMode::Absolute, 3)),                    // 0x4C
+    Some((Instruction::Eor, AddressingMode::Absolute, 4)),                    // 0x4D
+    Some((Instruction::Lsr, AddressingMode::Absolute, 6)),                    // 0x4E
+    Some((Instruction::Bbr(4), AddressingMode::ZeropageRelative, 5)),         // 0x4F
+    Some((Instruction::Bvc, AddressingMode::Relative, 2)),                    // 0x50
+    Some((Instruction::Eor, AddressingMode::IndirectYIndexed, 5)),            // 0x51
+    Some((Instruction::Eor, AddressingMode::ZeropageIndirect, 5)),            // 0x52
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x53
+    Some((Instruction::Nop, AddressingMode::ZeropageXIndexed, 4)),            // 0x54
+    Some((Instruction::Eor, AddressingMode::ZeropageXIndexed, 4)),            // 0x55
+    Some((Instruction::Lsr, AddressingMode::ZeropageXIndexed, 6)),            // 0x56
+    Some((Instruction::Rmb(5), AddressingMode::Zeropage, 5)),                 // 0x57
+    Some((Instruction::Cli, AddressingMode::Implied, 2)),                     // 0x58
+    Some((Instruction::Eor, AddressingMode::AbsoluteYIndexed, 4)),            // 0x59
+    Some((Instruction::Phy, AddressingMode::Implied, 3)),                     // 0x5A
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x5B
+    Some((Instruction::Nop, AddressingMode::Absolute, 8)),                    // 0x5C
+    Some((Instruction::Eor, AddressingMode::AbsoluteXIndexed, 4)),            // 0x5D
+    Some((Instruction::Lsr, AddressingMode::AbsoluteXIndexed, 6)),            // 0x5E
+    Some((Instruction::Bbr(5), AddressingMode::ZeropageRelative, 5)),         // 0x5F
+    Some((Instruction::Rts, AddressingMode::Implied, 6)),                     // 0x60
+    Some((Instruction::Adc, AddressingMode::XIndexedIndirect, 6)),            // 0x61
+    Some((Instruction::Nop, AddressingMode::Immediate, 2)),                   // 0x62
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x63
+    Some((Instruction::Stz, AddressingMode::Zeropage, 3)),                    // 0x64
+    Some((Instruction::Adc, AddressingMode::Zeropage, 3)),                    // 0x65
+    Some((Instruction::Ror, AddressingMode::Zeropage, 5)),                    // 0x66
+    Some((Instruction::Rmb(6), AddressingMode::Zeropage, 5)),                 // 0x67
+    Some((Instruction::Pla, AddressingMode::Implied, 4)),                     // 0x68
+    Some((Instruction::Adc, AddressingMode::Immediate, 2)),                   // 0x69
+    Some((Instruction::Ror, AddressingMode::Accumulator, 2)),                 // 0x6A
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x6B
+    Some((Instruction::Jmp, AddressingMode::Indirect, 6)),                    // 0x6C
+    Some((Instruction::Adc, AddressingMode::Absolute, 4)),                    // 0x6D
+    Some((Instruction::Ror, AddressingMode::Absolute, 6)),                    // 0x6E
+    Some((Instruction::Bbr(6), AddressingMode::ZeropageRelative, 5)),         // 0x6F
+    Some((Instruction::Bvs, AddressingMode::Relative, 2)),                    // 0x70
+    Some((Instruction::Adc, AddressingMode::IndirectYIndexed, 5)),            // 0x71
+    Some((Instruction::Adc, AddressingMode::ZeropageIndirect, 5)),            // 0x72
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x73
+    Some((Instruction::Stz, AddressingMode::ZeropageXIndexed, 4)),            // 0x74
+    Some((Instruction::Adc, AddressingMode::ZeropageXIndexed, 4)),            // 0x75
+    Some((Instruction::Ror, AddressingMode::ZeropageXIndexed, 6)),            // 0x76
+    Some((Instruction::Rmb(7), AddressingMode::Zeropage, 5)),                 // 0x77
+    Some((Instruction::Sei, AddressingMode::Implied, 2)),                     // 0x78
+    Some((Instruction::Adc, AddressingMode::AbsoluteYIndexed, 4)),            // 0x79
+    Some((Instruction::Ply, AddressingMode::Implied, 4)),                     // 0x7A
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x7B
+    Some((Instruction::Jmp, AddressingMode::AbsoluteXIndexedIndirect, 6)),    // 0x7C
+    Some((Instruction::Adc, AddressingMode::AbsoluteXIndexed, 4)),            // 0x7D
+    Some((Instruction::Ror, AddressingMode::AbsoluteXIndexed, 6)),            // 0x7E
+    Some((Instruction::Bbr(7), AddressingMode::ZeropageRelative, 5)),         // 0x7F
+    Some((Instruction::Bra, AddressingMode::Relative, 2)),                    // 0x80
+    Some((Instruction::Sta, AddressingMode::XIndexedIndirect, 6)),            // 0x81
+    Some((Instruction::Nop, AddressingMode::Immediate, 2)),                   // 0x82
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x83
+    Some((Instruction::Sty, AddressingMode::Zeropage, 3)),                    // 0x84
+    Some((Instruction::Sta, AddressingMode::Zeropage, 3)),                    // 0x85
+    Some((Instruction::Stx, AddressingMode::Zeropage, 3)),                    // 0x86
+    Some((Instruction::Smb(0), AddressingMode::Zeropage, 5)),                 // 0x87
+    Some((Instruction::Dey, AddressingMode::Implied, 2)),                     // 0x88
+    Some((Instruction::Bit, AddressingMode::Immediate, 2)),                   // 0x89
+    Some((Instruction::Txa, AddressingMode::Implied, 2)),                     // 0x8A
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x8B
+    Some((Instruction::Sty, AddressingMode::Absolute, 4)),                    // 0x8C
+    Some((Instruction::Sta, AddressingMode::Absolute, 4)),                    // 0x8D
+    Some((Instruction::Stx, AddressingMode::Absolute, 4)),                    // 0x8E
+    Some((Instruction::Bbs(0), AddressingMode::ZeropageRelative, 5)),         // 0x8F
+    Some((Instruction::Bcc, AddressingMode::Relative, 2)),                    // 0x90
+    Some((Instruction::Sta, AddressingMode::IndirectYIndexed, 6)),            // 0x91
+    Some((Instruction::Sta, AddressingMode::ZeropageIndirect, 5)),            // 0x92
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x93
+    Some((Instruction::Sty, AddressingMode::ZeropageXIndexed, 4)),            // 0x94
+    Some((Instruction::Sta, AddressingMode::ZeropageXIndexed, 4)),            // 0x95
+    Some((Instruction::Stx, AddressingMode::ZeropageYIndexed, 4)),            // 0x96
+    Some((Instruction::Smb(1), AddressingMode::Zeropage, 5)),                 // 0x97
+    Some((Instruction::Tya, AddressingMode::Implied, 2)),                     // 0x98
+    Some((Instruction::Sta, AddressingMode::AbsoluteYIndexed, 5)),            // 0x99
+    Some((Instruction::Txs, AddressingMode::Implied, 2)),                     // 0x9A
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0x9B
+    Some((Instruction::Stz, AddressingMode::Absolute, 4)),                    // 0x9C
+    Some((Instruction::Sta, AddressingMode::AbsoluteXIndexed, 5)),            // 0x9D
+    Some((Instruction::Stz, AddressingMode::AbsoluteXIndexed, 5)),            // 0x9E
+    Some((Instruction::Bbs(1), AddressingMode::ZeropageRelative, 5)),         // 0x9F
+    Some((Instruction::Ldy, AddressingMode::Immediate, 2)),                   // 0xA0
+    Some((Instruction::Lda, AddressingMode::XIndexedIndirect, 6)),            // 0xA1
+    Some((Instruction::Ldx, AddressingMode::Immediate, 2)),                   // 0xA2
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0xA3
+    Some((Instruction::Ldy, AddressingMode::Zeropage, 3)),                    // 0xA4
+    Some((Instruction::Lda, AddressingMode::Zeropage, 3)),                    // 0xA5
+    Some((Instruction::Ldx, AddressingMode::Zeropage, 3)),                    // 0xA6
+    Some((Instruction::Smb(2), AddressingMode::Zeropage, 5)),                 // 0xA7
+    Some((Instruction::Tay, AddressingMode::Implied, 2)),                     // 0xA8
+    Some((Instruction::Lda, AddressingMode::Immediate, 2)),                   // 0xA9
+    Some((Instruction::Tax, AddressingMode::Implied, 2)),                     // 0xAA
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0xAB
+    Some((Instruction::Ldy, AddressingMode::Absolute, 4)),                    // 0xAC
+    Some((Instruction::Lda, AddressingMode::Absolute, 4)),                    // 0xAD
+    Some((Instruction::Ldx, AddressingMode::Absolute, 4)),                    // 0xAE
+    Some((Instruction::Bbs(2), AddressingMode::ZeropageRelative, 5)),         // 0xAF
+    Some((Instruction::Bcs, AddressingMode::Relative, 2)),                    // 0xB0
+    Some((Instruction::Lda, AddressingMode::IndirectYIndexed, 5)),            // 0xB1
+    Some((Instruction::Lda, AddressingMode::ZeropageIndirect, 5)),            // 0xB2
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0xB3
+    Some((Instruction::Ldy, AddressingMode::ZeropageXIndexed, 4)),            // 0xB4
+    Some((Instruction::Lda, AddressingMode::ZeropageXIndexed, 4)),            // 0xB5
+    Some((Instruction::Ldx, AddressingMode::ZeropageYIndexed, 4)),            // 0xB6
+    Some((Instruction::Smb(3), AddressingMode::Zeropage, 5)),                 // 0xB7
+    Some((Instruction::Clv, AddressingMode::Implied, 2)),                     // 0xB8
+    Some((Instruction::Lda, AddressingMode::AbsoluteYIndexed, 4)),            // 0xB9
+    Some((Instruction::Tsx, AddressingMode::Implied, 2)),                     // 0xBA
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0xBB
+    Some((Instruction::Ldy, AddressingMode::AbsoluteXIndexed, 4)),            // 0xBC
+    Some((Instruction::Lda, AddressingMode::AbsoluteXIndexed, 4)),            // 0xBD
+    Some((Instruction::Ldx, AddressingMode::AbsoluteYIndexed, 4)),            // 0xBE
+    Some((Instruction::Bbs(3), AddressingMode::ZeropageRelative, 5)),         // 0xBF
+    Some((Instruction::Cpy, AddressingMode::Immediate, 2)),                   // 0xC0
+    Some((Instruction::Cmp, AddressingMode::XIndexedIndirect, 6)),            // 0xC1
+    Some((Instruction::Nop, AddressingMode::Immediate, 2)),                   // 0xC2
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0xC3
+    Some((Instruction::Cpy, AddressingMode::Zeropage, 3)),                    // 0xC4
+    Some((Instruction::Cmp, AddressingMode::Zeropage, 3)),                    // 0xC5
+    Some((Instruction::Dec, AddressingMode::Zeropage, 5)),                    // 0xC6
+    Some((Instruction::Smb(4), AddressingMode::Zeropage, 5)),                 // 0xC7
+    Some((Instruction::Iny, AddressingMode::Implied, 2)),                     // 0xC8
+    Some((Instruction::Cmp, AddressingMode::Immediate, 2)),                   // 0xC9
+    Some((Instruction::Dex, AddressingMode::Implied, 2)),                     // 0xCA
+    Some((Instruction::Wai, AddressingMode::Implied, 3)),                     // 0xCB
+    Some((Instruction::Cpy, AddressingMode::Absolute, 4)),                    // 0xCC
+    Some((Instruction::Cmp, AddressingMode::Absolute, 4)),                    // 0xCD
+    Some((Instruction::Dec, AddressingMode::Absolute, 6)),                    // 0xCE
+    Some((Instruction::Bbs(4), AddressingMode::ZeropageRelative, 5)),         // 0xCF
+    Some((Instruction::Bne, AddressingMode::Relative, 2)),                    // 0xD0
+    Some((Instruction::Cmp, AddressingMode::IndirectYIndexed, 5)),            // 0xD1
+    Some((Instruction::Cmp, AddressingMode::ZeropageIndirect, 5)),            // 0xD2
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0xD3
+    Some((Instruction::Nop, AddressingMode::ZeropageXIndexed, 4)),            // 0xD4
+    Some((Instruction::Cmp, AddressingMode::ZeropageXIndexed, 4)),            // 0xD5
+    Some((Instruction::Dec, AddressingMode::ZeropageXIndexed, 6)),            // 0xD6
+    Some((Instruction::Smb(5), AddressingMode::Zeropage, 5)),                 // 0xD7
+    Some((Instruction::Cld, AddressingMode::Implied, 2)),                     // 0xD8
+    Some((Instruction::Cmp, AddressingMode::AbsoluteYIndexed, 4)),            // 0xD9
+    Some((Instruction::Phx, AddressingMode::Implied, 3)),                     // 0xDA
+    Some((Instruction::Stp, AddressingMode::Implied, 3)),                     // 0xDB
+    Some((Instruction::Nop, AddressingMode::Absolute, 4)),                    // 0xDC
+    Some((Instruction::Cmp, AddressingMode::AbsoluteXIndexed, 4)),            // 0xDD
+    Some((Instruction::Dec, AddressingMode::AbsoluteXIndexed, 7)),            // 0xDE
+    Some((Instruction::Bbs(5), AddressingMode::ZeropageRelative, 5)),         // 0xDF
+    Some((Instruction::Cpx, AddressingMode::Immediate, 2)),                   // 0xE0
+    Some((Instruction::Sbc, AddressingMode::XIndexedIndirect, 6)),            // 0xE1
+    Some((Instruction::Nop, AddressingMode::Immediate, 2)),                   // 0xE2
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0xE3
+    Some((Instruction::Cpx, AddressingMode::Zeropage, 3)),                    // 0xE4
+    Some((Instruction::Sbc, AddressingMode::Zeropage, 3)),                    // 0xE5
+    Some((Instruction::Inc, AddressingMode::Zeropage, 5)),                    // 0xE6
+    Some((Instruction::Smb(6), AddressingMode::Zeropage, 5)),                 // 0xE7
+    Some((Instruction::Inx, AddressingMode::Implied, 2)),                     // 0xE8
+    Some((Instruction::Sbc, AddressingMode::Immediate, 2)),                   // 0xE9
+    Some((Instruction::Nop, AddressingMode::Implied, 2)),                     // 0xEA
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0xEB
+    Some((Instruction::Cpx, AddressingMode::Absolute, 4)),                    // 0xEC
+    Some((Instruction::Sbc, AddressingMode::Absolute, 4)),                    // 0xED
+    Some((Instruction::Inc, AddressingMode::Absolute, 6)),                    // 0xEE
+    Some((Instruction::Bbs(6), AddressingMode::ZeropageRelative, 5)),         // 0xEF
+    Some((Instruction::Beq, AddressingMode::Relative, 2)),                    // 0xF0
+    Some((Instruction::Sbc, AddressingMode::IndirectYIndexed, 5)),            // 0xF1
+    Some((Instruction::Sbc, AddressingMode::ZeropageIndirect, 5)),            // 0xF2
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0xF3
+    Some((Instruction::Nop, AddressingMode::ZeropageXIndexed, 4)),            // 0xF4
+    Some((Instruction::Sbc, AddressingMode::ZeropageXIndexed, 4)),            // 0xF5
+    Some((Instruction::Inc, AddressingMode::ZeropageXIndexed, 6)),            // 0xF6
+    Some((Instruction::Smb(7), AddressingMode::Zeropage, 5)),                 // 0xF7
+    Some((Instruction::Sed, AddressingMode::Implied, 2)),                     // 0xF8
+    Some((Instruction::Sbc, AddressingMode::AbsoluteYIndexed, 4)),            // 0xF9
+    Some((Instruction::Plx, AddressingMode::Implied, 4)),                     // 0xFA
+    Some((Instruction::Nop, AddressingMode::Implied, 1)),                     // 0xFB
+    Some((Instruction::Nop, AddressingMode::Absolute, 4)),                    // 0xFC
+    Some((Instruction::Sbc, AddressingMode::AbsoluteXIndexed, 4)),            // 0xFD
+    Some((Instruction::Inc, AddressingMode::AbsoluteXIndexed, 7)),            // 0xFE
+    Some((Instruction::Bbs(7), AddressingMode::ZeropageRelative, 5)),         // 0xFF
+];
+
+///
+/// Looks up an opcode in the tables implemented by the given variant.
+///
+pub fn decode(opcode: u8, variant: Variant) -> Option<(Instruction, AddressingMode, u8)> {
+    if variant.is_cmos() {
+        return CMOS_OPCODES[opcode as usize];
+    }
+    match OPCODES[opcode as usize] {
+        None if variant.has_undocumented_opcodes() => UNDOCUMENTED_OPCODES[opcode as usize],
+        entry => entry,
+    }
+}
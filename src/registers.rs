@@ -1,5 +1,16 @@
 use crate::bus::Bus;
 
+/// Flags of the processor status register.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Flag {
+    Carry,
+    Zero,
+    IrqDisable,
+    DecimalMode,
+    Overflow,
+    Negative,
+}
+
 pub struct StatusRegister {
     pub carry: bool,
     pub zero: bool,
@@ -35,6 +46,28 @@ impl StatusRegister {
         value
     }
 
+    pub fn flag(&self, flag: Flag) -> bool {
+        match flag {
+            Flag::Carry => self.carry,
+            Flag::Zero => self.zero,
+            Flag::IrqDisable => self.irq_disable,
+            Flag::DecimalMode => self.decimal_mode,
+            Flag::Overflow => self.overflow,
+            Flag::Negative => self.negative,
+        }
+    }
+
+    pub fn set_flag(&mut self, flag: Flag, value: bool) {
+        match flag {
+            Flag::Carry => self.carry = value,
+            Flag::Zero => self.zero = value,
+            Flag::IrqDisable => self.irq_disable = value,
+            Flag::DecimalMode => self.decimal_mode = value,
+            Flag::Overflow => self.overflow = value,
+            Flag::Negative => self.negative = value,
+        }
+    }
+
     pub fn set(&mut self, value: u8) {
         self.negative = (value & 0x80) != 0;
         self.overflow = (value & 0x40) != 0;
@@ -63,12 +96,30 @@ impl Stack {
         self.pointer = self.pointer.wrapping_sub(1);
     }
 
-    pub fn pop<T: Bus>(&mut self, bus: &T) -> u8 {
+    pub fn pop<T: Bus>(&mut self, bus: &mut T) -> u8 {
         self.pointer = self.pointer.wrapping_add(1);
         let addr = 0x100u16 | self.pointer as u16;
         bus.read(addr)
     }
 
+    /// Pushes a 16 bit address, high byte first.
+    pub fn push_address<T: Bus>(&mut self, value: u16, bus: &mut T) {
+        self.push((value >> 8) as u8, bus);
+        self.push((value & 0xFF) as u8, bus);
+    }
+
+    /// Pops a 16 bit address, low byte first.
+    pub fn pop_address<T: Bus>(&mut self, bus: &mut T) -> u16 {
+        let lo = self.pop(bus) as u16;
+        let hi = self.pop(bus) as u16;
+        (hi << 8) | lo
+    }
+
+    /// Returns the address in the bus the stack pointer points to.
+    pub fn address(&self) -> u16 {
+        0x100u16 | self.pointer as u16
+    }
+
     pub fn get(&self) -> u8 {
         self.pointer
     }
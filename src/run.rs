@@ -0,0 +1,56 @@
+use crate::watch::WatchHit;
+
+/// Limits of a `Cpu::run`, on top of the breakpoints set on the `Cpu`.
+/// The default runs forever.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RunUntil {
+    /// Stops once this many cycles have elapsed since the run started.
+    pub cycles: Option<u64>,
+    /// Stops once this many steps have run. Servicing an interrupt counts
+    /// as a step.
+    pub instructions: Option<u64>,
+    /// Stops before executing any of these opcodes, e.g. `0x00` for BRK.
+    pub opcodes: Vec<u8>,
+}
+
+impl RunUntil {
+    ///
+    /// Runs for at most the given number of cycles.
+    ///
+    pub fn cycles(cycles: u64) -> RunUntil {
+        RunUntil { cycles: Some(cycles), ..RunUntil::default() }
+    }
+
+    ///
+    /// Runs at most the given number of instructions.
+    ///
+    pub fn instructions(instructions: u64) -> RunUntil {
+        RunUntil { instructions: Some(instructions), ..RunUntil::default() }
+    }
+
+    ///
+    /// Runs until one of the given opcodes is about to be executed.
+    ///
+    pub fn opcodes(opcodes: &[u8]) -> RunUntil {
+        RunUntil { opcodes: opcodes.to_vec(), ..RunUntil::default() }
+    }
+}
+
+/// Why a run stopped.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// The program counter reached a breakpoint, which is not executed yet.
+    Breakpoint(u16),
+    /// The cycle budget is spent.
+    CycleLimit,
+    /// The instruction budget is spent.
+    InstructionLimit,
+    /// One of the requested opcodes is at `addr`, not executed yet.
+    Opcode { addr: u16, opcode: u8 },
+    /// The subroutine returned to its caller.
+    StepOut,
+    /// The instruction, or the whole subroutine called by a JSR, completed.
+    StepOver,
+    /// An instruction accessed memory under a watchpoint of a `WatchBus`.
+    Watchpoint(WatchHit),
+}
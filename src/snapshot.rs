@@ -0,0 +1,314 @@
+use crate::error::SnapshotError;
+use crate::execution_mode::ExecutionMode;
+use crate::variant::Variant;
+
+/// Version of the snapshot format written by this crate. Snapshots with a
+/// different version are rejected when restored.
+pub const SNAPSHOT_VERSION: u16 = 1;
+/// Signature at the start of the binary encoding.
+const SIGNATURE: [u8; 4] = *b"M65S";
+
+/// Variants in the order of their index in the binary encodings.
+const VARIANTS: [Variant; 5] = [
+    Variant::Nmos6502,
+    Variant::Nmos6502Undocumented,
+    Variant::Wdc65c02,
+    Variant::Ricoh2A03,
+    Variant::Mos6510,
+];
+const EXECUTION_MODES: [ExecutionMode; 2] = [ExecutionMode::Fast, ExecutionMode::CycleAccurate];
+
+const IRQ_ACTIVE: u8 = 0x01;
+const NMI_ACTIVE: u8 = 0x02;
+const JAMMED: u8 = 0x04;
+const WAITING: u8 = 0x08;
+const STOPPED: u8 = 0x10;
+const HAS_IO_PORT: u8 = 0x20;
+
+/// Saved state of a `Cpu`, taken with `Cpu::snapshot` and brought back
+/// with `Cpu::restore`.
+///
+/// `to_bytes` and `from_bytes` give a compact binary encoding. With the
+/// `serde` feature, the snapshot can also be written in any format supported
+/// by serde, such as JSON or RON.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot {
+    /// Version of the format, `SNAPSHOT_VERSION` when taken by this crate.
+    pub version: u16,
+    pub variant: Variant,
+    pub execution_mode: ExecutionMode,
+    pub accumulator: u8,
+    pub x_index: u8,
+    pub y_index: u8,
+    /// Stack pointer, as an offset into page 1.
+    pub stack_pointer: u8,
+    pub program_counter: u16,
+    /// Packed status register, B bit included.
+    pub status: u8,
+    /// IRQB line raised and not yet released.
+    pub irq_active: bool,
+    /// NMI signalled and not yet serviced.
+    pub nmi_active: bool,
+    pub cycles: u64,
+    pub jammed: bool,
+    pub waiting: bool,
+    pub stopped: bool,
+    /// State of the I/O port, only present on the 6510.
+    pub io_port: Option<IoPortSnapshot>,
+}
+
+/// Saved state of the 6510 I/O port. The banking callback is not part of
+/// it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IoPortSnapshot {
+    pub direction: u8,
+    pub data: u8,
+    pub pull_ups: u8,
+    /// Last values driven on the bits released as floating inputs.
+    pub floating: u8,
+    /// Cycle at which every floating bit fades out to zero.
+    pub fade_out: [u64; 6],
+    /// Banking bits as last seen by the memory configuration logic.
+    pub banking: u8,
+}
+
+impl Snapshot {
+    ///
+    /// Encodes the snapshot in the binary format: a signature, the version
+    /// and the fields in little endian.
+    ///
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(96);
+        bytes.extend_from_slice(&SIGNATURE);
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.push(variant_index(self.variant));
+        bytes.push(match self.execution_mode {
+            ExecutionMode::Fast => 0,
+            ExecutionMode::CycleAccurate => 1,
+        });
+        bytes.extend_from_slice(&[self.accumulator, self.x_index, self.y_index, self.stack_pointer]);
+        bytes.extend_from_slice(&self.program_counter.to_le_bytes());
+        bytes.push(self.status);
+        bytes.extend_from_slice(&self.cycles.to_le_bytes());
+
+        let mut flags = 0;
+        if self.irq_active { flags |= IRQ_ACTIVE; }
+        if self.nmi_active { flags |= NMI_ACTIVE; }
+        if self.jammed { flags |= JAMMED; }
+        if self.waiting { flags |= WAITING; }
+        if self.stopped { flags |= STOPPED; }
+        if self.io_port.is_some() { flags |= HAS_IO_PORT; }
+        bytes.push(flags);
+
+        if let Some(port) = &self.io_port {
+            bytes.extend_from_slice(&[port.direction, port.data, port.pull_ups, port.floating, port.banking]);
+            for cycles in port.fade_out.iter() {
+                bytes.extend_from_slice(&cycles.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    ///
+    /// Decodes a snapshot written by `to_bytes`. The version is checked
+    /// before anything else is decoded, and the data must end with the
+    /// snapshot.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> Result<Snapshot, SnapshotError> {
+        let mut reader = Reader { bytes };
+        if reader.take(SIGNATURE.len())? != SIGNATURE {
+            return Err(SnapshotError::BadSignature);
+        }
+        let version = reader.u16()?;
+        check_version(version)?;
+
+        let variant = variant_from_index(reader.u8()?)
+            .ok_or(SnapshotError::InvalidField { field: "variant" })?;
+        let execution_mode = *EXECUTION_MODES.get(reader.u8()? as usize)
+            .ok_or(SnapshotError::InvalidField { field: "execution_mode" })?;
+        let accumulator = reader.u8()?;
+        let x_index = reader.u8()?;
+        let y_index = reader.u8()?;
+        let stack_pointer = reader.u8()?;
+        let program_counter = reader.u16()?;
+        let status = reader.u8()?;
+        let cycles = reader.u64()?;
+        let flags = reader.u8()?;
+
+        let io_port = if (flags & HAS_IO_PORT) != 0 {
+            let direction = reader.u8()?;
+            let data = reader.u8()?;
+            let pull_ups = reader.u8()?;
+            let floating = reader.u8()?;
+            let banking = reader.u8()?;
+            let mut fade_out = [0; 6];
+            for cycles in fade_out.iter_mut() {
+                *cycles = reader.u64()?;
+            }
+            Some(IoPortSnapshot { direction, data, pull_ups, floating, fade_out, banking })
+        } else {
+            None
+        };
+        if !reader.bytes.is_empty() {
+            return Err(SnapshotError::TrailingBytes { count: reader.bytes.len() });
+        }
+
+        Ok(Snapshot {
+            version,
+            variant,
+            execution_mode,
+            accumulator,
+            x_index,
+            y_index,
+            stack_pointer,
+            program_counter,
+            status,
+            irq_active: (flags & IRQ_ACTIVE) != 0,
+            nmi_active: (flags & NMI_ACTIVE) != 0,
+            cycles,
+            jammed: (flags & JAMMED) != 0,
+            waiting: (flags & WAITING) != 0,
+            stopped: (flags & STOPPED) != 0,
+            io_port,
+        })
+    }
+}
+
+/// Returns the index of the variant in the binary encodings.
+pub(crate) fn variant_index(variant: Variant) -> u8 {
+    match variant {
+        Variant::Nmos6502 => 0,
+        Variant::Nmos6502Undocumented => 1,
+        Variant::Wdc65c02 => 2,
+        Variant::Ricoh2A03 => 3,
+        Variant::Mos6510 => 4,
+    }
+}
+
+/// Returns the variant with the given index in the binary encodings.
+pub(crate) fn variant_from_index(index: u8) -> Option<Variant> {
+    VARIANTS.get(index as usize).copied()
+}
+
+///
+/// Fails unless the version is the one written by this crate.
+///
+pub(crate) fn check_version(version: u16) -> Result<(), SnapshotError> {
+    if version != SNAPSHOT_VERSION {
+        return Err(SnapshotError::UnsupportedVersion { found: version, supported: SNAPSHOT_VERSION });
+    }
+    Ok(())
+}
+
+/// Cursor over the binary encoding.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], SnapshotError> {
+        if self.bytes.len() < count {
+            return Err(SnapshotError::Truncated);
+        }
+        let (head, tail) = self.bytes.split_at(count);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SnapshotError> {
+        let mut value = [0; 2];
+        value.copy_from_slice(self.take(2)?);
+        Ok(u16::from_le_bytes(value))
+    }
+
+    fn u64(&mut self) -> Result<u64, SnapshotError> {
+        let mut value = [0; 8];
+        value.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            variant: Variant::Mos6510,
+            execution_mode: ExecutionMode::CycleAccurate,
+            accumulator: 0x12,
+            x_index: 0x34,
+            y_index: 0x56,
+            stack_pointer: 0xF0,
+            program_counter: 0xC000,
+            status: 0x81,
+            irq_active: true,
+            nmi_active: false,
+            cycles: 0x0123_4567_89AB,
+            jammed: false,
+            waiting: true,
+            stopped: false,
+            io_port: Some(IoPortSnapshot {
+                direction: 0x2F,
+                data: 0x37,
+                pull_ups: 0x17,
+                floating: 0x20,
+                fade_out: [0, 0, 0, 0, 0, 350_000],
+                banking: 0x07,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let snapshot = snapshot();
+        assert_eq!(Snapshot::from_bytes(&snapshot.to_bytes()), Ok(snapshot));
+
+        let snapshot = Snapshot { variant: Variant::Wdc65c02, io_port: None, ..self::snapshot() };
+        assert_eq!(Snapshot::from_bytes(&snapshot.to_bytes()), Ok(snapshot));
+    }
+
+    #[test]
+    fn test_binary_errors() {
+        let mut bytes = snapshot().to_bytes();
+        assert_eq!(Snapshot::from_bytes(&bytes[..bytes.len() - 1]), Err(SnapshotError::Truncated));
+        assert_eq!(Snapshot::from_bytes(b"6502"), Err(SnapshotError::BadSignature));
+
+        bytes[4] = 0x02;
+        assert_eq!(Snapshot::from_bytes(&bytes),
+                   Err(SnapshotError::UnsupportedVersion { found: 2, supported: SNAPSHOT_VERSION }));
+
+        bytes[4] = 0x01;
+        bytes[6] = 0x09;
+        assert_eq!(Snapshot::from_bytes(&bytes), Err(SnapshotError::InvalidField { field: "variant" }));
+
+        let mut bytes = snapshot().to_bytes();
+        bytes.extend_from_slice(&[0x00, 0x00]);
+        assert_eq!(Snapshot::from_bytes(&bytes), Err(SnapshotError::TrailingBytes { count: 2 }));
+    }
+
+    #[test]
+    fn test_variant_indices() {
+        for (index, variant) in VARIANTS.iter().enumerate() {
+            assert_eq!(variant_index(*variant), index as u8);
+            assert_eq!(variant_from_index(index as u8), Some(*variant));
+        }
+        assert_eq!(variant_from_index(VARIANTS.len() as u8), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let snapshot = snapshot();
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(json.contains("\"variant\":\"Mos6510\""));
+        assert_eq!(serde_json::from_str::<Snapshot>(&json).unwrap(), snapshot);
+    }
+}
@@ -0,0 +1,424 @@
+use crate::addressing_modes::AddressingMode;
+use crate::bus::Bus;
+use crate::instruction::Instruction;
+use crate::opcodes::decode;
+use crate::snapshot::{variant_from_index, variant_index};
+use crate::variant::Variant;
+use std::io::{self, ErrorKind, Read, Write};
+
+/// Signature at the start of the binary trace.
+const SIGNATURE: [u8; 4] = *b"M65T";
+/// Version of the binary trace format.
+const TRACE_VERSION: u8 = 1;
+/// PPU dots per CPU cycle, scanline and frame of the NTSC NES, used for the
+/// PPU column of the nestest format.
+const DOTS_PER_CYCLE: u64 = 3;
+const DOTS_PER_SCANLINE: u64 = 341;
+const SCANLINES_PER_FRAME: u64 = 262;
+
+/// State of the processor before an instruction, as given to a `Tracer`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub variant: Variant,
+    /// Address of the instruction.
+    pub pc: u16,
+    /// Opcode and operand bytes, `len` of them are used.
+    pub bytes: [u8; 3],
+    pub len: u8,
+    pub accumulator: u8,
+    pub x_index: u8,
+    pub y_index: u8,
+    /// Packed status register, as returned by `Cpu::status`.
+    pub status: u8,
+    pub stack_pointer: u8,
+    /// Cycles elapsed before the instruction.
+    pub cycles: u64,
+}
+
+impl TraceEntry {
+    /// Returns the opcode and operand bytes of the instruction.
+    pub fn instruction_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+
+    ///
+    /// Formats the entry as a line of the nestest.log reference, without
+    /// the line break. The values shown next to the memory operands are
+    /// peeked from the bus, which must be in the state the instruction is
+    /// about to run in.
+    ///
+    /// The PPU column is derived from the cycle count, at three dots per
+    /// cycle on a 341 by 262 frame.
+    ///
+    pub fn nestest_line(&self, bus: &mut dyn Bus) -> String {
+        let bytes: Vec<String> = self.instruction_bytes().iter().map(|b| format!("{:02X}", b)).collect();
+        let opcode = self.bytes[0];
+        let (marker, disassembly) = match decode(opcode, self.variant) {
+            Some((instruction, mode, _)) => {
+                let documented = self.variant.is_cmos() || decode(opcode, Variant::Nmos6502).is_some();
+                let mnemonic = match instruction {
+                    Instruction::Isc => "ISB".to_string(),
+                    _ => instruction.mnemonic(),
+                };
+                let operand = self.nestest_operand(instruction, mode, bus);
+                let text = if operand.is_empty() { mnemonic } else { format!("{} {}", mnemonic, operand) };
+                (if documented { ' ' } else { '*' }, text)
+            }
+            None => ('*', format!(".BYTE ${:02X}", opcode)),
+        };
+        let dots = self.cycles * DOTS_PER_CYCLE;
+        format!("{:04X}  {:<8} {}{:<31} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:>3},{:>3} CYC:{}",
+                self.pc, bytes.join(" "), marker, disassembly,
+                self.accumulator, self.x_index, self.y_index, self.status, self.stack_pointer,
+                (dots / DOTS_PER_SCANLINE) % SCANLINES_PER_FRAME, dots % DOTS_PER_SCANLINE, self.cycles)
+    }
+
+    fn nestest_operand(&self, instruction: Instruction, mode: AddressingMode, bus: &mut dyn Bus) -> String {
+        let byte = self.bytes[1];
+        let word = u16::from_le_bytes([self.bytes[1], self.bytes[2]]);
+        let next = self.pc.wrapping_add(self.len as u16);
+        match mode {
+            AddressingMode::Implied => String::new(),
+            AddressingMode::Accumulator => "A".to_string(),
+            AddressingMode::Immediate => format!("#${:02X}", byte),
+            AddressingMode::Relative => format!("${:04X}", next.wrapping_add(byte as i8 as u16)),
+            AddressingMode::Zeropage => format!("${:02X} = {:02X}", byte, bus.peek(byte as u16)),
+            AddressingMode::ZeropageXIndexed | AddressingMode::ZeropageYIndexed => {
+                let (register, index) = if mode == AddressingMode::ZeropageXIndexed {
+                    ('X', self.x_index)
+                } else {
+                    ('Y', self.y_index)
+                };
+                let addr = byte.wrapping_add(index);
+                format!("${:02X},{} @ {:02X} = {:02X}", byte, register, addr, bus.peek(addr as u16))
+            }
+            AddressingMode::Absolute => match instruction {
+                Instruction::Jmp | Instruction::Jsr => format!("${:04X}", word),
+                _ => format!("${:04X} = {:02X}", word, bus.peek(word)),
+            },
+            AddressingMode::AbsoluteXIndexed | AddressingMode::AbsoluteYIndexed => {
+                let (register, index) = if mode == AddressingMode::AbsoluteXIndexed {
+                    ('X', self.x_index)
+                } else {
+                    ('Y', self.y_index)
+                };
+                let addr = word.wrapping_add(index as u16);
+                format!("${:04X},{} @ {:04X} = {:02X}", word, register, addr, bus.peek(addr))
+            }
+            AddressingMode::Indirect => {
+                // The NMOS cores don't carry into the high byte of the pointer.
+                let high = if self.variant.is_cmos() {
+                    word.wrapping_add(1)
+                } else {
+                    (word & 0xFF00) | (word.wrapping_add(1) & 0x00FF)
+                };
+                let target = u16::from_le_bytes([bus.peek(word), bus.peek(high)]);
+                format!("(${:04X}) = {:04X}", word, target)
+            }
+            AddressingMode::AbsoluteXIndexedIndirect => {
+                let pointer = word.wrapping_add(self.x_index as u16);
+                let target = u16::from_le_bytes([bus.peek(pointer), bus.peek(pointer.wrapping_add(1))]);
+                format!("(${:04X},X) = {:04X}", word, target)
+            }
+            AddressingMode::XIndexedIndirect => {
+                let pointer = byte.wrapping_add(self.x_index);
+                let addr = zeropage_word(bus, pointer);
+                format!("(${:02X},X) @ {:02X} = {:04X} = {:02X}", byte, pointer, addr, bus.peek(addr))
+            }
+            AddressingMode::IndirectYIndexed => {
+                let base = zeropage_word(bus, byte);
+                let addr = base.wrapping_add(self.y_index as u16);
+                format!("(${:02X}),Y = {:04X} @ {:04X} = {:02X}", byte, base, addr, bus.peek(addr))
+            }
+            AddressingMode::ZeropageIndirect => {
+                let addr = zeropage_word(bus, byte);
+                format!("(${:02X}) = {:04X} = {:02X}", byte, addr, bus.peek(addr))
+            }
+            AddressingMode::ZeropageRelative => {
+                let target = next.wrapping_add(self.bytes[2] as i8 as u16);
+                format!("${:02X},${:04X} = {:02X}", byte, target, bus.peek(byte as u16))
+            }
+        }
+    }
+}
+
+/// Reads a pointer from the zero page, wrapping within it.
+fn zeropage_word(bus: &mut dyn Bus, addr: u8) -> u16 {
+    u16::from_le_bytes([bus.peek(addr as u16), bus.peek(addr.wrapping_add(1) as u16)])
+}
+
+/// Receives the state of the processor before each instruction, once set
+/// with `Cpu::set_tracer`. Interrupt sequences are not traced.
+pub trait Tracer {
+    ///
+    /// Called before the instruction of the entry is executed. The bus can
+    /// be peeked to show the operands.
+    ///
+    fn trace(&mut self, entry: &TraceEntry, bus: &mut dyn Bus);
+
+    ///
+    /// Flushes the trace and reports the first error met while writing it.
+    ///
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes a line per instruction in the format of the nestest.log
+/// reference.
+pub struct NestestTracer<W: Write> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> NestestTracer<W> {
+    /// Constructs a tracer writing to the given writer.
+    pub fn new(writer: W) -> Self {
+        NestestTracer { writer, error: None }
+    }
+}
+
+impl<W: Write> Tracer for NestestTracer<W> {
+    fn trace(&mut self, entry: &TraceEntry, bus: &mut dyn Bus) {
+        if self.error.is_none() {
+            let line = entry.nestest_line(bus);
+            if let Err(e) = writeln!(self.writer, "{}", line) {
+                self.error = Some(e);
+            }
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.writer.flush(),
+        }
+    }
+}
+
+/// Writes a compact binary record per instruction, read back with
+/// `BinaryTraceReader`.
+///
+/// The trace starts with a signature, a version and the variant. Each
+/// record then holds the program counter in little endian, A, X, Y, P and
+/// SP, the instruction bytes and the cycles elapsed since the previous
+/// record. The cycle delta is an LEB128 number, usually one byte, so a
+/// record usually takes 9 to 11 bytes. Memory operands are not recorded.
+pub struct BinaryTracer<W: Write> {
+    writer: W,
+    error: Option<io::Error>,
+    /// Cycle count of the previous record, `None` before the header.
+    cycles: Option<u64>,
+}
+
+impl<W: Write> BinaryTracer<W> {
+    /// Constructs a tracer writing to the given writer.
+    pub fn new(writer: W) -> Self {
+        BinaryTracer { writer, error: None, cycles: None }
+    }
+
+    fn write_entry(&mut self, entry: &TraceEntry) -> io::Result<()> {
+        let previous = match self.cycles {
+            Some(cycles) => cycles,
+            None => {
+                let variant = variant_index(entry.variant);
+                self.writer.write_all(&SIGNATURE)?;
+                self.writer.write_all(&[TRACE_VERSION, variant])?;
+                0
+            }
+        };
+        self.cycles = Some(entry.cycles);
+
+        let mut record = Vec::with_capacity(16);
+        record.extend_from_slice(&entry.pc.to_le_bytes());
+        record.extend_from_slice(&[entry.accumulator, entry.x_index, entry.y_index, entry.status, entry.stack_pointer]);
+        record.extend_from_slice(entry.instruction_bytes());
+        let mut delta = entry.cycles.wrapping_sub(previous);
+        loop {
+            let byte = (delta & 0x7F) as u8;
+            delta >>= 7;
+            if delta == 0 {
+                record.push(byte);
+                break;
+            }
+            record.push(byte | 0x80);
+        }
+        self.writer.write_all(&record)
+    }
+}
+
+impl<W: Write> Tracer for BinaryTracer<W> {
+    fn trace(&mut self, entry: &TraceEntry, _bus: &mut dyn Bus) {
+        if self.error.is_none() {
+            if let Err(e) = self.write_entry(entry) {
+                self.error = Some(e);
+            }
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.writer.flush(),
+        }
+    }
+}
+
+/// Decodes a trace written by `BinaryTracer`, one entry at a time.
+pub struct BinaryTraceReader<R: Read> {
+    reader: R,
+    variant: Variant,
+    cycles: u64,
+}
+
+impl<R: Read> BinaryTraceReader<R> {
+    ///
+    /// Reads the header of the trace. Fails with `InvalidData` if the
+    /// signature, version or variant is not recognized.
+    ///
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = [0; 6];
+        reader.read_exact(&mut header)?;
+        if header[..4] != SIGNATURE || header[4] != TRACE_VERSION {
+            return Err(io::Error::new(ErrorKind::InvalidData, "not a binary trace of a supported version"));
+        }
+        let variant = variant_from_index(header[5])
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "unknown variant"))?;
+        Ok(BinaryTraceReader { reader, variant, cycles: 0 })
+    }
+
+    /// Returns the variant the trace was taken on.
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    ///
+    /// Reads the next entry. Returns `None` at the end of the trace.
+    ///
+    pub fn read_entry(&mut self) -> io::Result<Option<TraceEntry>> {
+        let mut registers = [0; 7];
+        match self.reader.read(&mut registers[..1])? {
+            0 => return Ok(None),
+            _ => self.reader.read_exact(&mut registers[1..])?,
+        }
+        let mut bytes = [0; 3];
+        self.reader.read_exact(&mut bytes[..1])?;
+        let len = decode(bytes[0], self.variant).map_or(1, |(_, mode, _)| 1 + mode.operand_length() as u8);
+        self.reader.read_exact(&mut bytes[1..len as usize])?;
+
+        let mut delta = 0u64;
+        for shift in (0..64).step_by(7) {
+            let mut byte = [0];
+            self.reader.read_exact(&mut byte)?;
+            delta |= ((byte[0] & 0x7F) as u64) << shift;
+            if byte[0] & 0x80 == 0 {
+                break;
+            }
+        }
+        self.cycles = self.cycles.wrapping_add(delta);
+
+        Ok(Some(TraceEntry {
+            variant: self.variant,
+            pc: u16::from_le_bytes([registers[0], registers[1]]),
+            bytes,
+            len,
+            accumulator: registers[2],
+            x_index: registers[3],
+            y_index: registers[4],
+            status: registers[5],
+            stack_pointer: registers[6],
+            cycles: self.cycles,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for BinaryTraceReader<R> {
+    type Item = io::Result<TraceEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_entry().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::tests::DummyBus;
+
+    fn entry(bytes: &[u8]) -> TraceEntry {
+        let mut entry = TraceEntry {
+            variant: Variant::Ricoh2A03,
+            pc: 0xC000,
+            bytes: [0; 3],
+            len: bytes.len() as u8,
+            accumulator: 0x00,
+            x_index: 0x02,
+            y_index: 0x10,
+            status: 0x24,
+            stack_pointer: 0xFD,
+            cycles: 7,
+        };
+        entry.bytes[..bytes.len()].copy_from_slice(bytes);
+        entry
+    }
+
+    #[test]
+    fn test_nestest_lines() {
+        let mut bus = DummyBus::new();
+        bus.write(0x0080, 0x00);
+        bus.write(0x0081, 0x02);
+        bus.write(0x0082, 0x34);
+        bus.write(0x0083, 0x12);
+        bus.write(0x0200, 0x5A);
+        bus.write(0x0210, 0x89);
+        bus.write(0x02FF, 0x7E);
+        bus.write(0x0300, 0xDB);
+        bus.write(0x1236, 0x11);
+
+        let line = |bytes: &[u8], bus: &mut DummyBus| entry(bytes).nestest_line(bus);
+        assert_eq!(line(&[0x4C, 0xF5, 0xC5], &mut bus),
+                   "C000  4C F5 C5  JMP $C5F5                       A:00 X:02 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7");
+        assert_eq!(line(&[0xA1, 0x7E], &mut bus),
+                   "C000  A1 7E     LDA ($7E,X) @ 80 = 0200 = 5A    A:00 X:02 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7");
+        assert_eq!(line(&[0xB1, 0x80], &mut bus),
+                   "C000  B1 80     LDA ($80),Y = 0200 @ 0210 = 89  A:00 X:02 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7");
+        assert_eq!(line(&[0x6C, 0xFF, 0x02], &mut bus),
+                   "C000  6C FF 02  JMP ($02FF) = 5A7E              A:00 X:02 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7");
+        assert_eq!(line(&[0x04, 0x80], &mut bus),
+                   "C000  04 80    *NOP $80 = 00                    A:00 X:02 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7");
+        assert_eq!(line(&[0xE3, 0x80], &mut bus),
+                   "C000  E3 80    *ISB ($80,X) @ 82 = 1234 = 00    A:00 X:02 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7");
+        assert_eq!(line(&[0xBD, 0x34, 0x12], &mut bus),
+                   "C000  BD 34 12  LDA $1234,X @ 1236 = 11         A:00 X:02 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7");
+        assert_eq!(line(&[0x4A], &mut bus),
+                   "C000  4A        LSR A                           A:00 X:02 Y:10 P:24 SP:FD PPU:  0, 21 CYC:7");
+
+        let late = TraceEntry { cycles: 27_394, pc: 0xC5F5, ..entry(&[0xD0, 0xFB]) };
+        assert_eq!(late.nestest_line(&mut bus),
+                   "C5F5  D0 FB     BNE $C5F2                       A:00 X:02 Y:10 P:24 SP:FD PPU:241,  1 CYC:27394");
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let entries = [
+            entry(&[0x4C, 0xF5, 0xC5]),
+            TraceEntry { pc: 0xC5F5, cycles: 10, ..entry(&[0xA2, 0x00]) },
+            TraceEntry { pc: 0xC5F7, cycles: 100_000, ..entry(&[0x02]) },
+        ];
+        let mut trace = Vec::new();
+        let mut tracer = BinaryTracer::new(&mut trace);
+        for entry in entries.iter() {
+            tracer.trace(entry, &mut DummyBus::new());
+        }
+        tracer.finish().unwrap();
+        assert_eq!(trace.len(), 6 + 11 + 10 + 11);
+
+        let reader = BinaryTraceReader::new(trace.as_slice()).unwrap();
+        assert_eq!(reader.variant(), Variant::Ricoh2A03);
+        assert_eq!(reader.collect::<io::Result<Vec<_>>>().unwrap(), entries);
+
+        let truncated = BinaryTraceReader::new(&trace[..trace.len() - 1]).unwrap();
+        assert!(truncated.collect::<io::Result<Vec<_>>>().is_err());
+        assert_eq!(BinaryTraceReader::new(&b"M65S\x01\x00"[..]).err().map(|e| e.kind()), Some(ErrorKind::InvalidData));
+    }
+}
@@ -0,0 +1,54 @@
+
+/// Flavours of the 6502 core that can be emulated by `Cpu`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Variant {
+    /// Original NMOS 6502, as manufactured by MOS Technology.
+    #[default]
+    Nmos6502,
+    /// Original NMOS 6502 that also executes the undocumented opcodes.
+    Nmos6502Undocumented,
+    /// WDC 65C02, the CMOS redesign, with the Rockwell bit instructions.
+    Wdc65c02,
+    /// Ricoh 2A03 of the NES: an NMOS 6502, undocumented opcodes included,
+    /// whose decimal mode is disconnected. D is still a regular flag.
+    Ricoh2A03,
+    /// MOS 6510 of the C64: an NMOS 6502, undocumented opcodes included,
+    /// with an I/O port mapped at $0000 and $0001.
+    Mos6510,
+}
+
+impl Variant {
+    /// Returns true if ADC and SBC honour the decimal flag on this variant.
+    pub fn has_decimal_mode(&self) -> bool {
+        match self {
+            Variant::Nmos6502 | Variant::Nmos6502Undocumented | Variant::Wdc65c02 |
+            Variant::Mos6510 => true,
+            Variant::Ricoh2A03 => false,
+        }
+    }
+
+    /// Returns true if the undocumented NMOS opcodes are executed instead of
+    /// being reported as unknown.
+    pub fn has_undocumented_opcodes(&self) -> bool {
+        match self {
+            Variant::Nmos6502 | Variant::Wdc65c02 => false,
+            Variant::Nmos6502Undocumented | Variant::Ricoh2A03 | Variant::Mos6510 => true,
+        }
+    }
+
+    /// Returns true for the CMOS cores, which fix the bugs of the NMOS chip
+    /// and use their own opcode table.
+    pub fn is_cmos(&self) -> bool {
+        match self {
+            Variant::Nmos6502 | Variant::Nmos6502Undocumented | Variant::Ricoh2A03 |
+            Variant::Mos6510 => false,
+            Variant::Wdc65c02 => true,
+        }
+    }
+
+    /// Returns true if the variant has the on-chip I/O port of the 6510.
+    pub fn has_io_port(&self) -> bool {
+        *self == Variant::Mos6510
+    }
+}
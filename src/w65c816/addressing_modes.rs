@@ -0,0 +1,369 @@
+use crate::bus::Bus;
+use super::registers::Registers;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AddressingMode {
+    Accumulator,
+    Implied,
+    /// `#imm`, as wide as the accumulator.
+    Immediate,
+    /// `#imm`, as wide as the index registers.
+    ImmediateIndex,
+    /// `#imm`, always 8 bits: REP, SEP, WDM and the BRK/COP signature.
+    ImmediateByte,
+    Relative,
+    RelativeLong,
+    Direct,
+    DirectXIndexed,
+    DirectYIndexed,
+    /// `(dp)`
+    DirectIndirect,
+    /// `[dp]`
+    DirectIndirectLong,
+    /// `(dp,X)`
+    XIndexedIndirect,
+    /// `(dp),Y`
+    IndirectYIndexed,
+    /// `[dp],Y`
+    IndirectLongYIndexed,
+    Absolute,
+    AbsoluteXIndexed,
+    AbsoluteYIndexed,
+    AbsoluteLong,
+    AbsoluteLongXIndexed,
+    /// `(abs)`, only used by JMP.
+    Indirect,
+    /// `[abs]`, only used by JML.
+    IndirectLong,
+    /// `(abs,X)`, only used by JMP and JSR.
+    AbsoluteXIndexedIndirect,
+    /// `sr,S`
+    StackRelative,
+    /// `(sr,S),Y`
+    StackRelativeIndirectYIndexed,
+    /// `dst,src` bank operands of MVN and MVP.
+    BlockMove,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Operand {
+    /// 24 bit address of the operand.
+    Addr(u32),
+    /// Address in bank 0 of a direct page or stack operand. The second
+    /// byte of a 16 bit operand wraps inside bank 0.
+    DirectAddr(u16),
+    Value(u16),
+    Accumulator,
+    /// Destination and source banks of a block move.
+    Banks(u8, u8),
+    None,
+}
+
+impl AddressingMode {
+    ///
+    /// Fetches the operand of the current instruction. The second element of
+    /// the result is true when indexing takes an extra cycle, which happens
+    /// for reads that cross a page or use 16 bit index registers.
+    ///
+    pub fn get_operand<T: Bus>(&self, bus: &mut T, regs: &mut Registers) -> (Operand, bool) {
+        match self {
+            AddressingMode::Accumulator => (Operand::Accumulator, false),
+            AddressingMode::Implied => (Operand::None, false),
+            AddressingMode::Immediate => {
+                let value = if regs.accumulator_8bit() { self.fetch_byte(bus, regs) as u16 } else { self.fetch_word(bus, regs) };
+                (Operand::Value(value), false)
+            },
+            AddressingMode::ImmediateIndex => {
+                let value = if regs.index_8bit() { self.fetch_byte(bus, regs) as u16 } else { self.fetch_word(bus, regs) };
+                (Operand::Value(value), false)
+            },
+            AddressingMode::ImmediateByte => {
+                (Operand::Value(self.fetch_byte(bus, regs) as u16), false)
+            },
+            AddressingMode::Relative => {
+                let offset = self.fetch_byte(bus, regs) as i8;
+                let target = regs.program_counter.wrapping_add(offset as u16);
+                (Operand::Addr(self.program_bank_address(regs, target)), false)
+            },
+            AddressingMode::RelativeLong => {
+                let offset = self.fetch_word(bus, regs);
+                let target = regs.program_counter.wrapping_add(offset);
+                (Operand::Addr(self.program_bank_address(regs, target)), false)
+            },
+            AddressingMode::Direct => {
+                let offset = self.fetch_byte(bus, regs) as u16;
+                (Operand::DirectAddr(regs.direct_page.wrapping_add(offset)), false)
+            },
+            AddressingMode::DirectXIndexed => {
+                let offset = self.fetch_byte(bus, regs) as u16;
+                (Operand::DirectAddr(self.direct_address(regs, offset.wrapping_add(regs.x_index))), false)
+            },
+            AddressingMode::DirectYIndexed => {
+                let offset = self.fetch_byte(bus, regs) as u16;
+                (Operand::DirectAddr(self.direct_address(regs, offset.wrapping_add(regs.y_index))), false)
+            },
+            AddressingMode::DirectIndirect => {
+                let offset = self.fetch_byte(bus, regs) as u16;
+                let ptr = self.read_direct_pointer(bus, regs, offset);
+                (Operand::Addr(self.data_bank_address(regs, ptr)), false)
+            },
+            AddressingMode::DirectIndirectLong => {
+                let offset = self.fetch_byte(bus, regs) as u16;
+                (Operand::Addr(self.read_direct_long_pointer(bus, regs, offset)), false)
+            },
+            AddressingMode::XIndexedIndirect => {
+                let offset = self.fetch_byte(bus, regs) as u16;
+                let ptr = self.read_direct_pointer(bus, regs, offset.wrapping_add(regs.x_index));
+                (Operand::Addr(self.data_bank_address(regs, ptr)), false)
+            },
+            AddressingMode::IndirectYIndexed => {
+                let offset = self.fetch_byte(bus, regs) as u16;
+                let base = self.data_bank_address(regs, self.read_direct_pointer(bus, regs, offset));
+                self.index_address(regs, base, regs.y_index)
+            },
+            AddressingMode::IndirectLongYIndexed => {
+                let offset = self.fetch_byte(bus, regs) as u16;
+                let base = self.read_direct_long_pointer(bus, regs, offset);
+                (Operand::Addr(base.wrapping_add(regs.y_index as u32) & 0xFF_FFFF), false)
+            },
+            AddressingMode::Absolute => {
+                let addr = self.fetch_word(bus, regs);
+                (Operand::Addr(self.data_bank_address(regs, addr)), false)
+            },
+            AddressingMode::AbsoluteXIndexed => {
+                let addr = self.fetch_word(bus, regs);
+                let base = self.data_bank_address(regs, addr);
+                self.index_address(regs, base, regs.x_index)
+            },
+            AddressingMode::AbsoluteYIndexed => {
+                let addr = self.fetch_word(bus, regs);
+                let base = self.data_bank_address(regs, addr);
+                self.index_address(regs, base, regs.y_index)
+            },
+            AddressingMode::AbsoluteLong => {
+                (Operand::Addr(self.fetch_long(bus, regs)), false)
+            },
+            AddressingMode::AbsoluteLongXIndexed => {
+                let base = self.fetch_long(bus, regs);
+                (Operand::Addr(base.wrapping_add(regs.x_index as u32) & 0xFF_FFFF), false)
+            },
+            AddressingMode::Indirect => {
+                let ptr = self.fetch_word(bus, regs);
+                let target = self.read_word(bus, ptr as u32);
+                (Operand::Addr(self.program_bank_address(regs, target)), false)
+            },
+            AddressingMode::IndirectLong => {
+                let ptr = self.fetch_word(bus, regs);
+                let lo = self.read_word(bus, ptr as u32) as u32;
+                let bank = bus.read_long(ptr.wrapping_add(2) as u32) as u32;
+                (Operand::Addr((bank << 16) | lo), false)
+            },
+            AddressingMode::AbsoluteXIndexedIndirect => {
+                // The pointer is read from the program bank.
+                let ptr = self.fetch_word(bus, regs).wrapping_add(regs.x_index);
+                let target = self.read_word(bus, self.program_bank_address(regs, ptr));
+                (Operand::Addr(self.program_bank_address(regs, target)), false)
+            },
+            AddressingMode::StackRelative => {
+                let offset = self.fetch_byte(bus, regs) as u16;
+                (Operand::DirectAddr(regs.stack_pointer.wrapping_add(offset)), false)
+            },
+            AddressingMode::StackRelativeIndirectYIndexed => {
+                let offset = self.fetch_byte(bus, regs) as u16;
+                let ptr_addr = regs.stack_pointer.wrapping_add(offset);
+                let ptr = self.read_bank0_word(bus, ptr_addr);
+                let base = self.data_bank_address(regs, ptr);
+                (Operand::Addr(base.wrapping_add(regs.y_index as u32) & 0xFF_FFFF), false)
+            },
+            AddressingMode::BlockMove => {
+                let destination = self.fetch_byte(bus, regs);
+                let source = self.fetch_byte(bus, regs);
+                (Operand::Banks(destination, source), false)
+            },
+        }
+    }
+
+    ///
+    /// Returns true if the mode addresses the direct page, which costs an
+    /// extra cycle when the direct page register is not page aligned.
+    ///
+    pub fn is_direct(&self) -> bool {
+        matches!(self,
+            AddressingMode::Direct | AddressingMode::DirectXIndexed | AddressingMode::DirectYIndexed |
+            AddressingMode::DirectIndirect | AddressingMode::DirectIndirectLong |
+            AddressingMode::XIndexedIndirect | AddressingMode::IndirectYIndexed |
+            AddressingMode::IndirectLongYIndexed)
+    }
+
+    fn index_address(&self, regs: &Registers, base: u32, index: u16) -> (Operand, bool) {
+        let addr = base.wrapping_add(index as u32) & 0xFF_FFFF;
+        let extra_cycle = (base & 0xFF_FF00) != (addr & 0xFF_FF00) || !regs.index_8bit();
+        (Operand::Addr(addr), extra_cycle)
+    }
+
+    fn data_bank_address(&self, regs: &Registers, addr: u16) -> u32 {
+        ((regs.data_bank as u32) << 16) | addr as u32
+    }
+
+    fn program_bank_address(&self, regs: &Registers, addr: u16) -> u32 {
+        ((regs.program_bank as u32) << 16) | addr as u32
+    }
+
+    ///
+    /// Adds an offset to the direct page register. In emulation mode, with
+    /// a page aligned direct page, the result wraps inside the page like the
+    /// zero page of the 6502.
+    ///
+    fn direct_address(&self, regs: &Registers, offset: u16) -> u16 {
+        if regs.emulation && (regs.direct_page & 0x00FF) == 0 {
+            (regs.direct_page & 0xFF00) | (offset & 0x00FF)
+        } else {
+            regs.direct_page.wrapping_add(offset)
+        }
+    }
+
+    fn read_direct_pointer<T: Bus>(&self, bus: &mut T, regs: &Registers, offset: u16) -> u16 {
+        let lo = bus.read_long(self.direct_address(regs, offset) as u32) as u16;
+        let hi = bus.read_long(self.direct_address(regs, offset.wrapping_add(1)) as u32) as u16;
+        (hi << 8) | lo
+    }
+
+    fn read_direct_long_pointer<T: Bus>(&self, bus: &mut T, regs: &Registers, offset: u16) -> u32 {
+        let addr = regs.direct_page.wrapping_add(offset);
+        let lo = self.read_bank0_word(bus, addr) as u32;
+        let bank = bus.read_long(addr.wrapping_add(2) as u32) as u32;
+        (bank << 16) | lo
+    }
+
+    fn read_bank0_word<T: Bus>(&self, bus: &mut T, addr: u16) -> u16 {
+        let lo = bus.read_long(addr as u32) as u16;
+        let hi = bus.read_long(addr.wrapping_add(1) as u32) as u16;
+        (hi << 8) | lo
+    }
+
+    fn read_word<T: Bus>(&self, bus: &mut T, addr: u32) -> u16 {
+        let lo = bus.read_long(addr) as u16;
+        let hi = bus.read_long((addr & 0xFF_0000) | ((addr as u16).wrapping_add(1) as u32)) as u16;
+        (hi << 8) | lo
+    }
+
+    fn fetch_byte<T: Bus>(&self, bus: &mut T, regs: &mut Registers) -> u8 {
+        let value = bus.read_long(regs.program_address());
+        regs.program_counter = regs.program_counter.wrapping_add(1);
+        value
+    }
+
+    fn fetch_word<T: Bus>(&self, bus: &mut T, regs: &mut Registers) -> u16 {
+        let lo = self.fetch_byte(bus, regs) as u16;
+        let hi = self.fetch_byte(bus, regs) as u16;
+        (hi << 8) | lo
+    }
+
+    fn fetch_long<T: Bus>(&self, bus: &mut T, regs: &mut Registers) -> u32 {
+        let lo = self.fetch_word(bus, regs) as u32;
+        let bank = self.fetch_byte(bus, regs) as u32;
+        (bank << 16) | lo
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::w65c816::tests::LongBus;
+
+    fn native_regs() -> Registers {
+        let mut regs = Registers::new();
+        regs.set_emulation(false);
+        regs.program_bank = 0x01;
+        regs.program_counter = 0x8000;
+        regs.data_bank = 0x7E;
+        regs
+    }
+
+    #[test]
+    fn test_immediate_width() {
+        let mut bus = LongBus::new();
+        bus.load(0x01_8000, &[0x34, 0x12]);
+        let mut regs = native_regs();
+        regs.set_status(0x10);
+
+        assert_eq!(AddressingMode::Immediate.get_operand(&mut bus, &mut regs), (Operand::Value(0x1234), false));
+        assert_eq!(regs.program_counter, 0x8002);
+
+        regs.program_counter = 0x8000;
+        assert_eq!(AddressingMode::ImmediateIndex.get_operand(&mut bus, &mut regs), (Operand::Value(0x34), false));
+        assert_eq!(regs.program_counter, 0x8001);
+    }
+
+    #[test]
+    fn test_absolute_uses_data_bank() {
+        let mut bus = LongBus::new();
+        bus.load(0x01_8000, &[0xF0, 0xFF]);
+        let mut regs = native_regs();
+        regs.x_index = 0x20;
+
+        assert_eq!(AddressingMode::Absolute.get_operand(&mut bus, &mut regs), (Operand::Addr(0x7E_FFF0), false));
+
+        // Indexing carries into the next bank.
+        regs.program_counter = 0x8000;
+        assert_eq!(AddressingMode::AbsoluteXIndexed.get_operand(&mut bus, &mut regs), (Operand::Addr(0x7F_0010), true));
+    }
+
+    #[test]
+    fn test_long_addressing() {
+        let mut bus = LongBus::new();
+        bus.load(0x01_8000, &[0x56, 0x34, 0x12]);
+        let mut regs = native_regs();
+        regs.x_index = 0x0010;
+
+        assert_eq!(AddressingMode::AbsoluteLong.get_operand(&mut bus, &mut regs), (Operand::Addr(0x12_3456), false));
+        regs.program_counter = 0x8000;
+        assert_eq!(AddressingMode::AbsoluteLongXIndexed.get_operand(&mut bus, &mut regs), (Operand::Addr(0x12_3466), false));
+    }
+
+    #[test]
+    fn test_direct_indirect_long() {
+        let mut bus = LongBus::new();
+        bus.load(0x01_8000, &[0x10]);
+        bus.load(0x00_2010, &[0x00, 0x80, 0x05]);
+        let mut regs = native_regs();
+        regs.direct_page = 0x2000;
+        regs.y_index = 0x0004;
+
+        assert_eq!(AddressingMode::IndirectLongYIndexed.get_operand(&mut bus, &mut regs), (Operand::Addr(0x05_8004), false));
+    }
+
+    #[test]
+    fn test_stack_relative() {
+        let mut bus = LongBus::new();
+        bus.load(0x01_8000, &[0x03]);
+        bus.load(0x00_1FF3, &[0x00, 0x40]);
+        let mut regs = native_regs();
+        regs.stack_pointer = 0x1FF0;
+        regs.y_index = 0x0002;
+
+        assert_eq!(AddressingMode::StackRelative.get_operand(&mut bus, &mut regs), (Operand::DirectAddr(0x1FF3), false));
+        regs.program_counter = 0x8000;
+        assert_eq!(AddressingMode::StackRelativeIndirectYIndexed.get_operand(&mut bus, &mut regs), (Operand::Addr(0x7E_4002), false));
+    }
+
+    #[test]
+    fn test_direct_page_wrap_in_emulation() {
+        let mut bus = LongBus::new();
+        bus.load(0x00_8000, &[0xF0]);
+        let mut regs = Registers::new();
+        regs.program_counter = 0x8000;
+        regs.x_index = 0x20;
+
+        assert_eq!(AddressingMode::DirectXIndexed.get_operand(&mut bus, &mut regs), (Operand::DirectAddr(0x0010), false));
+    }
+
+    #[test]
+    fn test_block_move() {
+        let mut bus = LongBus::new();
+        bus.load(0x01_8000, &[0x7E, 0x05]);
+        let mut regs = native_regs();
+
+        assert_eq!(AddressingMode::BlockMove.get_operand(&mut bus, &mut regs), (Operand::Banks(0x7E, 0x05), false));
+    }
+}
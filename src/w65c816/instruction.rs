@@ -0,0 +1,851 @@
+use crate::bus::Bus;
+use crate::instruction::{Access, InvalidOperand};
+use super::registers::{Registers, StatusRegister};
+use super::addressing_modes::Operand;
+use super::interrupt;
+use super::{NATIVE_BRK_VECTOR, NATIVE_COP_VECTOR, EMULATION_IRQ_VECTOR, EMULATION_COP_VECTOR};
+
+/// Width of the data an instruction works on.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Width {
+    /// Selected by the M flag.
+    Memory,
+    /// Selected by the X flag.
+    Index,
+    /// Fixed, whatever the flags say.
+    Fixed,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Instruction {
+    Adc,
+    And,
+    Asl,
+    Bcc,
+    Bcs,
+    Beq,
+    Bit,
+    Bmi,
+    Bne,
+    Bpl,
+    Bra,
+    Brk,
+    /// Branch always, with a 16 bit offset.
+    Brl,
+    Bvc,
+    Bvs,
+    Clc,
+    Cld,
+    Cli,
+    Clv,
+    Cmp,
+    /// Co-processor software interrupt.
+    Cop,
+    Cpx,
+    Cpy,
+    Dec,
+    Dex,
+    Dey,
+    Eor,
+    Inc,
+    Inx,
+    Iny,
+    /// Jump to a 24 bit address.
+    Jml,
+    Jmp,
+    /// Jump to a subroutine at a 24 bit address, pushing the program bank.
+    Jsl,
+    Jsr,
+    Lda,
+    Ldx,
+    Ldy,
+    Lsr,
+    /// Block move, incrementing the addresses.
+    Mvn,
+    /// Block move, decrementing the addresses.
+    Mvp,
+    Nop,
+    Ora,
+    /// Pushes a 16 bit immediate.
+    Pea,
+    /// Pushes the 16 bit word at a direct page address.
+    Pei,
+    /// Pushes a 16 bit address relative to the program counter.
+    Per,
+    Pha,
+    Phb,
+    Phd,
+    Phk,
+    Php,
+    Phx,
+    Phy,
+    Pla,
+    Plb,
+    Pld,
+    Plp,
+    Plx,
+    Ply,
+    /// Resets status bits.
+    Rep,
+    Rol,
+    Ror,
+    Rti,
+    /// Returns from a subroutine called with JSL.
+    Rtl,
+    Rts,
+    Sbc,
+    Sec,
+    Sed,
+    Sei,
+    /// Sets status bits.
+    Sep,
+    Sta,
+    Stp,
+    Stx,
+    Sty,
+    Stz,
+    Tax,
+    Tay,
+    Tcd,
+    Tcs,
+    Tdc,
+    Trb,
+    Tsb,
+    Tsc,
+    Tsx,
+    Txa,
+    Txs,
+    Txy,
+    Tya,
+    Tyx,
+    Wai,
+    /// Reserved for future expansion, a two byte NOP.
+    Wdm,
+    /// Exchanges the two bytes of the accumulator.
+    Xba,
+    /// Exchanges the carry and emulation flags.
+    Xce,
+}
+
+impl Instruction {
+    ///
+    /// Executes the instruction with the given operand. Returns the number of
+    /// cycles spent on top of the base cycle count of the opcode, for taken
+    /// branches and for interrupts and returns in native mode.
+    ///
+    pub fn process<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers) -> Result<u8, InvalidOperand> {
+        let byte = self.is_8bit(regs);
+        match self {
+            // Logical operations
+            Instruction::And => {
+                let value = regs.a() & self.read_operand(operand, bus, byte)?;
+                regs.set_a(value);
+                self.set_nz(&mut regs.status_reg, value, byte);
+            },
+            Instruction::Ora => {
+                let value = regs.a() | self.read_operand(operand, bus, byte)?;
+                regs.set_a(value);
+                self.set_nz(&mut regs.status_reg, value, byte);
+            },
+            Instruction::Eor => {
+                let value = regs.a() ^ self.read_operand(operand, bus, byte)?;
+                regs.set_a(value);
+                self.set_nz(&mut regs.status_reg, value, byte);
+            },
+            Instruction::Bit => self.bit(operand, bus, regs, byte)?,
+
+            // Comparison operations
+            Instruction::Cmp => self.compare(operand, bus, regs, regs.a(), byte)?,
+            Instruction::Cpx => self.compare(operand, bus, regs, regs.x_index, byte)?,
+            Instruction::Cpy => self.compare(operand, bus, regs, regs.y_index, byte)?,
+
+            // Arithmetic operations
+            Instruction::Adc => {
+                let value = self.read_operand(operand, bus, byte)?;
+                self.add_with_carry(value, regs, byte);
+            },
+            Instruction::Sbc => {
+                let value = self.read_operand(operand, bus, byte)?;
+                self.subtract_with_carry(value, regs, byte);
+            },
+
+            // Increment/Decrement operations
+            Instruction::Inc => self.read_modify_write(operand, bus, regs, byte, |value, regs| {
+                let value = value.wrapping_add(1);
+                Instruction::Inc.set_nz(&mut regs.status_reg, value, byte);
+                value
+            })?,
+            Instruction::Dec => self.read_modify_write(operand, bus, regs, byte, |value, regs| {
+                let value = value.wrapping_sub(1);
+                Instruction::Dec.set_nz(&mut regs.status_reg, value, byte);
+                value
+            })?,
+            Instruction::Inx => {
+                regs.set_x(regs.x_index.wrapping_add(1));
+                self.set_nz(&mut regs.status_reg, regs.x_index, byte);
+            },
+            Instruction::Iny => {
+                regs.set_y(regs.y_index.wrapping_add(1));
+                self.set_nz(&mut regs.status_reg, regs.y_index, byte);
+            },
+            Instruction::Dex => {
+                regs.set_x(regs.x_index.wrapping_sub(1));
+                self.set_nz(&mut regs.status_reg, regs.x_index, byte);
+            },
+            Instruction::Dey => {
+                regs.set_y(regs.y_index.wrapping_sub(1));
+                self.set_nz(&mut regs.status_reg, regs.y_index, byte);
+            },
+
+            // Data shifting instructions
+            Instruction::Asl => self.read_modify_write(operand, bus, regs, byte, |value, regs| {
+                regs.status_reg.carry = (value & sign_bit(byte)) != 0;
+                let value = value << 1;
+                Instruction::Asl.set_nz(&mut regs.status_reg, value, byte);
+                value
+            })?,
+            Instruction::Lsr => self.read_modify_write(operand, bus, regs, byte, |value, regs| {
+                regs.status_reg.carry = (value & 0x0001) != 0;
+                let value = value >> 1;
+                Instruction::Lsr.set_nz(&mut regs.status_reg, value, byte);
+                value
+            })?,
+            Instruction::Rol => self.read_modify_write(operand, bus, regs, byte, |value, regs| {
+                let carry = regs.status_reg.carry as u16;
+                regs.status_reg.carry = (value & sign_bit(byte)) != 0;
+                let value = (value << 1) | carry;
+                Instruction::Rol.set_nz(&mut regs.status_reg, value, byte);
+                value
+            })?,
+            Instruction::Ror => self.read_modify_write(operand, bus, regs, byte, |value, regs| {
+                let carry = if regs.status_reg.carry { sign_bit(byte) } else { 0 };
+                regs.status_reg.carry = (value & 0x0001) != 0;
+                let value = (value >> 1) | carry;
+                Instruction::Ror.set_nz(&mut regs.status_reg, value, byte);
+                value
+            })?,
+            Instruction::Tsb => self.read_modify_write(operand, bus, regs, byte, |value, regs| {
+                regs.status_reg.zero = (value & regs.a()) == 0;
+                value | regs.a()
+            })?,
+            Instruction::Trb => self.read_modify_write(operand, bus, regs, byte, |value, regs| {
+                regs.status_reg.zero = (value & regs.a()) == 0;
+                value & !regs.a()
+            })?,
+
+            // Status flag operations
+            Instruction::Clc => regs.status_reg.carry = false,
+            Instruction::Cld => regs.status_reg.decimal_mode = false,
+            Instruction::Cli => regs.status_reg.irq_disable = false,
+            Instruction::Clv => regs.status_reg.overflow = false,
+            Instruction::Sec => regs.status_reg.carry = true,
+            Instruction::Sed => regs.status_reg.decimal_mode = true,
+            Instruction::Sei => regs.status_reg.irq_disable = true,
+            Instruction::Rep => {
+                let mask = self.read_operand(operand, bus, true)? as u8;
+                let status = regs.status_reg.get() & !mask;
+                regs.set_status(status);
+            },
+            Instruction::Sep => {
+                let mask = self.read_operand(operand, bus, true)? as u8;
+                let status = regs.status_reg.get() | mask;
+                regs.set_status(status);
+            },
+            Instruction::Xce => {
+                let carry = regs.status_reg.carry;
+                regs.status_reg.carry = regs.emulation;
+                regs.set_emulation(carry);
+            },
+
+            // Branch instructions
+            Instruction::Bcs => return self.branch(regs.status_reg.carry, operand, regs),
+            Instruction::Bcc => return self.branch(!regs.status_reg.carry, operand, regs),
+            Instruction::Beq => return self.branch(regs.status_reg.zero, operand, regs),
+            Instruction::Bne => return self.branch(!regs.status_reg.zero, operand, regs),
+            Instruction::Bmi => return self.branch(regs.status_reg.negative, operand, regs),
+            Instruction::Bpl => return self.branch(!regs.status_reg.negative, operand, regs),
+            Instruction::Bvs => return self.branch(regs.status_reg.overflow, operand, regs),
+            Instruction::Bvc => return self.branch(!regs.status_reg.overflow, operand, regs),
+            Instruction::Bra => return self.branch(true, operand, regs),
+            Instruction::Brl => regs.program_counter = self.address(operand)? as u16,
+
+            // Jump and subroutine instructions
+            Instruction::Jmp => regs.program_counter = self.address(operand)? as u16,
+            Instruction::Jml => {
+                let addr = self.address(operand)?;
+                regs.program_bank = (addr >> 16) as u8;
+                regs.program_counter = addr as u16;
+            },
+            Instruction::Jsr => {
+                let addr = self.address(operand)?;
+                // The address of the last byte of the instruction is pushed.
+                regs.push_word(regs.program_counter.wrapping_sub(1), bus);
+                regs.program_counter = addr as u16;
+            },
+            Instruction::Jsl => {
+                let addr = self.address(operand)?;
+                regs.push(regs.program_bank, bus);
+                regs.push_word(regs.program_counter.wrapping_sub(1), bus);
+                regs.program_bank = (addr >> 16) as u8;
+                regs.program_counter = addr as u16;
+            },
+            Instruction::Rts => {
+                regs.program_counter = regs.pop_word(bus).wrapping_add(1);
+            },
+            Instruction::Rtl => {
+                regs.program_counter = regs.pop_word(bus).wrapping_add(1);
+                regs.program_bank = regs.pop(bus);
+            },
+
+            // Interrupt instructions
+            Instruction::Brk | Instruction::Cop => {
+                let native = !regs.emulation;
+                if *self == Instruction::Brk {
+                    interrupt(bus, regs, NATIVE_BRK_VECTOR, EMULATION_IRQ_VECTOR, true);
+                } else {
+                    interrupt(bus, regs, NATIVE_COP_VECTOR, EMULATION_COP_VECTOR, true);
+                }
+                return Ok(native as u8);
+            },
+            Instruction::Rti => {
+                let status = regs.pop(bus);
+                regs.set_status(status);
+                regs.program_counter = regs.pop_word(bus);
+                if !regs.emulation {
+                    regs.program_bank = regs.pop(bus);
+                    return Ok(1);
+                }
+            },
+
+            // Memory transfer operations
+            Instruction::Lda => {
+                let value = self.read_operand(operand, bus, byte)?;
+                regs.set_a(value);
+                self.set_nz(&mut regs.status_reg, value, byte);
+            },
+            Instruction::Ldx => {
+                let value = self.read_operand(operand, bus, byte)?;
+                regs.set_x(value);
+                self.set_nz(&mut regs.status_reg, value, byte);
+            },
+            Instruction::Ldy => {
+                let value = self.read_operand(operand, bus, byte)?;
+                regs.set_y(value);
+                self.set_nz(&mut regs.status_reg, value, byte);
+            },
+            Instruction::Sta => self.write_operand(operand, bus, regs.a(), byte)?,
+            Instruction::Stx => self.write_operand(operand, bus, regs.x_index, byte)?,
+            Instruction::Sty => self.write_operand(operand, bus, regs.y_index, byte)?,
+            Instruction::Stz => self.write_operand(operand, bus, 0, byte)?,
+            Instruction::Mvn | Instruction::Mvp => self.block_move(operand, bus, regs)?,
+
+            // Register data transfer operations
+            Instruction::Tax => self.transfer_to_x(regs.accumulator, regs),
+            Instruction::Tsx => self.transfer_to_x(regs.stack_pointer, regs),
+            Instruction::Tyx => self.transfer_to_x(regs.y_index, regs),
+            Instruction::Tay => {
+                regs.set_y(regs.accumulator);
+                let (value, byte) = (regs.y_index, regs.index_8bit());
+                self.set_nz(&mut regs.status_reg, value, byte);
+            },
+            Instruction::Txy => {
+                regs.set_y(regs.x_index);
+                let (value, byte) = (regs.y_index, regs.index_8bit());
+                self.set_nz(&mut regs.status_reg, value, byte);
+            },
+            Instruction::Txa => {
+                regs.set_a(regs.x_index);
+                let (value, byte) = (regs.a(), regs.accumulator_8bit());
+                self.set_nz(&mut regs.status_reg, value, byte);
+            },
+            Instruction::Tya => {
+                regs.set_a(regs.y_index);
+                let (value, byte) = (regs.a(), regs.accumulator_8bit());
+                self.set_nz(&mut regs.status_reg, value, byte);
+            },
+            // The transfers to the stack pointer leave the flags alone.
+            Instruction::Txs => regs.set_stack_pointer(regs.x_index),
+            Instruction::Tcs => regs.set_stack_pointer(regs.accumulator),
+            Instruction::Tsc => {
+                regs.accumulator = regs.stack_pointer;
+                self.set_nz(&mut regs.status_reg, regs.accumulator, false);
+            },
+            Instruction::Tcd => {
+                regs.direct_page = regs.accumulator;
+                self.set_nz(&mut regs.status_reg, regs.direct_page, false);
+            },
+            Instruction::Tdc => {
+                regs.accumulator = regs.direct_page;
+                self.set_nz(&mut regs.status_reg, regs.accumulator, false);
+            },
+            Instruction::Xba => {
+                regs.accumulator = regs.accumulator.rotate_left(8);
+                self.set_nz(&mut regs.status_reg, regs.accumulator, true);
+            },
+
+            // Stack operations
+            Instruction::Pha => self.push(regs.a(), bus, regs, byte),
+            Instruction::Phx => self.push(regs.x_index, bus, regs, byte),
+            Instruction::Phy => self.push(regs.y_index, bus, regs, byte),
+            Instruction::Php => regs.push(regs.status_reg.get(), bus),
+            Instruction::Phb => regs.push(regs.data_bank, bus),
+            Instruction::Phk => regs.push(regs.program_bank, bus),
+            Instruction::Phd => regs.push_word(regs.direct_page, bus),
+            Instruction::Pla => {
+                let value = self.pull(bus, regs, byte);
+                regs.set_a(value);
+                self.set_nz(&mut regs.status_reg, value, byte);
+            },
+            Instruction::Plx => {
+                let value = self.pull(bus, regs, byte);
+                regs.set_x(value);
+                self.set_nz(&mut regs.status_reg, value, byte);
+            },
+            Instruction::Ply => {
+                let value = self.pull(bus, regs, byte);
+                regs.set_y(value);
+                self.set_nz(&mut regs.status_reg, value, byte);
+            },
+            Instruction::Plp => {
+                let status = regs.pop(bus);
+                regs.set_status(status);
+            },
+            Instruction::Plb => {
+                regs.data_bank = regs.pop(bus);
+                self.set_nz(&mut regs.status_reg, regs.data_bank as u16, true);
+            },
+            Instruction::Pld => {
+                regs.direct_page = regs.pop_word(bus);
+                self.set_nz(&mut regs.status_reg, regs.direct_page, false);
+            },
+            Instruction::Pea | Instruction::Per => {
+                let addr = self.address(operand)?;
+                regs.push_word(addr as u16, bus);
+            },
+            Instruction::Pei => {
+                let value = self.read_operand(operand, bus, false)?;
+                regs.push_word(value, bus);
+            },
+
+            // The Cpu stops after these.
+            Instruction::Wai | Instruction::Stp => {},
+            Instruction::Nop | Instruction::Wdm => {},
+        }
+        Ok(0)
+    }
+
+    ///
+    /// Returns how the instruction accesses the memory pointed by its operand.
+    ///
+    pub fn access(&self) -> Access {
+        match self {
+            Instruction::Adc | Instruction::And | Instruction::Bit | Instruction::Cmp |
+            Instruction::Cpx | Instruction::Cpy | Instruction::Eor | Instruction::Lda |
+            Instruction::Ldx | Instruction::Ldy | Instruction::Ora | Instruction::Sbc => Access::Read,
+            Instruction::Sta | Instruction::Stx | Instruction::Sty | Instruction::Stz => Access::Write,
+            Instruction::Asl | Instruction::Dec | Instruction::Inc | Instruction::Lsr |
+            Instruction::Rol | Instruction::Ror | Instruction::Trb | Instruction::Tsb => Access::ReadModifyWrite,
+            _ => Access::None,
+        }
+    }
+
+    ///
+    /// Returns the flag that selects the width of the data the instruction
+    /// works on.
+    ///
+    pub fn width(&self) -> Width {
+        match self {
+            Instruction::Adc | Instruction::And | Instruction::Asl | Instruction::Bit |
+            Instruction::Cmp | Instruction::Dec | Instruction::Eor | Instruction::Inc |
+            Instruction::Lda | Instruction::Lsr | Instruction::Ora | Instruction::Rol |
+            Instruction::Ror | Instruction::Sbc | Instruction::Sta | Instruction::Stz |
+            Instruction::Trb | Instruction::Tsb | Instruction::Pha | Instruction::Pla => Width::Memory,
+            Instruction::Cpx | Instruction::Cpy | Instruction::Dex | Instruction::Dey |
+            Instruction::Inx | Instruction::Iny | Instruction::Ldx | Instruction::Ldy |
+            Instruction::Stx | Instruction::Sty | Instruction::Phx | Instruction::Phy |
+            Instruction::Plx | Instruction::Ply => Width::Index,
+            _ => Width::Fixed,
+        }
+    }
+
+    ///
+    /// Returns the cycles a 16 bit operation takes on top of the base count:
+    /// one per extra byte transferred, two for read-modify-write instructions
+    /// on memory.
+    ///
+    pub fn wide_cycles(&self, operand: Operand, regs: &Registers) -> u8 {
+        if self.width() == Width::Fixed || self.is_8bit(regs) {
+            return 0;
+        }
+        match operand {
+            Operand::Addr(_) | Operand::DirectAddr(_) if self.access() == Access::ReadModifyWrite => 2,
+            Operand::Addr(_) | Operand::DirectAddr(_) | Operand::Value(_) => 1,
+            _ => match self {
+                Instruction::Pha | Instruction::Pla | Instruction::Phx |
+                Instruction::Phy | Instruction::Plx | Instruction::Ply => 1,
+                _ => 0,
+            },
+        }
+    }
+
+    fn is_8bit(&self, regs: &Registers) -> bool {
+        match self.width() {
+            Width::Memory => regs.accumulator_8bit(),
+            Width::Index => regs.index_8bit(),
+            Width::Fixed => true,
+        }
+    }
+
+    fn set_nz(&self, status_reg: &mut StatusRegister, value: u16, byte: bool) {
+        status_reg.zero = (value & width_mask(byte)) == 0;
+        status_reg.negative = (value & sign_bit(byte)) != 0;
+    }
+
+    fn address(&self, operand: Operand) -> Result<u32, InvalidOperand> {
+        match operand {
+            Operand::Addr(addr) => Ok(addr),
+            _ => Err(InvalidOperand),
+        }
+    }
+
+    ///
+    /// Returns an immediate operand or reads it from memory, 8 or 16 bits
+    /// wide. The second byte of direct page and stack operands wraps in
+    /// bank 0.
+    ///
+    fn read_operand<T: Bus>(&self, operand: Operand, bus: &mut T, byte: bool) -> Result<u16, InvalidOperand> {
+        let (lo, hi) = match operand {
+            Operand::Value(value) => return Ok(value & width_mask(byte)),
+            Operand::Addr(addr) => (addr, addr.wrapping_add(1) & 0xFF_FFFF),
+            Operand::DirectAddr(addr) => (addr as u32, addr.wrapping_add(1) as u32),
+            _ => return Err(InvalidOperand),
+        };
+        let mut value = bus.read_long(lo) as u16;
+        if !byte {
+            value |= (bus.read_long(hi) as u16) << 8;
+        }
+        Ok(value)
+    }
+
+    fn write_operand<T: Bus>(&self, operand: Operand, bus: &mut T, value: u16, byte: bool) -> Result<(), InvalidOperand> {
+        let (lo, hi) = match operand {
+            Operand::Addr(addr) => (addr, addr.wrapping_add(1) & 0xFF_FFFF),
+            Operand::DirectAddr(addr) => (addr as u32, addr.wrapping_add(1) as u32),
+            _ => return Err(InvalidOperand),
+        };
+        bus.write_long(lo, value as u8);
+        if !byte {
+            bus.write_long(hi, (value >> 8) as u8);
+        }
+        Ok(())
+    }
+
+    ///
+    /// Runs a read-modify-write operation on memory or on the accumulator.
+    ///
+    fn read_modify_write<T, F>(&self, operand: Operand, bus: &mut T, regs: &mut Registers, byte: bool, op: F) -> Result<(), InvalidOperand>
+        where T: Bus, F: FnOnce(u16, &mut Registers) -> u16 {
+        if operand == Operand::Accumulator {
+            let value = op(regs.a(), regs);
+            regs.set_a(value);
+            return Ok(());
+        }
+        let value = self.read_operand(operand, bus, byte)?;
+        let value = op(value, regs);
+        self.write_operand(operand, bus, value, byte)
+    }
+
+    fn bit<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers, byte: bool) -> Result<(), InvalidOperand> {
+        let value = self.read_operand(operand, bus, byte)?;
+        regs.status_reg.zero = (value & regs.a()) == 0;
+        // The immediate form only changes Z.
+        if let Operand::Value(_) = operand {
+            return Ok(());
+        }
+        regs.status_reg.negative = (value & sign_bit(byte)) != 0;
+        regs.status_reg.overflow = (value & (sign_bit(byte) >> 1)) != 0;
+        Ok(())
+    }
+
+    fn compare<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers, register: u16, byte: bool) -> Result<(), InvalidOperand> {
+        let value = self.read_operand(operand, bus, byte)?;
+        let register = register & width_mask(byte);
+        regs.status_reg.carry = register >= value;
+        self.set_nz(&mut regs.status_reg, register.wrapping_sub(value), byte);
+        Ok(())
+    }
+
+    ///
+    /// ADC on 8 or 16 bits. In decimal mode every nibble is a BCD digit and,
+    /// as on the 65C02, N and Z come from the result while V is taken before
+    /// the most significant digit is adjusted.
+    ///
+    fn add_with_carry(&self, value: u16, regs: &mut Registers, byte: bool) {
+        let acc = regs.a() as u32;
+        let value = value as u32;
+        let sign = sign_bit(byte) as u32;
+        let mut carry = regs.status_reg.carry as u32;
+
+        let result = if regs.status_reg.decimal_mode {
+            let digits = if byte { 2 } else { 4 };
+            let mut result = 0;
+            let mut intermediate = 0;
+            for digit in 0..digits {
+                let shift = digit * 4;
+                let mut sum = ((acc >> shift) & 0x0F) + ((value >> shift) & 0x0F) + carry;
+                intermediate = result | (sum << shift);
+                if sum > 0x09 {
+                    sum += 0x06;
+                }
+                carry = (sum > 0x0F) as u32;
+                result |= (sum & 0x0F) << shift;
+            }
+            regs.status_reg.overflow = ((acc ^ intermediate) & (value ^ intermediate) & sign) != 0;
+            result
+        } else {
+            let sum = acc + value + carry;
+            regs.status_reg.overflow = ((acc ^ sum) & (value ^ sum) & sign) != 0;
+            carry = (sum > width_mask(byte) as u32) as u32;
+            sum & width_mask(byte) as u32
+        };
+
+        regs.status_reg.carry = carry != 0;
+        regs.set_a(result as u16);
+        self.set_nz(&mut regs.status_reg, result as u16, byte);
+    }
+
+    ///
+    /// SBC on 8 or 16 bits. C and V come from the binary subtraction, in
+    /// decimal mode every nibble of the result is then a BCD digit.
+    ///
+    fn subtract_with_carry(&self, value: u16, regs: &mut Registers, byte: bool) {
+        let acc = regs.a() as i32;
+        let decimal = regs.status_reg.decimal_mode;
+        let mut borrow = !regs.status_reg.carry as i32;
+
+        let mut result = 0;
+        if decimal {
+            let digits = if byte { 2 } else { 4 };
+            for digit in 0..digits {
+                let shift = digit * 4;
+                let mut diff = ((acc >> shift) & 0x0F) - ((value as i32 >> shift) & 0x0F) - borrow;
+                borrow = (diff < 0) as i32;
+                if diff < 0 {
+                    diff += 10;
+                }
+                result |= (diff & 0x0F) << shift;
+            }
+        }
+
+        // In binary mode, subtracting is adding the one's complement of the argument.
+        regs.status_reg.decimal_mode = false;
+        self.add_with_carry(!value & width_mask(byte), regs, byte);
+        regs.status_reg.decimal_mode = decimal;
+        if decimal {
+            regs.set_a(result as u16);
+            self.set_nz(&mut regs.status_reg, result as u16, byte);
+        }
+    }
+
+    ///
+    /// Takes the branch if the condition holds. Returns the extra cycles
+    /// spent: one for a taken branch and, in emulation mode, another one if
+    /// it lands in a different page.
+    ///
+    fn branch(&self, condition: bool, operand: Operand, regs: &mut Registers) -> Result<u8, InvalidOperand> {
+        let target = self.address(operand)? as u16;
+        if !condition {
+            return Ok(0);
+        }
+        let pc = regs.program_counter;
+        regs.program_counter = target;
+        if regs.emulation && (pc & 0xFF00) != (target & 0xFF00) {
+            Ok(2)
+        } else {
+            Ok(1)
+        }
+    }
+
+    fn transfer_to_x(&self, value: u16, regs: &mut Registers) {
+        regs.set_x(value);
+        let (value, byte) = (regs.x_index, regs.index_8bit());
+        self.set_nz(&mut regs.status_reg, value, byte);
+    }
+
+    fn push<T: Bus>(&self, value: u16, bus: &mut T, regs: &mut Registers, byte: bool) {
+        if byte {
+            regs.push(value as u8, bus);
+        } else {
+            regs.push_word(value, bus);
+        }
+    }
+
+    fn pull<T: Bus>(&self, bus: &mut T, regs: &mut Registers, byte: bool) -> u16 {
+        if byte {
+            regs.pop(bus) as u16
+        } else {
+            regs.pop_word(bus)
+        }
+    }
+
+    ///
+    /// MVN and MVP move a single byte from the source bank at X to the
+    /// destination bank at Y. The instruction runs again until the
+    /// accumulator, which counts the bytes left minus one, wraps to $FFFF.
+    ///
+    fn block_move<T: Bus>(&self, operand: Operand, bus: &mut T, regs: &mut Registers) -> Result<(), InvalidOperand> {
+        if let Operand::Banks(destination, source) = operand {
+            let value = bus.read_long(((source as u32) << 16) | regs.x_index as u32);
+            bus.write_long(((destination as u32) << 16) | regs.y_index as u32, value);
+            regs.data_bank = destination;
+
+            if *self == Instruction::Mvn {
+                regs.set_x(regs.x_index.wrapping_add(1));
+                regs.set_y(regs.y_index.wrapping_add(1));
+            } else {
+                regs.set_x(regs.x_index.wrapping_sub(1));
+                regs.set_y(regs.y_index.wrapping_sub(1));
+            }
+            regs.accumulator = regs.accumulator.wrapping_sub(1);
+            if regs.accumulator != 0xFFFF {
+                regs.program_counter = regs.program_counter.wrapping_sub(3);
+            }
+            Ok(())
+        } else {
+            Err(InvalidOperand)
+        }
+    }
+}
+
+fn width_mask(byte: bool) -> u16 {
+    if byte { 0x00FF } else { 0xFFFF }
+}
+
+fn sign_bit(byte: bool) -> u16 {
+    if byte { 0x0080 } else { 0x8000 }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::w65c816::tests::LongBus;
+
+    fn native_regs() -> Registers {
+        let mut regs = Registers::new();
+        regs.set_emulation(false);
+        regs.set_status(0x00);
+        regs
+    }
+
+    #[test]
+    fn adc_16bit_test() {
+        let mut regs = native_regs();
+        let mut bus = LongBus::new();
+        regs.accumulator = 0x7FFF;
+
+        Instruction::Adc.process(Operand::Value(0x0001), &mut bus, &mut regs).unwrap();
+        assert_eq!(regs.accumulator, 0x8000);
+        assert!(regs.status_reg.overflow);
+        assert!(regs.status_reg.negative);
+        assert!(!regs.status_reg.carry);
+    }
+
+    #[test]
+    fn adc_decimal_16bit_test() {
+        let mut regs = native_regs();
+        let mut bus = LongBus::new();
+        regs.status_reg.decimal_mode = true;
+        regs.accumulator = 0x9999;
+
+        Instruction::Adc.process(Operand::Value(0x0001), &mut bus, &mut regs).unwrap();
+        assert_eq!(regs.accumulator, 0x0000);
+        assert!(regs.status_reg.carry);
+        assert!(regs.status_reg.zero);
+
+        regs.status_reg.carry = true;
+        regs.accumulator = 0x1000;
+        Instruction::Sbc.process(Operand::Value(0x0001), &mut bus, &mut regs).unwrap();
+        assert_eq!(regs.accumulator, 0x0999);
+        assert!(regs.status_reg.carry);
+    }
+
+    #[test]
+    fn sbc_8bit_keeps_high_byte_test() {
+        let mut regs = Registers::new();
+        let mut bus = LongBus::new();
+        regs.accumulator = 0x1250;
+        regs.status_reg.carry = true;
+
+        Instruction::Sbc.process(Operand::Value(0x60), &mut bus, &mut regs).unwrap();
+        assert_eq!(regs.accumulator, 0x12F0);
+        assert!(!regs.status_reg.carry);
+        assert!(regs.status_reg.negative);
+    }
+
+    #[test]
+    fn rmw_16bit_test() {
+        let mut regs = native_regs();
+        let mut bus = LongBus::new();
+        bus.load(0x7E_1000, &[0x00, 0x80]);
+
+        Instruction::Asl.process(Operand::Addr(0x7E_1000), &mut bus, &mut regs).unwrap();
+        assert_eq!(bus.read_long(0x7E_1000), 0x00);
+        assert_eq!(bus.read_long(0x7E_1001), 0x00);
+        assert!(regs.status_reg.carry);
+        assert!(regs.status_reg.zero);
+    }
+
+    #[test]
+    fn rep_sep_test() {
+        let mut regs = Registers::new();
+        let mut bus = LongBus::new();
+
+        // In emulation mode M and X stay set.
+        Instruction::Rep.process(Operand::Value(0x30), &mut bus, &mut regs).unwrap();
+        assert!(regs.accumulator_8bit());
+
+        regs.set_emulation(false);
+        Instruction::Rep.process(Operand::Value(0x30), &mut bus, &mut regs).unwrap();
+        assert!(!regs.accumulator_8bit());
+        assert!(!regs.index_8bit());
+
+        regs.x_index = 0x1234;
+        Instruction::Sep.process(Operand::Value(0x10), &mut bus, &mut regs).unwrap();
+        assert!(regs.index_8bit());
+        assert_eq!(regs.x_index, 0x0034);
+    }
+
+    #[test]
+    fn xba_test() {
+        let mut regs = Registers::new();
+        let mut bus = LongBus::new();
+        regs.accumulator = 0x80FF;
+
+        Instruction::Xba.process(Operand::None, &mut bus, &mut regs).unwrap();
+        assert_eq!(regs.accumulator, 0xFF80);
+        assert!(regs.status_reg.negative);
+    }
+
+    #[test]
+    fn block_move_test() {
+        let mut regs = native_regs();
+        let mut bus = LongBus::new();
+        bus.load(0x01_2000, &[0x11, 0x22, 0x33]);
+        regs.accumulator = 0x0002;
+        regs.x_index = 0x2000;
+        regs.y_index = 0x3000;
+        regs.program_counter = 0x8003;
+
+        // The instruction steps back onto itself until the last byte.
+        for _ in 0..3 {
+            regs.program_counter = 0x8003;
+            Instruction::Mvn.process(Operand::Banks(0x02, 0x01), &mut bus, &mut regs).unwrap();
+        }
+        assert_eq!(bus.read_long(0x02_3000), 0x11);
+        assert_eq!(bus.read_long(0x02_3002), 0x33);
+        assert_eq!(regs.accumulator, 0xFFFF);
+        assert_eq!(regs.x_index, 0x2003);
+        assert_eq!(regs.data_bank, 0x02);
+        assert_eq!(regs.program_counter, 0x8003);
+    }
+}
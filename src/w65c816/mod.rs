@@ -0,0 +1,486 @@
+mod instruction;
+mod addressing_modes;
+mod opcodes;
+mod registers;
+
+use crate::bus::Bus;
+use crate::error::Error;
+use crate::instruction::Access;
+use crate::registers::Flag;
+use self::instruction::Instruction;
+use self::opcodes::OPCODES;
+use self::registers::Registers;
+
+/// Address of the COP vector in native mode.
+const NATIVE_COP_VECTOR: u16 = 0xFFE4;
+/// Address of the BRK vector in native mode.
+const NATIVE_BRK_VECTOR: u16 = 0xFFE6;
+/// Address of the non-maskable interrupt vector in native mode.
+const NATIVE_NMI_VECTOR: u16 = 0xFFEA;
+/// Address of the interrupt request vector in native mode.
+const NATIVE_IRQ_VECTOR: u16 = 0xFFEE;
+/// Address of the COP vector in emulation mode.
+const EMULATION_COP_VECTOR: u16 = 0xFFF4;
+/// Address of the non-maskable interrupt vector in emulation mode.
+const EMULATION_NMI_VECTOR: u16 = 0xFFFA;
+/// Address of the reset vector, which always starts in emulation mode.
+const RESET_VECTOR: u16 = 0xFFFC;
+/// Address of the interrupt request and BRK vector in emulation mode.
+const EMULATION_IRQ_VECTOR: u16 = 0xFFFE;
+/// Number of cycles taken by the reset and interrupt sequences in
+/// emulation mode. Native mode takes one more to push the program bank.
+const INTERRUPT_CYCLES: u8 = 7;
+
+/// WDC 65816 processor emulator, the 16 bit successor of the 65C02.
+///
+/// The processor starts in emulation mode, where it runs 65C02 code, and
+/// switches to native mode with `CLC; XCE`. Its 24 bit addresses reach the
+/// bus through `Bus::read_long` and `Bus::write_long`.
+pub struct Cpu65816 {
+    registers: Registers,
+    cycles: u64,
+    waiting: bool,
+    stopped: bool,
+}
+
+impl Cpu65816 {
+    ///
+    /// Constructs a Cpu65816 struct, in emulation mode.
+    ///
+    pub fn new() -> Cpu65816 {
+        Cpu65816 {
+            registers: Registers::new(),
+            cycles: 0,
+            waiting: false,
+            stopped: false,
+        }
+    }
+
+    ///
+    /// Returns the number of cycles elapsed since the last reset.
+    ///
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    ///
+    /// Returns true while a WAI instruction is waiting for an interrupt.
+    ///
+    pub fn is_waiting(&self) -> bool {
+        self.waiting
+    }
+
+    ///
+    /// Returns true in emulation mode, false in native mode.
+    ///
+    pub fn is_emulation(&self) -> bool {
+        self.registers.emulation
+    }
+
+    /// Returns true if the accumulator and memory accesses are 8 bits wide.
+    pub fn is_accumulator_8bit(&self) -> bool {
+        self.registers.accumulator_8bit()
+    }
+
+    /// Returns true if the index registers are 8 bits wide.
+    pub fn is_index_8bit(&self) -> bool {
+        self.registers.index_8bit()
+    }
+
+    /// Returns the full 16 bit accumulator (C), whatever the width of A.
+    pub fn accumulator(&self) -> u16 {
+        self.registers.accumulator
+    }
+
+    /// Sets the full 16 bit accumulator (C).
+    pub fn set_accumulator(&mut self, value: u16) {
+        self.registers.accumulator = value;
+    }
+
+    /// Returns the value of the X index register.
+    pub fn x_index(&self) -> u16 {
+        self.registers.x_index
+    }
+
+    /// Sets the X index register, truncated to 8 bits if X is set.
+    pub fn set_x_index(&mut self, value: u16) {
+        self.registers.set_x(value);
+    }
+
+    /// Returns the value of the Y index register.
+    pub fn y_index(&self) -> u16 {
+        self.registers.y_index
+    }
+
+    /// Sets the Y index register, truncated to 8 bits if X is set.
+    pub fn set_y_index(&mut self, value: u16) {
+        self.registers.set_y(value);
+    }
+
+    /// Returns the stack pointer.
+    pub fn stack_pointer(&self) -> u16 {
+        self.registers.stack_pointer
+    }
+
+    /// Sets the stack pointer, which stays in page 1 in emulation mode.
+    pub fn set_stack_pointer(&mut self, value: u16) {
+        self.registers.set_stack_pointer(value);
+    }
+
+    /// Returns the direct page register (D).
+    pub fn direct_page(&self) -> u16 {
+        self.registers.direct_page
+    }
+
+    /// Sets the direct page register (D).
+    pub fn set_direct_page(&mut self, value: u16) {
+        self.registers.direct_page = value;
+    }
+
+    /// Returns the data bank register (DBR).
+    pub fn data_bank(&self) -> u8 {
+        self.registers.data_bank
+    }
+
+    /// Sets the data bank register (DBR).
+    pub fn set_data_bank(&mut self, value: u8) {
+        self.registers.data_bank = value;
+    }
+
+    /// Returns the program bank register (PBR).
+    pub fn program_bank(&self) -> u8 {
+        self.registers.program_bank
+    }
+
+    /// Sets the program bank register (PBR).
+    pub fn set_program_bank(&mut self, value: u8) {
+        self.registers.program_bank = value;
+    }
+
+    /// Returns the address of the next instruction in the program bank.
+    pub fn program_counter(&self) -> u16 {
+        self.registers.program_counter
+    }
+
+    /// Sets the address of the next instruction in the program bank.
+    pub fn set_program_counter(&mut self, value: u16) {
+        self.registers.program_counter = value;
+    }
+
+    ///
+    /// Returns the packed processor status register (P). Bits 4 and 5 are
+    /// the X and M flags, always set in emulation mode.
+    ///
+    pub fn status(&self) -> u8 {
+        self.registers.status_reg.get()
+    }
+
+    ///
+    /// Sets the processor status register from its packed form. In
+    /// emulation mode, M and X stay set.
+    ///
+    pub fn set_status(&mut self, value: u8) {
+        self.registers.set_status(value);
+    }
+
+    /// Returns the value of a single flag of the status register.
+    pub fn flag(&self, flag: Flag) -> bool {
+        self.registers.status_reg.flag(flag)
+    }
+
+    /// Sets the value of a single flag of the status register.
+    pub fn set_flag(&mut self, flag: Flag, value: bool) {
+        self.registers.status_reg.set_flag(flag, value);
+    }
+
+    ///
+    /// Resets the processor into emulation mode, fetching the reset handler
+    /// from bank 0 and jumping to it.
+    ///
+    pub fn reset<T: Bus>(&mut self, bus: &mut T) {
+        *self = Cpu65816::new();
+        self.registers.program_counter = read_vector(bus, RESET_VECTOR);
+        self.cycles = INTERRUPT_CYCLES as u64;
+    }
+
+    ///
+    /// Runs a single instruction of the processor and returns the number of
+    /// cycles it took.
+    ///
+    /// MVN and MVP move a single byte per step and run again until the
+    /// whole block is moved. After a STP instruction, every step fails until
+    /// the processor is reset; after a WAI instruction, every step takes a
+    /// single cycle until an interrupt is signalled.
+    ///
+    /// Pending interrupts are checked before fetching the next opcode, as
+    /// on the 6502 `Cpu`.
+    ///
+    pub fn single_step<T: Bus>(&mut self, bus: &mut T) -> Result<u8, Error> {
+        if self.stopped {
+            return Err(Error::Stopped { addr: self.registers.program_counter });
+        }
+
+        if self.waiting {
+            if !self.registers.nmi_active && !self.registers.irq_active {
+                return Ok(self.count_cycles(1));
+            }
+            self.waiting = false;
+        }
+
+        let native = !self.registers.emulation as u8;
+        if self.registers.nmi_active {
+            // NMI is edge triggered, servicing it acknowledges the request.
+            self.registers.nmi_active = false;
+            interrupt(bus, &mut self.registers, NATIVE_NMI_VECTOR, EMULATION_NMI_VECTOR, false);
+            return Ok(self.count_cycles(INTERRUPT_CYCLES + native));
+        }
+
+        // IRQ is level triggered, it stays active until the device releases the line.
+        if self.registers.irq_active && !self.registers.status_reg.irq_disable {
+            interrupt(bus, &mut self.registers, NATIVE_IRQ_VECTOR, EMULATION_IRQ_VECTOR, false);
+            return Ok(self.count_cycles(INTERRUPT_CYCLES + native));
+        }
+
+        // Fetch opcode
+        let addr = self.registers.program_counter;
+        let opcode = bus.read_long(self.registers.program_address());
+        self.registers.program_counter = addr.wrapping_add(1);
+        let (instruction, addressing_mode, cycles) = OPCODES[opcode as usize];
+
+        let (operand, index_cycle) = addressing_mode.get_operand(bus, &mut self.registers);
+        let mut cycles = cycles + instruction.wide_cycles(operand, &self.registers);
+        if addressing_mode.is_direct() && (self.registers.direct_page & 0x00FF) != 0 {
+            // Direct page accesses take a cycle more when D is not page aligned.
+            cycles += 1;
+        }
+        if index_cycle && instruction.access() == Access::Read {
+            cycles += 1;
+        }
+        cycles += instruction.process(operand, bus, &mut self.registers)
+            .map_err(|_| Error::InvalidOperand { addr, opcode })?;
+
+        match instruction {
+            Instruction::Wai => self.waiting = true,
+            Instruction::Stp => self.stopped = true,
+            _ => {},
+        }
+        Ok(self.count_cycles(cycles))
+    }
+
+    /// Signals an interrupt (IRQB signal) to the core.
+    pub fn signal_irq(&mut self) {
+        self.registers.irq_active = true;
+    }
+
+    /// Releases the IRQB signal previously raised with `signal_irq`.
+    pub fn clear_irq(&mut self) {
+        self.registers.irq_active = false;
+    }
+
+    /// Signals a NMI Interrupt to the core.
+    pub fn signal_nmi(&mut self) {
+        self.registers.nmi_active = true;
+    }
+
+    ///
+    /// Adds the cycles spent in the last step to the running counter.
+    ///
+    fn count_cycles(&mut self, cycles: u8) -> u8 {
+        self.cycles += cycles as u64;
+        cycles
+    }
+}
+
+impl Default for Cpu65816 {
+    fn default() -> Self {
+        Cpu65816::new()
+    }
+}
+
+///
+/// Pushes the return address and the status register and jumps to the
+/// handler stored in the vector of the current mode. Native mode also
+/// pushes the program bank; emulation mode pushes B set only for BRK.
+///
+fn interrupt<T: Bus>(bus: &mut T, regs: &mut Registers, native_vector: u16, emulation_vector: u16, software: bool) {
+    let vector = if regs.emulation {
+        emulation_vector
+    } else {
+        regs.push(regs.program_bank, bus);
+        native_vector
+    };
+    regs.push_word(regs.program_counter, bus);
+
+    // In emulation mode bit 4 reads as set, and stands for B on the stack.
+    let mut status = regs.status_reg.get();
+    if regs.emulation && !software {
+        status &= !0x10;
+    }
+    regs.push(status, bus);
+
+    regs.status_reg.irq_disable = true;
+    regs.status_reg.decimal_mode = false;
+    regs.program_bank = 0;
+    regs.program_counter = read_vector(bus, vector);
+}
+
+fn read_vector<T: Bus>(bus: &mut T, vector: u16) -> u16 {
+    let low_byte = bus.read_long(vector as u32) as u16;
+    let high_byte = bus.read_long(vector.wrapping_add(1) as u32) as u16;
+    low_byte | (high_byte << 8)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// Bus with the full 16MB address space of the 65816 as RAM.
+    pub struct LongBus {
+        mem: Vec<u8>,
+    }
+
+    impl LongBus {
+        pub fn new() -> LongBus {
+            LongBus {
+                mem: vec![0u8; 0x100_0000],
+            }
+        }
+
+        pub fn load(&mut self, addr: u32, bytes: &[u8]) {
+            let start = addr as usize;
+            self.mem[start..start + bytes.len()].copy_from_slice(bytes);
+        }
+    }
+
+    impl Bus for LongBus {
+        fn read(&mut self, addr: u16) -> u8 {
+            self.mem[addr as usize]
+        }
+
+        fn write(&mut self, addr: u16, value: u8) {
+            self.mem[addr as usize] = value;
+        }
+
+        fn read_long(&mut self, addr: u32) -> u8 {
+            self.mem[(addr & 0xFF_FFFF) as usize]
+        }
+
+        fn write_long(&mut self, addr: u32, value: u8) {
+            self.mem[(addr & 0xFF_FFFF) as usize] = value;
+        }
+    }
+
+    fn start(program: &[u8]) -> (Cpu65816, LongBus) {
+        let mut bus = LongBus::new();
+        bus.load(0x8000, program);
+        bus.load(RESET_VECTOR as u32, &[0x00, 0x80]);
+        let mut cpu = Cpu65816::new();
+        cpu.reset(&mut bus);
+        (cpu, bus)
+    }
+
+    fn run(cpu: &mut Cpu65816, bus: &mut LongBus, steps: usize) {
+        for _ in 0..steps {
+            cpu.single_step(bus).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_emulation_mode_program() {
+        // LDA #$12; ADC #$34; STA $10; LDX #$FF; INX
+        let (mut cpu, mut bus) = start(&[0xA9, 0x12, 0x69, 0x34, 0x85, 0x10, 0xA2, 0xFF, 0xE8]);
+        assert!(cpu.is_emulation());
+        run(&mut cpu, &mut bus, 5);
+
+        assert_eq!(bus.read_long(0x10), 0x46);
+        assert_eq!(cpu.x_index(), 0x00);
+        assert!(cpu.flag(Flag::Zero));
+        assert_eq!(cpu.cycles(), 7 + 2 + 2 + 3 + 2 + 2);
+    }
+
+    #[test]
+    fn test_native_mode_program() {
+        // CLC; XCE; REP #$30; LDA #$1234; LDX #$ABCD; STA $7E0000,X
+        let (mut cpu, mut bus) = start(&[
+            0x18, 0xFB, 0xC2, 0x30, 0xA9, 0x34, 0x12, 0xA2, 0xCD, 0xAB, 0x9F, 0x00, 0x00, 0x7E,
+        ]);
+        run(&mut cpu, &mut bus, 3);
+        assert!(!cpu.is_emulation());
+        assert!(cpu.flag(Flag::Carry));
+        assert!(!cpu.is_accumulator_8bit());
+
+        let cycles = cpu.cycles();
+        run(&mut cpu, &mut bus, 3);
+        assert_eq!(cpu.accumulator(), 0x1234);
+        assert_eq!(cpu.x_index(), 0xABCD);
+        assert_eq!(bus.read_long(0x7E_ABCD), 0x34);
+        assert_eq!(bus.read_long(0x7E_ABCE), 0x12);
+        assert_eq!(cpu.cycles() - cycles, 3 + 3 + 6);
+    }
+
+    #[test]
+    fn test_long_subroutine() {
+        // CLC; XCE; JSL $018000; NOP
+        let (mut cpu, mut bus) = start(&[0x18, 0xFB, 0x22, 0x00, 0x80, 0x01, 0xEA]);
+        // PHK; PLB; RTL
+        bus.load(0x01_8000, &[0x4B, 0xAB, 0x6B]);
+        run(&mut cpu, &mut bus, 3);
+        assert_eq!(cpu.program_bank(), 0x01);
+        assert_eq!(cpu.program_counter(), 0x8000);
+
+        run(&mut cpu, &mut bus, 3);
+        assert_eq!(cpu.data_bank(), 0x01);
+        assert_eq!(cpu.program_bank(), 0x00);
+        assert_eq!(cpu.program_counter(), 0x8006);
+        assert_eq!(cpu.stack_pointer(), 0x01FF);
+    }
+
+    #[test]
+    fn test_native_interrupts() {
+        // CLC; XCE; JML $028000
+        let (mut cpu, mut bus) = start(&[0x18, 0xFB, 0x5C, 0x00, 0x80, 0x02]);
+        bus.load(NATIVE_IRQ_VECTOR as u32, &[0x00, 0x90]);
+        bus.load(0x9000, &[0x40]);
+        run(&mut cpu, &mut bus, 3);
+        cpu.set_flag(Flag::IrqDisable, false);
+
+        cpu.signal_irq();
+        assert_eq!(cpu.single_step(&mut bus), Ok(8));
+        cpu.clear_irq();
+        assert_eq!(cpu.program_bank(), 0x00);
+        assert_eq!(cpu.program_counter(), 0x9000);
+        assert_eq!(bus.read_long(0x01FF), 0x02);
+
+        // RTI pulls the program bank back.
+        assert_eq!(cpu.single_step(&mut bus), Ok(7));
+        assert_eq!(cpu.program_bank(), 0x02);
+        assert_eq!(cpu.program_counter(), 0x8000);
+    }
+
+    #[test]
+    fn test_block_move() {
+        // CLC; XCE; REP #$30; LDA #$0003; LDX #$1000; LDY #$2000; MVN $00,$01
+        let (mut cpu, mut bus) = start(&[
+            0x18, 0xFB, 0xC2, 0x30, 0xA9, 0x03, 0x00, 0xA2, 0x00, 0x10, 0xA0, 0x00, 0x20, 0x54, 0x00, 0x01,
+        ]);
+        bus.load(0x01_1000, &[1, 2, 3, 4]);
+        run(&mut cpu, &mut bus, 6);
+
+        while cpu.program_counter() == 0x800D {
+            assert_eq!(cpu.single_step(&mut bus), Ok(7));
+        }
+        assert_eq!(cpu.program_counter(), 0x8010);
+        assert_eq!(cpu.accumulator(), 0xFFFF);
+        assert_eq!(bus.read_long(0x2000), 1);
+        assert_eq!(bus.read_long(0x2003), 4);
+        assert_eq!(cpu.x_index(), 0x1004);
+    }
+
+    #[test]
+    fn test_stp() {
+        // STP
+        let (mut cpu, mut bus) = start(&[0xDB]);
+        assert_eq!(cpu.single_step(&mut bus), Ok(3));
+        assert_eq!(cpu.single_step(&mut bus), Err(Error::Stopped { addr: 0x8001 }));
+    }
+}
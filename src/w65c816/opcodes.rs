@@ -0,0 +1,264 @@
+
+use super::instruction::Instruction;
+use super::addressing_modes::AddressingMode;
+
+/// Opcode table of the WDC 65816: instruction, addressing mode and base
+/// cycle count, which assumes 8 bit registers and a page aligned direct page.
+pub static OPCODES : [(Instruction, AddressingMode, u8); 256 ] = [
+    (Instruction::Brk, AddressingMode::ImmediateByte, 7),                 // 0x00
+    (Instruction::Ora, AddressingMode::XIndexedIndirect, 6),              // 0x01
+    (Instruction::Cop, AddressingMode::ImmediateByte, 7),                 // 0x02
+    (Instruction::Ora, AddressingMode::StackRelative, 4),                 // 0x03
+    (Instruction::Tsb, AddressingMode::Direct, 5),                        // 0x04
+    (Instruction::Ora, AddressingMode::Direct, 3),                        // 0x05
+    (Instruction::Asl, AddressingMode::Direct, 5),                        // 0x06
+    (Instruction::Ora, AddressingMode::DirectIndirectLong, 6),            // 0x07
+    (Instruction::Php, AddressingMode::Implied, 3),                       // 0x08
+    (Instruction::Ora, AddressingMode::Immediate, 2),                     // 0x09
+    (Instruction::Asl, AddressingMode::Accumulator, 2),                   // 0x0A
+    (Instruction::Phd, AddressingMode::Implied, 4),                       // 0x0B
+    (Instruction::Tsb, AddressingMode::Absolute, 6),                      // 0x0C
+    (Instruction::Ora, AddressingMode::Absolute, 4),                      // 0x0D
+    (Instruction::Asl, AddressingMode::Absolute, 6),                      // 0x0E
+    (Instruction::Ora, AddressingMode::AbsoluteLong, 5),                  // 0x0F
+    (Instruction::Bpl, AddressingMode::Relative, 2),                      // 0x10
+    (Instruction::Ora, AddressingMode::IndirectYIndexed, 5),              // 0x11
+    (Instruction::Ora, AddressingMode::DirectIndirect, 5),                // 0x12
+    (Instruction::Ora, AddressingMode::StackRelativeIndirectYIndexed, 7), // 0x13
+    (Instruction::Trb, AddressingMode::Direct, 5),                        // 0x14
+    (Instruction::Ora, AddressingMode::DirectXIndexed, 4),                // 0x15
+    (Instruction::Asl, AddressingMode::DirectXIndexed, 6),                // 0x16
+    (Instruction::Ora, AddressingMode::IndirectLongYIndexed, 6),          // 0x17
+    (Instruction::Clc, AddressingMode::Implied, 2),                       // 0x18
+    (Instruction::Ora, AddressingMode::AbsoluteYIndexed, 4),              // 0x19
+    (Instruction::Inc, AddressingMode::Accumulator, 2),                   // 0x1A
+    (Instruction::Tcs, AddressingMode::Implied, 2),                       // 0x1B
+    (Instruction::Trb, AddressingMode::Absolute, 6),                      // 0x1C
+    (Instruction::Ora, AddressingMode::AbsoluteXIndexed, 4),              // 0x1D
+    (Instruction::Asl, AddressingMode::AbsoluteXIndexed, 7),              // 0x1E
+    (Instruction::Ora, AddressingMode::AbsoluteLongXIndexed, 5),          // 0x1F
+    (Instruction::Jsr, AddressingMode::Absolute, 6),                      // 0x20
+    (Instruction::And, AddressingMode::XIndexedIndirect, 6),              // 0x21
+    (Instruction::Jsl, AddressingMode::AbsoluteLong, 8),                  // 0x22
+    (Instruction::And, AddressingMode::StackRelative, 4),                 // 0x23
+    (Instruction::Bit, AddressingMode::Direct, 3),                        // 0x24
+    (Instruction::And, AddressingMode::Direct, 3),                        // 0x25
+    (Instruction::Rol, AddressingMode::Direct, 5),                        // 0x26
+    (Instruction::And, AddressingMode::DirectIndirectLong, 6),            // 0x27
+    (Instruction::Plp, AddressingMode::Implied, 4),                       // 0x28
+    (Instruction::And, AddressingMode::Immediate, 2),                     // 0x29
+    (Instruction::Rol, AddressingMode::Accumulator, 2),                   // 0x2A
+    (Instruction::Pld, AddressingMode::Implied, 5),                       // 0x2B
+    (Instruction::Bit, AddressingMode::Absolute, 4),                      // 0x2C
+    (Instruction::And, AddressingMode::Absolute, 4),                      // 0x2D
+    (Instruction::Rol, AddressingMode::Absolute, 6),                      // 0x2E
+    (Instruction::And, AddressingMode::AbsoluteLong, 5),                  // 0x2F
+    (Instruction::Bmi, AddressingMode::Relative, 2),                      // 0x30
+    (Instruction::And, AddressingMode::IndirectYIndexed, 5),              // 0x31
+    (Instruction::And, AddressingMode::DirectIndirect, 5),                // 0x32
+    (Instruction::And, AddressingMode::StackRelativeIndirectYIndexed, 7), // 0x33
+    (Instruction::Bit, AddressingMode::DirectXIndexed, 4),                // 0x34
+    (Instruction::And, AddressingMode::DirectXIndexed, 4),                // 0x35
+    (Instruction::Rol, AddressingMode::DirectXIndexed, 6),                // 0x36
+    (Instruction::And, AddressingMode::IndirectLongYIndexed, 6),          // 0x37
+    (Instruction::Sec, AddressingMode::Implied, 2),                       // 0x38
+    (Instruction::And, AddressingMode::AbsoluteYIndexed, 4),              // 0x39
+    (Instruction::Dec, AddressingMode::Accumulator, 2),                   // 0x3A
+    (Instruction::Tsc, AddressingMode::Implied, 2),                       // 0x3B
+    (Instruction::Bit, AddressingMode::AbsoluteXIndexed, 4),              // 0x3C
+    (Instruction::And, AddressingMode::AbsoluteXIndexed, 4),              // 0x3D
+    (Instruction::Rol, AddressingMode::AbsoluteXIndexed, 7),              // 0x3E
+    (Instruction::And, AddressingMode::AbsoluteLongXIndexed, 5),          // 0x3F
+    (Instruction::Rti, AddressingMode::Implied, 6),                       // 0x40
+    (Instruction::Eor, AddressingMode::XIndexedIndirect, 6),              // 0x41
+    (Instruction::Wdm, AddressingMode::ImmediateByte, 2),                 // 0x42
+    (Instruction::Eor, AddressingMode::StackRelative, 4),                 // 0x43
+    (Instruction::Mvp, AddressingMode::BlockMove, 7),                     // 0x44
+    (Instruction::Eor, AddressingMode::Direct, 3),                        // 0x45
+    (Instruction::Lsr, AddressingMode::Direct, 5),                        // 0x46
+    (Instruction::Eor, AddressingMode::DirectIndirectLong, 6),            // 0x47
+    (Instruction::Pha, AddressingMode::Implied, 3),                       // 0x48
+    (Instruction::Eor, AddressingMode::Immediate, 2),                     // 0x49
+    (Instruction::Lsr, AddressingMode::Accumulator, 2),                   // 0x4A
+    (Instruction::Phk, AddressingMode::Implied, 3),                       // 0x4B
+    (Instruction::Jmp, AddressingMode::Absolute, 3),                      // 0x4C
+    (Instruction::Eor, AddressingMode::Absolute, 4),                      // 0x4D
+    (Instruction::Lsr, AddressingMode::Absolute, 6),                      // 0x4E
+    (Instruction::Eor, AddressingMode::AbsoluteLong, 5),                  // 0x4F
+    (Instruction::Bvc, AddressingMode::Relative, 2),                      // 0x50
+    (Instruction::Eor, AddressingMode::IndirectYIndexed, 5),              // 0x51
+    (Instruction::Eor, AddressingMode::DirectIndirect, 5),                // 0x52
+    (Instruction::Eor, AddressingMode::StackRelativeIndirectYIndexed, 7), // 0x53
+    (Instruction::Mvn, AddressingMode::BlockMove, 7),                     // 0x54
+    (Instruction::Eor, AddressingMode::DirectXIndexed, 4),                // 0x55
+    (Instruction::Lsr, AddressingMode::DirectXIndexed, 6),                // 0x56
+    (Instruction::Eor, AddressingMode::IndirectLongYIndexed, 6),          // 0x57
+    (Instruction::Cli, AddressingMode::Implied, 2),                       // 0x58
+    (Instruction::Eor, AddressingMode::AbsoluteYIndexed, 4),              // 0x59
+    (Instruction::Phy, AddressingMode::Implied, 3),                       // 0x5A
+    (Instruction::Tcd, AddressingMode::Implied, 2),                       // 0x5B
+    (Instruction::Jml, AddressingMode::AbsoluteLong, 4),                  // 0x5C
+    (Instruction::Eor, AddressingMode::AbsoluteXIndexed, 4),              // 0x5D
+    (Instruction::Lsr, AddressingMode::AbsoluteXIndexed, 7),              // 0x5E
+    (Instruction::Eor, AddressingMode::AbsoluteLongXIndexed, 5),          // 0x5F
+    (Instruction::Rts, AddressingMode::Implied, 6),                       // 0x60
+    (Instruction::Adc, AddressingMode::XIndexedIndirect, 6),              // 0x61
+    (Instruction::Per, AddressingMode::RelativeLong, 6),                  // 0x62
+    (Instruction::Adc, AddressingMode::StackRelative, 4),                 // 0x63
+    (Instruction::Stz, AddressingMode::Direct, 3),                        // 0x64
+    (Instruction::Adc, AddressingMode::Direct, 3),                        // 0x65
+    (Instruction::Ror, AddressingMode::Direct, 5),                        // 0x66
+    (Instruction::Adc, AddressingMode::DirectIndirectLong, 6),            // 0x67
+    (Instruction::Pla, AddressingMode::Implied, 4),                       // 0x68
+    (Instruction::Adc, AddressingMode::Immediate, 2),                     // 0x69
+    (Instruction::Ror, AddressingMode::Accumulator, 2),                   // 0x6A
+    (Instruction::Rtl, AddressingMode::Implied, 6),                       // 0x6B
+    (Instruction::Jmp, AddressingMode::Indirect, 5),                      // 0x6C
+    (Instruction::Adc, AddressingMode::Absolute, 4),                      // 0x6D
+    (Instruction::Ror, AddressingMode::Absolute, 6),                      // 0x6E
+    (Instruction::Adc, AddressingMode::AbsoluteLong, 5),                  // 0x6F
+    (Instruction::Bvs, AddressingMode::Relative, 2),                      // 0x70
+    (Instruction::Adc, AddressingMode::IndirectYIndexed, 5),              // 0x71
+    (Instruction::Adc, AddressingMode::DirectIndirect, 5),                // 0x72
+    (Instruction::Adc, AddressingMode::StackRelativeIndirectYIndexed, 7), // 0x73
+    (Instruction::Stz, AddressingMode::DirectXIndexed, 4),                // 0x74
+    (Instruction::Adc, AddressingMode::DirectXIndexed, 4),                // 0x75
+    (Instruction::Ror, AddressingMode::DirectXIndexed, 6),                // 0x76
+    (Instruction::Adc, AddressingMode::IndirectLongYIndexed, 6),          // 0x77
+    (Instruction::Sei, AddressingMode::Implied, 2),                       // 0x78
+    (Instruction::Adc, AddressingMode::AbsoluteYIndexed, 4),              // 0x79
+    (Instruction::Ply, AddressingMode::Implied, 4),                       // 0x7A
+    (Instruction::Tdc, AddressingMode::Implied, 2),                       // 0x7B
+    (Instruction::Jmp, AddressingMode::AbsoluteXIndexedIndirect, 6),      // 0x7C
+    (Instruction::Adc, AddressingMode::AbsoluteXIndexed, 4),              // 0x7D
+    (Instruction::Ror, AddressingMode::AbsoluteXIndexed, 7),              // 0x7E
+    (Instruction::Adc, AddressingMode::AbsoluteLongXIndexed, 5),          // 0x7F
+    (Instruction::Bra, AddressingMode::Relative, 2),                      // 0x80
+    (Instruction::Sta, AddressingMode::XIndexedIndirect, 6),              // 0x81
+    (Instruction::Brl, AddressingMode::RelativeLong, 4),                  // 0x82
+    (Instruction::Sta, AddressingMode::StackRelative, 4),                 // 0x83
+    (Instruction::Sty, AddressingMode::Direct, 3),                        // 0x84
+    (Instruction::Sta, AddressingMode::Direct, 3),                        // 0x85
+    (Instruction::Stx, AddressingMode::Direct, 3),                        // 0x86
+    (Instruction::Sta, AddressingMode::DirectIndirectLong, 6),            // 0x87
+    (Instruction::Dey, AddressingMode::Implied, 2),                       // 0x88
+    (Instruction::Bit, AddressingMode::Immediate, 2),                     // 0x89
+    (Instruction::Txa, AddressingMode::Implied, 2),                       // 0x8A
+    (Instruction::Phb, AddressingMode::Implied, 3),                       // 0x8B
+    (Instruction::Sty, AddressingMode::Absolute, 4),                      // 0x8C
+    (Instruction::Sta, AddressingMode::Absolute, 4),                      // 0x8D
+    (Instruction::Stx, AddressingMode::Absolute, 4),                      // 0x8E
+    (Instruction::Sta, AddressingMode::AbsoluteLong, 5),                  // 0x8F
+    (Instruction::Bcc, AddressingMode::Relative, 2),                      // 0x90
+    (Instruction::Sta, AddressingMode::IndirectYIndexed, 6),              // 0x91
+    (Instruction::Sta, AddressingMode::DirectIndirect, 5),                // 0x92
+    (Instruction::Sta, AddressingMode::StackRelativeIndirectYIndexed, 7), // 0x93
+    (Instruction::Sty, AddressingMode::DirectXIndexed, 4),                // 0x94
+    (Instruction::Sta, AddressingMode::DirectXIndexed, 4),                // 0x95
+    (Instruction::Stx, AddressingMode::DirectYIndexed, 4),                // 0x96
+    (Instruction::Sta, AddressingMode::IndirectLongYIndexed, 6),          // 0x97
+    (Instruction::Tya, AddressingMode::Implied, 2),                       // 0x98
+    (Instruction::Sta, AddressingMode::AbsoluteYIndexed, 5),              // 0x99
+    (Instruction::Txs, AddressingMode::Implied, 2),                       // 0x9A
+    (Instruction::Txy, AddressingMode::Implied, 2),                       // 0x9B
+    (Instruction::Stz, AddressingMode::Absolute, 4),                      // 0x9C
+    (Instruction::Sta, AddressingMode::AbsoluteXIndexed, 5),              // 0x9D
+    (Instruction::Stz, AddressingMode::AbsoluteXIndexed, 5),              // 0x9E
+    (Instruction::Sta, AddressingMode::AbsoluteLongXIndexed, 5),          // 0x9F
+    (Instruction::Ldy, AddressingMode::ImmediateIndex, 2),                // 0xA0
+    (Instruction::Lda, AddressingMode::XIndexedIndirect, 6),              // 0xA1
+    (Instruction::Ldx, AddressingMode::ImmediateIndex, 2),                // 0xA2
+    (Instruction::Lda, AddressingMode::StackRelative, 4),                 // 0xA3
+    (Instruction::Ldy, AddressingMode::Direct, 3),                        // 0xA4
+    (Instruction::Lda, AddressingMode::Direct, 3),                        // 0xA5
+    (Instruction::Ldx, AddressingMode::Direct, 3),                        // 0xA6
+    (Instruction::Lda, AddressingMode::DirectIndirectLong, 6),            // 0xA7
+    (Instruction::Tay, AddressingMode::Implied, 2),                       // 0xA8
+    (Instruction::Lda, AddressingMode::Immediate, 2),                     // 0xA9
+    (Instruction::Tax, AddressingMode::Implied, 2),                       // 0xAA
+    (Instruction::Plb, AddressingMode::Implied, 4),                       // 0xAB
+    (Instruction::Ldy, AddressingMode::Absolute, 4),                      // 0xAC
+    (Instruction::Lda, AddressingMode::Absolute, 4),                      // 0xAD
+    (Instruction::Ldx, AddressingMode::Absolute, 4),                      // 0xAE
+    (Instruction::Lda, AddressingMode::AbsoluteLong, 5),                  // 0xAF
+    (Instruction::Bcs, AddressingMode::Relative, 2),                      // 0xB0
+    (Instruction::Lda, AddressingMode::IndirectYIndexed, 5),              // 0xB1
+    (Instruction::Lda, AddressingMode::DirectIndirect, 5),                // 0xB2
+    (Instruction::Lda, AddressingMode::StackRelativeIndirectYIndexed, 7), // 0xB3
+    (Instruction::Ldy, AddressingMode::DirectXIndexed, 4),                // 0xB4
+    (Instruction::Lda, AddressingMode::DirectXIndexed, 4),                // 0xB5
+    (Instruction::Ldx, AddressingMode::DirectYIndexed, 4),                // 0xB6
+    (Instruction::Lda, AddressingMode::IndirectLongYIndexed, 6),          // 0xB7
+    (Instruction::Clv, AddressingMode::Implied, 2),                       // 0xB8
+    (Instruction::Lda, AddressingMode::AbsoluteYIndexed, 4),              // 0xB9
+    (Instruction::Tsx, AddressingMode::Implied, 2),                       // 0xBA
+    (Instruction::Tyx, AddressingMode::Implied, 2),                       // 0xBB
+    (Instruction::Ldy, AddressingMode::AbsoluteXIndexed, 4),              // 0xBC
+    (Instruction::Lda, AddressingMode::AbsoluteXIndexed, 4),              // 0xBD
+    (Instruction::Ldx, AddressingMode::AbsoluteYIndexed, 4),              // 0xBE
+    (Instruction::Lda, AddressingMode::AbsoluteLongXIndexed, 5),          // 0xBF
+    (Instruction::Cpy, AddressingMode::ImmediateIndex, 2),                // 0xC0
+    (Instruction::Cmp, AddressingMode::XIndexedIndirect, 6),              // 0xC1
+    (Instruction::Rep, AddressingMode::ImmediateByte, 3),                 // 0xC2
+    (Instruction::Cmp, AddressingMode::StackRelative, 4),                 // 0xC3
+    (Instruction::Cpy, AddressingMode::Direct, 3),                        // 0xC4
+    (Instruction::Cmp, AddressingMode::Direct, 3),                        // 0xC5
+    (Instruction::Dec, AddressingMode::Direct, 5),                        // 0xC6
+    (Instruction::Cmp, AddressingMode::DirectIndirectLong, 6),            // 0xC7
+    (Instruction::Iny, AddressingMode::Implied, 2),                       // 0xC8
+    (Instruction::Cmp, AddressingMode::Immediate, 2),                     // 0xC9
+    (Instruction::Dex, AddressingMode::Implied, 2),                       // 0xCA
+    (Instruction::Wai, AddressingMode::Implied, 3),                       // 0xCB
+    (Instruction::Cpy, AddressingMode::Absolute, 4),                      // 0xCC
+    (Instruction::Cmp, AddressingMode::Absolute, 4),                      // 0xCD
+    (Instruction::Dec, AddressingMode::Absolute, 6),                      // 0xCE
+    (Instruction::Cmp, AddressingMode::AbsoluteLong, 5),                  // 0xCF
+    (Instruction::Bne, AddressingMode::Relative, 2),                      // 0xD0
+    (Instruction::Cmp, AddressingMode::IndirectYIndexed, 5),              // 0xD1
+    (Instruction::Cmp, AddressingMode::DirectIndirect, 5),                // 0xD2
+    (Instruction::Cmp, AddressingMode::StackRelativeIndirectYIndexed, 7), // 0xD3
+    (Instruction::Pei, AddressingMode::Direct, 6),                        // 0xD4
+    (Instruction::Cmp, AddressingMode::DirectXIndexed, 4),                // 0xD5
+    (Instruction::Dec, AddressingMode::DirectXIndexed, 6),                // 0xD6
+    (Instruction::Cmp, AddressingMode::IndirectLongYIndexed, 6),          // 0xD7
+    (Instruction::Cld, AddressingMode::Implied, 2),                       // 0xD8
+    (Instruction::Cmp, AddressingMode::AbsoluteYIndexed, 4),              // 0xD9
+    (Instruction::Phx, AddressingMode::Implied, 3),                       // 0xDA
+    (Instruction::Stp, AddressingMode::Implied, 3),                       // 0xDB
+    (Instruction::Jml, AddressingMode::IndirectLong, 6),                  // 0xDC
+    (Instruction::Cmp, AddressingMode::AbsoluteXIndexed, 4),              // 0xDD
+    (Instruction::Dec, AddressingMode::AbsoluteXIndexed, 7),              // 0xDE
+    (Instruction::Cmp, AddressingMode::AbsoluteLongXIndexed, 5),          // 0xDF
+    (Instruction::Cpx, AddressingMode::ImmediateIndex, 2),                // 0xE0
+    (Instruction::Sbc, AddressingMode::XIndexedIndirect, 6),              // 0xE1
+    (Instruction::Sep, AddressingMode::ImmediateByte, 3),                 // 0xE2
+    (Instruction::Sbc, AddressingMode::StackRelative, 4),                 // 0xE3
+    (Instruction::Cpx, AddressingMode::Direct, 3),                        // 0xE4
+    (Instruction::Sbc, AddressingMode::Direct, 3),                        // 0xE5
+    (Instruction::Inc, AddressingMode::Direct, 5),                        // 0xE6
+    (Instruction::Sbc, AddressingMode::DirectIndirectLong, 6),            // 0xE7
+    (Instruction::Inx, AddressingMode::Implied, 2),                       // 0xE8
+    (Instruction::Sbc, AddressingMode::Immediate, 2),                     // 0xE9
+    (Instruction::Nop, AddressingMode::Implied, 2),                       // 0xEA
+    (Instruction::Xba, AddressingMode::Implied, 3),                       // 0xEB
+    (Instruction::Cpx, AddressingMode::Absolute, 4),                      // 0xEC
+    (Instruction::Sbc, AddressingMode::Absolute, 4),                      // 0xED
+    (Instruction::Inc, AddressingMode::Absolute, 6),                      // 0xEE
+    (Instruction::Sbc, AddressingMode::AbsoluteLong, 5),                  // 0xEF
+    (Instruction::Beq, AddressingMode::Relative, 2),                      // 0xF0
+    (Instruction::Sbc, AddressingMode::IndirectYIndexed, 5),              // 0xF1
+    (Instruction::Sbc, AddressingMode::DirectIndirect, 5),                // 0xF2
+    (Instruction::Sbc, AddressingMode::StackRelativeIndirectYIndexed, 7), // 0xF3
+    (Instruction::Pea, AddressingMode::Absolute, 5),                      // 0xF4
+    (Instruction::Sbc, AddressingMode::DirectXIndexed, 4),                // 0xF5
+    (Instruction::Inc, AddressingMode::DirectXIndexed, 6),                // 0xF6
+    (Instruction::Sbc, AddressingMode::IndirectLongYIndexed, 6),          // 0xF7
+    (Instruction::Sed, AddressingMode::Implied, 2),                       // 0xF8
+    (Instruction::Sbc, AddressingMode::AbsoluteYIndexed, 4),              // 0xF9
+    (Instruction::Plx, AddressingMode::Implied, 4),                       // 0xFA
+    (Instruction::Xce, AddressingMode::Implied, 2),                       // 0xFB
+    (Instruction::Jsr, AddressingMode::AbsoluteXIndexedIndirect, 8),      // 0xFC
+    (Instruction::Sbc, AddressingMode::AbsoluteXIndexed, 4),              // 0xFD
+    (Instruction::Inc, AddressingMode::AbsoluteXIndexed, 7),              // 0xFE
+    (Instruction::Sbc, AddressingMode::AbsoluteLongXIndexed, 5),          // 0xFF
+];
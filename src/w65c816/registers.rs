@@ -0,0 +1,242 @@
+use crate::bus::Bus;
+use crate::registers::Flag;
+
+/// Processor status register of the 65816. In native mode, bits 4 and 5
+/// select the width of the index registers and of the accumulator.
+pub struct StatusRegister {
+    pub carry: bool,
+    pub zero: bool,
+    pub irq_disable: bool,
+    pub decimal_mode: bool,
+    pub index_8bit: bool,
+    pub memory_8bit: bool,
+    pub overflow: bool,
+    pub negative: bool,
+}
+
+impl StatusRegister {
+    fn new() -> StatusRegister {
+        StatusRegister {
+            carry: false,
+            zero: false,
+            irq_disable: true,
+            decimal_mode: false,
+            index_8bit: true,
+            memory_8bit: true,
+            overflow: false,
+            negative: false,
+        }
+    }
+
+    pub fn get(&self) -> u8 {
+        let mut value = 0x00;
+        if self.negative { value |= 0x80; }
+        if self.overflow { value |= 0x40; }
+        if self.memory_8bit { value |= 0x20; }
+        if self.index_8bit { value |= 0x10; }
+        if self.decimal_mode { value |= 0x08; }
+        if self.irq_disable { value |= 0x04; }
+        if self.zero { value |= 0x02; }
+        if self.carry { value |= 0x01; }
+        value
+    }
+
+    pub fn flag(&self, flag: Flag) -> bool {
+        match flag {
+            Flag::Carry => self.carry,
+            Flag::Zero => self.zero,
+            Flag::IrqDisable => self.irq_disable,
+            Flag::DecimalMode => self.decimal_mode,
+            Flag::Overflow => self.overflow,
+            Flag::Negative => self.negative,
+        }
+    }
+
+    pub fn set_flag(&mut self, flag: Flag, value: bool) {
+        match flag {
+            Flag::Carry => self.carry = value,
+            Flag::Zero => self.zero = value,
+            Flag::IrqDisable => self.irq_disable = value,
+            Flag::DecimalMode => self.decimal_mode = value,
+            Flag::Overflow => self.overflow = value,
+            Flag::Negative => self.negative = value,
+        }
+    }
+}
+
+pub struct Registers {
+    pub accumulator: u16,
+    pub x_index: u16,
+    pub y_index: u16,
+    pub stack_pointer: u16,
+    pub direct_page: u16,
+    pub data_bank: u8,
+    pub program_bank: u8,
+    pub program_counter: u16,
+    pub status_reg: StatusRegister,
+    pub emulation: bool,
+    pub irq_active: bool,
+    pub nmi_active: bool,
+}
+
+impl Registers {
+    pub fn new() -> Registers {
+        Registers {
+            accumulator: 0,
+            x_index: 0,
+            y_index: 0,
+            stack_pointer: 0x01FF,
+            direct_page: 0,
+            data_bank: 0,
+            program_bank: 0,
+            program_counter: 0xFFFC,
+            status_reg: StatusRegister::new(),
+            emulation: true,
+            irq_active: false,
+            nmi_active: false,
+        }
+    }
+
+    /// Returns true if the accumulator and memory accesses are 8 bits wide.
+    pub fn accumulator_8bit(&self) -> bool {
+        self.status_reg.memory_8bit
+    }
+
+    /// Returns true if the index registers are 8 bits wide.
+    pub fn index_8bit(&self) -> bool {
+        self.status_reg.index_8bit
+    }
+
+    ///
+    /// Sets the status register. In emulation mode, M and X stay set. When
+    /// the index registers become 8 bits wide, their high bytes are cleared.
+    ///
+    pub fn set_status(&mut self, value: u8) {
+        let status = &mut self.status_reg;
+        status.negative = (value & 0x80) != 0;
+        status.overflow = (value & 0x40) != 0;
+        status.memory_8bit = (value & 0x20) != 0 || self.emulation;
+        status.index_8bit = (value & 0x10) != 0 || self.emulation;
+        status.decimal_mode = (value & 0x08) != 0;
+        status.irq_disable = (value & 0x04) != 0;
+        status.zero = (value & 0x02) != 0;
+        status.carry = (value & 0x01) != 0;
+        if status.index_8bit {
+            self.x_index &= 0x00FF;
+            self.y_index &= 0x00FF;
+        }
+    }
+
+    ///
+    /// Switches between emulation and native mode. Entering emulation mode
+    /// forces 8 bit registers and moves the stack back to page 1.
+    ///
+    pub fn set_emulation(&mut self, emulation: bool) {
+        self.emulation = emulation;
+        if emulation {
+            self.stack_pointer = 0x0100 | (self.stack_pointer & 0x00FF);
+            let status = self.status_reg.get();
+            self.set_status(status);
+        }
+    }
+
+    /// Returns the accumulator, with the width selected by M.
+    pub fn a(&self) -> u16 {
+        if self.accumulator_8bit() { self.accumulator & 0x00FF } else { self.accumulator }
+    }
+
+    /// Sets the accumulator. In 8 bit mode, the high byte (B) is kept.
+    pub fn set_a(&mut self, value: u16) {
+        if self.accumulator_8bit() {
+            self.accumulator = (self.accumulator & 0xFF00) | (value & 0x00FF);
+        } else {
+            self.accumulator = value;
+        }
+    }
+
+    /// Sets the X register, truncated to the width selected by X.
+    pub fn set_x(&mut self, value: u16) {
+        self.x_index = if self.index_8bit() { value & 0x00FF } else { value };
+    }
+
+    /// Sets the Y register, truncated to the width selected by X.
+    pub fn set_y(&mut self, value: u16) {
+        self.y_index = if self.index_8bit() { value & 0x00FF } else { value };
+    }
+
+    /// Sets the stack pointer, which stays in page 1 in emulation mode.
+    pub fn set_stack_pointer(&mut self, value: u16) {
+        self.stack_pointer = if self.emulation { 0x0100 | (value & 0x00FF) } else { value };
+    }
+
+    /// Returns the 24 bit address of the next instruction byte.
+    pub fn program_address(&self) -> u32 {
+        ((self.program_bank as u32) << 16) | self.program_counter as u32
+    }
+
+    pub fn push<T: Bus>(&mut self, value: u8, bus: &mut T) {
+        bus.write_long(self.stack_pointer as u32, value);
+        let sp = self.stack_pointer.wrapping_sub(1);
+        self.set_stack_pointer(sp);
+    }
+
+    pub fn pop<T: Bus>(&mut self, bus: &mut T) -> u8 {
+        let sp = self.stack_pointer.wrapping_add(1);
+        self.set_stack_pointer(sp);
+        bus.read_long(self.stack_pointer as u32)
+    }
+
+    /// Pushes a 16 bit word, high byte first.
+    pub fn push_word<T: Bus>(&mut self, value: u16, bus: &mut T) {
+        self.push((value >> 8) as u8, bus);
+        self.push((value & 0xFF) as u8, bus);
+    }
+
+    /// Pops a 16 bit word, low byte first.
+    pub fn pop_word<T: Bus>(&mut self, bus: &mut T) -> u16 {
+        let lo = self.pop(bus) as u16;
+        let hi = self.pop(bus) as u16;
+        (hi << 8) | lo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emulation_mode() {
+        let mut regs = Registers::new();
+        regs.set_emulation(false);
+        regs.set_status(0x00);
+        regs.x_index = 0x1234;
+        regs.stack_pointer = 0x1FF0;
+        assert!(!regs.index_8bit());
+        assert!(!regs.accumulator_8bit());
+
+        regs.set_emulation(true);
+        assert!(regs.index_8bit());
+        assert!(regs.accumulator_8bit());
+        assert_eq!(regs.x_index, 0x0034);
+        assert_eq!(regs.stack_pointer, 0x01F0);
+
+        // The stack wraps inside page 1.
+        regs.stack_pointer = 0x0100;
+        regs.push(0x12, &mut crate::bus::tests::DummyBus::new());
+        assert_eq!(regs.stack_pointer, 0x01FF);
+    }
+
+    #[test]
+    fn test_accumulator_width() {
+        let mut regs = Registers::new();
+        regs.accumulator = 0x1234;
+        regs.set_a(0xABCD);
+        assert_eq!(regs.accumulator, 0x12CD);
+        assert_eq!(regs.a(), 0x00CD);
+
+        regs.set_emulation(false);
+        regs.set_status(0x00);
+        regs.set_a(0xABCD);
+        assert_eq!(regs.a(), 0xABCD);
+    }
+}
@@ -0,0 +1,255 @@
+use crate::bus::Bus;
+
+/// Bus accesses a watchpoint triggers on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    /// Both reads and writes.
+    Access,
+}
+
+/// Direction of a bus access.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+/// Condition on the bus accesses to an address range.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    /// First address of the range.
+    pub start: u16,
+    /// Last address of the range, included.
+    pub end: u16,
+    pub kind: WatchKind,
+    /// Only trigger when this value is read or written.
+    pub value: Option<u8>,
+}
+
+impl Watchpoint {
+    /// Watches the reads of a single address.
+    pub fn read(addr: u16) -> Watchpoint {
+        Watchpoint::range(addr, addr, WatchKind::Read)
+    }
+
+    /// Watches the writes to a single address.
+    pub fn write(addr: u16) -> Watchpoint {
+        Watchpoint::range(addr, addr, WatchKind::Write)
+    }
+
+    /// Watches every access to a single address.
+    pub fn access(addr: u16) -> Watchpoint {
+        Watchpoint::range(addr, addr, WatchKind::Access)
+    }
+
+    /// Watches the accesses to the addresses from `start` to `end`, both
+    /// included.
+    pub fn range(start: u16, end: u16, kind: WatchKind) -> Watchpoint {
+        Watchpoint { start, end, kind, value: None }
+    }
+
+    ///
+    /// Restricts the watchpoint to accesses of the given value, e.g. to
+    /// catch a write of $00.
+    ///
+    pub fn with_value(self, value: u8) -> Watchpoint {
+        Watchpoint { value: Some(value), ..self }
+    }
+
+    // `Option::is_none_or` needs Rust 1.82.
+    #[allow(clippy::unnecessary_map_or)]
+    pub(crate) fn matches(&self, addr: u16, access: AccessKind, value: u8) -> bool {
+        let kind = matches!((self.kind, access),
+            (WatchKind::Access, _) | (WatchKind::Read, AccessKind::Read) | (WatchKind::Write, AccessKind::Write));
+        kind && (self.start..=self.end).contains(&addr) && self.value.map_or(true, |v| v == value)
+    }
+}
+
+/// Access that triggered a watchpoint.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WatchHit {
+    /// Address of the instruction that made the access.
+    pub pc: u16,
+    pub addr: u16,
+    pub access: AccessKind,
+    /// Value before the access, as seen with `Bus::peek`.
+    pub old_value: u8,
+    /// Value written, or read.
+    pub new_value: u8,
+}
+
+/// Wraps a bus to check its reads and writes against a set of
+/// watchpoints. `Cpu::run_watched` stops after the instruction that
+/// triggers one.
+pub struct WatchBus<T: Bus> {
+    bus: T,
+    watchpoints: Vec<Watchpoint>,
+    pc: u16,
+    hit: Option<WatchHit>,
+}
+
+impl<T: Bus> WatchBus<T> {
+    /// Wraps a bus, with no watchpoints.
+    pub fn new(bus: T) -> Self {
+        WatchBus { bus, watchpoints: Vec::new(), pc: 0, hit: None }
+    }
+
+    /// Returns the wrapped bus.
+    pub fn bus(&self) -> &T {
+        &self.bus
+    }
+
+    /// Returns the wrapped bus, for accesses that don't go through the
+    /// watchpoints.
+    pub fn bus_mut(&mut self) -> &mut T {
+        &mut self.bus
+    }
+
+    /// Unwraps the bus.
+    pub fn into_inner(self) -> T {
+        self.bus
+    }
+
+    /// Adds a watchpoint.
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.push(watchpoint);
+    }
+
+    ///
+    /// Removes every watchpoint equal to the given one. Returns false if
+    /// there was none.
+    ///
+    pub fn remove_watchpoint(&mut self, watchpoint: &Watchpoint) -> bool {
+        let count = self.watchpoints.len();
+        self.watchpoints.retain(|w| w != watchpoint);
+        self.watchpoints.len() != count
+    }
+
+    /// Removes every watchpoint.
+    pub fn clear_watchpoints(&mut self) {
+        self.watchpoints.clear();
+    }
+
+    /// Returns the watchpoints, in the order they were added.
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    ///
+    /// Sets the address of the instruction being executed, reported in the
+    /// hits. `Cpu::run_watched` keeps it up to date.
+    ///
+    pub fn set_pc(&mut self, pc: u16) {
+        self.pc = pc;
+    }
+
+    ///
+    /// Returns the first access that triggered a watchpoint since the last
+    /// call, and forgets it.
+    ///
+    pub fn take_hit(&mut self) -> Option<WatchHit> {
+        self.hit.take()
+    }
+
+    fn triggers(&self, addr: u16, access: AccessKind, value: u8) -> bool {
+        self.hit.is_none() && self.watchpoints.iter().any(|w| w.matches(addr, access, value))
+    }
+}
+
+impl<T: Bus> Bus for WatchBus<T> {
+    fn write(&mut self, addr: u16, value: u8) {
+        // The old value is only peeked for the writes that trigger a hit.
+        if self.triggers(addr, AccessKind::Write, value) {
+            let old_value = self.bus.peek(addr);
+            self.hit = Some(WatchHit { pc: self.pc, addr, access: AccessKind::Write, old_value, new_value: value });
+        }
+        self.bus.write(addr, value);
+    }
+
+    fn read(&mut self, addr: u16) -> u8 {
+        let value = self.bus.read(addr);
+        if self.triggers(addr, AccessKind::Read, value) {
+            self.hit = Some(WatchHit { pc: self.pc, addr, access: AccessKind::Read, old_value: value, new_value: value });
+        }
+        value
+    }
+
+    fn peek(&mut self, addr: u16) -> u8 {
+        self.bus.peek(addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::tests::DummyBus;
+
+    #[test]
+    fn test_watchpoint_conditions() {
+        let mut bus = WatchBus::new(DummyBus::new());
+        bus.add_watchpoint(Watchpoint::range(0x10, 0x1F, WatchKind::Write).with_value(0x00));
+        bus.add_watchpoint(Watchpoint::read(0x80));
+        bus.set_pc(0x1234);
+
+        bus.write(0x10, 0x42);
+        bus.write(0x20, 0x00);
+        bus.read(0x10);
+        bus.write(0x80, 0x01);
+        assert_eq!(bus.take_hit(), None);
+
+        bus.write(0x1F, 0x99);
+        bus.write(0x1F, 0x00);
+        assert_eq!(bus.take_hit(), Some(WatchHit {
+            pc: 0x1234, addr: 0x1F, access: AccessKind::Write, old_value: 0x99, new_value: 0x00,
+        }));
+
+        // Peeking doesn't trigger watchpoints.
+        assert_eq!(bus.peek(0x80), 0x01);
+        assert_eq!(bus.take_hit(), None);
+        assert_eq!(bus.read(0x80), 0x01);
+        assert_eq!(bus.take_hit().map(|hit| hit.access), Some(AccessKind::Read));
+
+        assert!(bus.remove_watchpoint(&Watchpoint::read(0x80)));
+        assert!(!bus.remove_watchpoint(&Watchpoint::read(0x80)));
+        assert_eq!(bus.watchpoints().len(), 1);
+    }
+
+    /// Bus counting the peeks.
+    struct PeekCountingBus {
+        bus: DummyBus,
+        peeks: usize,
+    }
+
+    impl Bus for PeekCountingBus {
+        fn read(&mut self, addr: u16) -> u8 {
+            self.bus.read(addr)
+        }
+
+        fn write(&mut self, addr: u16, value: u8) {
+            self.bus.write(addr, value)
+        }
+
+        fn peek(&mut self, addr: u16) -> u8 {
+            self.peeks += 1;
+            self.bus.peek(addr)
+        }
+    }
+
+    #[test]
+    fn test_peeks_only_on_hits() {
+        let mut bus = WatchBus::new(PeekCountingBus { bus: DummyBus::new(), peeks: 0 });
+        bus.add_watchpoint(Watchpoint::write(0x10).with_value(0x00));
+        bus.add_watchpoint(Watchpoint::read(0x20));
+
+        bus.write(0x11, 0x00);
+        bus.write(0x10, 0x01);
+        bus.write(0x20, 0x00);
+        assert_eq!((bus.take_hit(), bus.bus().peeks), (None, 0));
+
+        bus.write(0x10, 0x00);
+        assert_eq!(bus.take_hit().map(|hit| hit.old_value), Some(0x01));
+        assert_eq!(bus.bus().peeks, 1);
+    }
+}
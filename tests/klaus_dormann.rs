@@ -0,0 +1,132 @@
+//! Runs the 6502 functional and decimal tests of Klaus Dormann.
+//!
+//! The binaries are not distributed with the crate. Assemble them from
+//! https://github.com/Klaus2m5/6502_65C02_functional_tests with the default
+//! configuration, or take the prebuilt ones from its `bin_files` directory,
+//! and put `6502_functional_test.bin` and `6502_decimal_test.bin` in
+//! `tests/roms`, or in the directory named by `KLAUS_DORMANN_DIR`. The
+//! tests are ignored by default; run them with
+//! `cargo test --release --test klaus_dormann -- --ignored`. A test fails
+//! if its binary is missing.
+//!
+//! The decimal test predicts the results of the processor it is assembled
+//! for, which differ for invalid BCD operands. The 65C02 run needs it
+//! assembled with `cputype = 1`, as `65C02_decimal_test.bin`.
+
+use mos6502::{Bus, Cpu, RunUntil, StopReason, Variant};
+use std::path::PathBuf;
+
+/// Entry point of the functional test.
+const FUNCTIONAL_START: u16 = 0x0400;
+/// Address of the `success` trap of the functional test, assembled with the
+/// default configuration.
+const FUNCTIONAL_SUCCESS: u16 = 0x3469;
+/// Origin and entry point of the decimal test.
+const DECIMAL_START: u16 = 0x0200;
+/// Zero page byte set to 0 when the decimal test passes.
+const DECIMAL_ERROR: u16 = 0x000B;
+/// Opcode ending the decimal test, STP on the 65C02 and not run on the NMOS
+/// cores.
+const DECIMAL_END: u8 = 0xDB;
+/// Bounds on the runs, well above the cycles the tests need.
+const FUNCTIONAL_MAX_CYCLES: u64 = 200_000_000;
+const DECIMAL_MAX_CYCLES: u64 = 100_000_000;
+
+/// Flat 64K of RAM.
+struct Ram {
+    mem: Vec<u8>,
+}
+
+impl Ram {
+    fn load(image: &[u8], origin: u16) -> Ram {
+        let mut mem = vec![0; 0x10000];
+        mem[origin as usize..origin as usize + image.len()].copy_from_slice(image);
+        Ram { mem }
+    }
+}
+
+impl Bus for Ram {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.mem[addr as usize]
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        self.mem[addr as usize] = value;
+    }
+}
+
+/// Reads a binary from the test directory.
+fn load_binary(name: &str) -> Vec<u8> {
+    let dir = std::env::var_os("KLAUS_DORMANN_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("roms"));
+    let path = dir.join(name);
+    std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {}, see tests/klaus_dormann.rs", path.display(), e))
+}
+
+///
+/// Runs until an instruction jumps or branches to itself, the way every
+/// test reports its outcome, and returns the address of that trap.
+///
+fn run_to_trap(cpu: &mut Cpu, bus: &mut Ram, max_cycles: u64) -> u16 {
+    loop {
+        let pc = cpu.program_counter();
+        if let Err(error) = cpu.single_step(bus) {
+            panic!("{} after {} cycles", error, cpu.cycles());
+        }
+        if cpu.program_counter() == pc {
+            return pc;
+        }
+        assert!(cpu.cycles() < max_cycles, "no trap reached, last instruction at ${:04X}", pc);
+    }
+}
+
+fn functional_test(variant: Variant) {
+    let image = load_binary("6502_functional_test.bin");
+    let mut bus = Ram::load(&image, 0x0000);
+    let mut cpu = Cpu::with_variant(variant);
+    cpu.set_program_counter(FUNCTIONAL_START);
+
+    let trap = run_to_trap(&mut cpu, &mut bus, FUNCTIONAL_MAX_CYCLES);
+    assert_eq!(trap, FUNCTIONAL_SUCCESS,
+               "trapped at ${:04X}, test case {:02X}, A={:02X} X={:02X} Y={:02X} P={:02X}",
+               trap, bus.mem[0x0200], cpu.accumulator(), cpu.x_index(), cpu.y_index(), cpu.status());
+}
+
+fn decimal_test(variant: Variant, name: &str) {
+    let image = load_binary(name);
+    let mut bus = Ram::load(&image, DECIMAL_START);
+    let mut cpu = Cpu::with_variant(variant);
+    cpu.set_program_counter(DECIMAL_START);
+
+    let until = RunUntil { cycles: Some(DECIMAL_MAX_CYCLES), ..RunUntil::opcodes(&[DECIMAL_END]) };
+    let result = cpu.run(&mut bus, &until);
+    assert!(matches!(result, Ok(StopReason::Opcode { .. })), "decimal test didn't end: {:?}", result);
+    assert_eq!(bus.mem[DECIMAL_ERROR as usize], 0,
+               "decimal test failed, N1={:02X} N2={:02X} carry={}",
+               bus.mem[0x0000], bus.mem[0x0001], cpu.y_index());
+}
+
+#[test]
+#[ignore]
+fn test_functional_nmos() {
+    functional_test(Variant::Nmos6502);
+}
+
+#[test]
+#[ignore]
+fn test_functional_65c02() {
+    functional_test(Variant::Wdc65c02);
+}
+
+#[test]
+#[ignore]
+fn test_decimal_nmos() {
+    decimal_test(Variant::Nmos6502, "6502_decimal_test.bin");
+}
+
+#[test]
+#[ignore]
+fn test_decimal_65c02() {
+    decimal_test(Variant::Wdc65c02, "65C02_decimal_test.bin");
+}
@@ -0,0 +1,102 @@
+//! Compares the trace of the Ricoh 2A03 core running nestest with the
+//! nestest.log reference, line by line.
+//!
+//! The ROM and its log are not distributed with the crate. Download
+//! `nestest.nes` and `nestest.log` from https://www.qmtpro.com/~nes/misc/
+//! into `tests/roms`, or into the directory named by `NESTEST_DIR`, then run
+//! `cargo test --test nestest -- --ignored`. The test fails if they are
+//! missing.
+//!
+//! nestest runs without a PPU when started at $C000, and reports errors in
+//! $0002 and $0003. Its log covers the official and the undocumented
+//! opcodes.
+
+use mos6502::{Bus, Cpu, NestestTracer, RunUntil, StopReason, Variant};
+use std::cell::RefCell;
+use std::io::Write;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// Size of the iNES header before the program ROM.
+const HEADER_SIZE: usize = 16;
+/// Entry point of the automated mode, which needs no PPU.
+const START: u16 = 0xC000;
+
+/// NES memory map with only the RAM and the program ROM. The APU and I/O
+/// registers read as $FF, as in nestest.log.
+struct Nes {
+    ram: [u8; 0x0800],
+    prg: Vec<u8>,
+}
+
+impl Bus for Nes {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.peek(addr)
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        if addr < 0x2000 {
+            self.ram[addr as usize & 0x07FF] = value;
+        }
+    }
+
+    fn peek(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x1FFF => self.ram[addr as usize & 0x07FF],
+            0x8000..=0xFFFF => self.prg[(addr as usize - 0x8000) % self.prg.len()],
+            _ => 0xFF,
+        }
+    }
+}
+
+/// Writer whose output stays readable once it is given to the tracer.
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn read_file(name: &str) -> Vec<u8> {
+    let dir = std::env::var_os("NESTEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("roms"));
+    let path = dir.join(name);
+    std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {}, see tests/nestest.rs", path.display(), e))
+}
+
+#[test]
+#[ignore]
+fn test_nestest() {
+    let rom = read_file("nestest.nes");
+    let log = String::from_utf8(read_file("nestest.log")).expect("nestest.log is not text");
+    let expected: Vec<&str> = log.lines().collect();
+    let prg_size = rom[4] as usize * 0x4000;
+    let mut bus = Nes { ram: [0; 0x0800], prg: rom[HEADER_SIZE..HEADER_SIZE + prg_size].to_vec() };
+
+    let output = SharedBuffer::default();
+    let mut cpu = Cpu::with_variant(Variant::Ricoh2A03);
+    cpu.set_tracer(NestestTracer::new(output.clone()));
+    cpu.reset(&mut bus);
+    // The power up state of nestest.log.
+    cpu.set_program_counter(START);
+    cpu.set_stack_pointer(0xFD);
+    cpu.set_status(0x24);
+
+    let result = cpu.run(&mut bus, &RunUntil::instructions(expected.len() as u64));
+    assert_eq!(result, Ok(StopReason::InstructionLimit));
+    cpu.take_tracer().unwrap().finish().unwrap();
+
+    let trace = String::from_utf8(output.0.borrow().clone()).unwrap();
+    for (number, (found, wanted)) in trace.lines().zip(expected.iter()).enumerate() {
+        assert_eq!(found, *wanted, "line {} of nestest.log differs", number + 1);
+    }
+    assert_eq!(trace.lines().count(), expected.len());
+    assert_eq!((bus.ram[0x0002], bus.ram[0x0003]), (0x00, 0x00), "nestest reported errors");
+}
@@ -0,0 +1,250 @@
+//! Checks every opcode against the SingleStepTests corpus of Tom Harte.
+//!
+//! The corpus is not distributed with the crate. Clone
+//! https://github.com/SingleStepTests/65x02 into `tests/single_step`, or
+//! point `SINGLE_STEP_TESTS_DIR` to a checkout, then run
+//! `cargo test --release --test single_step_tests -- --ignored`. Each set of
+//! tests is read from `<set>/v1/<opcode>.json`, and a missing set or opcode
+//! fails the test. `SINGLE_STEP_TESTS_LIMIT` caps the number of tests run
+//! per opcode.
+//!
+//! Every test runs a single step in cycle accurate mode, then compares the
+//! registers, the memory, the cycle count and the list of bus accesses. A
+//! matrix of the results per opcode is printed, and the test fails if an
+//! opcode fails, except for the ones with unstable results on the NMOS
+//! chips, and the JAM opcodes, which halt the processor.
+
+use mos6502::{Bus, Cpu, ExecutionMode, Variant};
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// Opcodes whose results depend on the chip and its temperature: ANE, LXA,
+/// SHA, SHX, SHY and TAS.
+const UNSTABLE_OPCODES: [u8; 7] = [0x8B, 0xAB, 0x93, 0x9F, 0x9E, 0x9C, 0x9B];
+/// Opcodes halting the NMOS processor.
+const JAM_OPCODES: [u8; 12] = [0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2];
+
+/// Bus access, as listed in the `cycles` of a test.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Access {
+    addr: u16,
+    value: u8,
+    kind: &'static str,
+}
+
+/// 64K of RAM logging every access.
+struct RecordingBus {
+    mem: Vec<u8>,
+    accesses: Vec<Access>,
+}
+
+impl Bus for RecordingBus {
+    fn read(&mut self, addr: u16) -> u8 {
+        let value = self.mem[addr as usize];
+        self.accesses.push(Access { addr, value, kind: "read" });
+        value
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        self.mem[addr as usize] = value;
+        self.accesses.push(Access { addr, value, kind: "write" });
+    }
+
+    fn peek(&mut self, addr: u16) -> u8 {
+        self.mem[addr as usize]
+    }
+}
+
+/// Outcome of the tests of an opcode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Outcome {
+    Missing,
+    Passed(usize),
+    /// Registers, memory or cycle count differ; the first failure.
+    StateFailed(String),
+    /// Only the bus accesses differ; the first failure.
+    BusFailed(String),
+}
+
+fn field(state: &Value, name: &str) -> u64 {
+    state[name].as_u64().unwrap_or_else(|| panic!("missing field {}", name))
+}
+
+fn ram(state: &Value) -> Vec<(u16, u8)> {
+    state["ram"].as_array().expect("missing ram").iter()
+        .map(|entry| (entry[0].as_u64().unwrap() as u16, entry[1].as_u64().unwrap() as u8))
+        .collect()
+}
+
+///
+/// Runs a test, returning `Err` with the outcome if it fails.
+///
+fn run_test(test: &Value, variant: Variant, bus: &mut RecordingBus) -> Result<(), Outcome> {
+    let name = test["name"].as_str().unwrap_or("?");
+    let initial = &test["initial"];
+    let expected = &test["final"];
+
+    let mut cpu = Cpu::with_variant(variant);
+    cpu.set_execution_mode(ExecutionMode::CycleAccurate);
+    cpu.set_program_counter(field(initial, "pc") as u16);
+    cpu.set_stack_pointer(field(initial, "s") as u8);
+    cpu.set_accumulator(field(initial, "a") as u8);
+    cpu.set_x_index(field(initial, "x") as u8);
+    cpu.set_y_index(field(initial, "y") as u8);
+    cpu.set_status(field(initial, "p") as u8);
+    let initial_ram = ram(initial);
+    for &(addr, value) in &initial_ram {
+        bus.mem[addr as usize] = value;
+    }
+    bus.accesses.clear();
+
+    let result = cpu.single_step(bus);
+
+    let mut errors = Vec::new();
+    let registers = [
+        ("pc", cpu.program_counter() as u64, field(expected, "pc")),
+        ("s", cpu.stack_pointer() as u64, field(expected, "s")),
+        ("a", cpu.accumulator() as u64, field(expected, "a")),
+        ("x", cpu.x_index() as u64, field(expected, "x")),
+        ("y", cpu.y_index() as u64, field(expected, "y")),
+        // Bits 4 and 5 don't exist in the register.
+        ("p", (cpu.status() & 0xCF) as u64, field(expected, "p") & 0xCF),
+    ];
+    for (register, found, wanted) in registers.iter() {
+        if found != wanted {
+            errors.push(format!("{} is {:02X}, expected {:02X}", register, found, wanted));
+        }
+    }
+    let final_ram = ram(expected);
+    for &(addr, value) in &final_ram {
+        if bus.mem[addr as usize] != value {
+            errors.push(format!("${:04X} is {:02X}, expected {:02X}", addr, bus.mem[addr as usize], value));
+        }
+    }
+    let cycles: Vec<Access> = test["cycles"].as_array().expect("missing cycles").iter()
+        .map(|cycle| Access {
+            addr: cycle[0].as_u64().unwrap() as u16,
+            value: cycle[1].as_u64().unwrap() as u8,
+            kind: if cycle[2].as_str() == Some("write") { "write" } else { "read" },
+        })
+        .collect();
+    match result {
+        Ok(count) if count as usize != cycles.len() => {
+            errors.push(format!("took {} cycles, expected {}", count, cycles.len()));
+        }
+        Ok(_) => {}
+        Err(error) => errors.push(error.to_string()),
+    }
+
+    // Clear the memory for the next test.
+    for &(addr, _) in initial_ram.iter().chain(final_ram.iter()) {
+        bus.mem[addr as usize] = 0;
+    }
+    for access in &bus.accesses {
+        bus.mem[access.addr as usize] = 0;
+    }
+
+    if !errors.is_empty() {
+        return Err(Outcome::StateFailed(format!("{}: {}", name, errors.join(", "))));
+    }
+    if bus.accesses != cycles {
+        return Err(Outcome::BusFailed(format!("{}: bus accesses {:?}, expected {:?}", name, bus.accesses, cycles)));
+    }
+    Ok(())
+}
+
+fn run_opcode(path: &PathBuf, variant: Variant, limit: usize, bus: &mut RecordingBus) -> Outcome {
+    let json = match std::fs::read(path) {
+        Ok(json) => json,
+        Err(_) => return Outcome::Missing,
+    };
+    let tests: Value = serde_json::from_slice(&json)
+        .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    let tests = tests.as_array().unwrap_or_else(|| panic!("{}: not a list of tests", path.display()));
+    let mut passed = 0;
+    for test in tests.iter().take(limit) {
+        if let Err(outcome) = run_test(test, variant, bus) {
+            return outcome;
+        }
+        passed += 1;
+    }
+    Outcome::Passed(passed)
+}
+
+fn run_set(set: &str, variant: Variant) {
+    let root = std::env::var_os("SINGLE_STEP_TESTS_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("single_step"));
+    let dir = root.join(set).join("v1");
+    assert!(dir.is_dir(), "{} not found, see tests/single_step_tests.rs", dir.display());
+    let limit = std::env::var("SINGLE_STEP_TESTS_LIMIT").ok()
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(usize::MAX);
+
+    let mut bus = RecordingBus { mem: vec![0; 0x10000], accesses: Vec::new() };
+    let outcomes: Vec<Outcome> = (0..=0xFFu8)
+        .map(|opcode| run_opcode(&dir.join(format!("{:02x}.json", opcode)), variant, limit, &mut bus))
+        .collect();
+    let excused = |opcode: u8| {
+        !variant.is_cmos() && (UNSTABLE_OPCODES.contains(&opcode) || JAM_OPCODES.contains(&opcode))
+    };
+
+    // One cell per opcode: passed, failed registers or memory, failed bus
+    // accesses only, or no tests found. Excused failures are in lowercase.
+    // Missing tests are never excused.
+    println!("{} on {:?}", set, variant);
+    println!("    {}", (0..16).map(|low| format!(" x{:X}", low)).collect::<String>());
+    for high in 0..16 {
+        let row: String = (0..16).map(|low| {
+            let opcode = high * 16 + low;
+            let cell = match &outcomes[opcode as usize] {
+                Outcome::Passed(_) => ".",
+                Outcome::Missing => "-",
+                Outcome::StateFailed(_) if excused(opcode) => "s",
+                Outcome::StateFailed(_) => "S",
+                Outcome::BusFailed(_) if excused(opcode) => "b",
+                Outcome::BusFailed(_) => "B",
+            };
+            format!("  {}", cell)
+        }).collect();
+        println!(" {:X}x {}", high, row);
+    }
+
+    let mut failures = Vec::new();
+    for (opcode, outcome) in outcomes.iter().enumerate() {
+        match outcome {
+            Outcome::StateFailed(message) | Outcome::BusFailed(message) => {
+                println!("{:02X} {}", opcode, message);
+                if !excused(opcode as u8) {
+                    failures.push(format!("{:02X}", opcode));
+                }
+            }
+            Outcome::Missing => {
+                println!("{:02X} no tests found", opcode);
+                failures.push(format!("{:02X}", opcode));
+            }
+            Outcome::Passed(_) => {}
+        }
+    }
+    let passed: usize = outcomes.iter().map(|o| if let Outcome::Passed(count) = o { *count } else { 0 }).sum();
+    println!("{} tests passed", passed);
+    assert!(failures.is_empty(), "opcodes failing on {:?}: {}", variant, failures.join(" "));
+}
+
+#[test]
+#[ignore]
+fn test_nmos_6502() {
+    run_set("6502", Variant::Nmos6502Undocumented);
+}
+
+#[test]
+#[ignore]
+fn test_ricoh_2a03() {
+    run_set("nes6502", Variant::Ricoh2A03);
+}
+
+#[test]
+#[ignore]
+fn test_wdc_65c02() {
+    run_set("wdc65c02", Variant::Wdc65c02);
+}